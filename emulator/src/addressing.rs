@@ -1,8 +1,31 @@
+use std::cell::RefCell;
 use std::fmt::Debug;
+use std::rc::Rc;
 
 pub trait Addressable {
     fn read(&mut self, address: u16) -> u8;
     fn write(&mut self, address: u16, data: u8);
+
+    // What a read would return without its side effects, None for devices whose reads
+    // change their state (e.g. PPU and IO registers)
+    fn peek(&self, _address: u16) -> Option<u8> {
+        None
+    }
+}
+
+// Devices shared between several buses (e.g. the cartridge) or with the console itself
+impl<T: Addressable + ?Sized> Addressable for Rc<RefCell<T>> {
+    fn read(&mut self, address: u16) -> u8 {
+        self.borrow_mut().read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.borrow_mut().write(address, data);
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        self.borrow().peek(address)
+    }
 }
 
 pub struct AddressRange {
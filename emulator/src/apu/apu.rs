@@ -0,0 +1,374 @@
+use crate::addressing::Addressable;
+use crate::apu::envelope::Envelope;
+use crate::apu::filter::FilterChain;
+use crate::apu::frame_counter::{FrameCounter, FrameCounterClock};
+use crate::apu::length_counter::LengthCounter;
+use log::{debug, info};
+use std::fmt::Debug;
+
+// First register of each channel carries the length counter halt flag,
+// the fourth one loads the length counter
+const PULSE_1_CONTROL_ADDRESS: u16 = 0x4000;
+const PULSE_1_LENGTH_ADDRESS: u16 = 0x4003;
+const PULSE_2_CONTROL_ADDRESS: u16 = 0x4004;
+const PULSE_2_LENGTH_ADDRESS: u16 = 0x4007;
+const TRIANGLE_CONTROL_ADDRESS: u16 = 0x4008;
+const TRIANGLE_LENGTH_ADDRESS: u16 = 0x400B;
+const NOISE_CONTROL_ADDRESS: u16 = 0x400C;
+const NOISE_LENGTH_ADDRESS: u16 = 0x400F;
+const STATUS_ADDRESS: u16 = 0x4015;
+const FRAME_COUNTER_ADDRESS: u16 = 0x4017;
+
+const CHANNEL_ENABLE_MASK: u8 = 0b0001_1111;
+const DMC_ENABLE_FLAG: u8 = 0b0001_0000;
+const FRAME_INTERRUPT_FLAG: u8 = 0b0100_0000;
+const DMC_INTERRUPT_FLAG: u8 = 0b1000_0000;
+const LENGTH_HALT_FLAG: u8 = 0b0010_0000;
+// The triangle shares its halt flag with the linear counter control
+const TRIANGLE_LENGTH_HALT_FLAG: u8 = 0b1000_0000;
+
+// Indices into the length counters, the pulse and noise channels also index the envelopes
+const PULSE_1: usize = 0;
+const PULSE_2: usize = 1;
+const TRIANGLE: usize = 2;
+const NOISE: usize = 3;
+
+pub const DEFAULT_SAMPLE_RATE: f32 = 44100.0;
+
+pub struct Apu {
+    // DMC, noise, triangle, pulse 2, pulse 1 from bit 4 down to bit 0
+    channel_enables: u8,
+    frame_counter: FrameCounter,
+    // Last value written to 0x4017, replayed by the reset line
+    frame_counter_register: u8,
+    // Raised by the DMC at the end of a sample, which is not emulated yet
+    dmc_irq: bool,
+    // Pulse 1, pulse 2, triangle and noise
+    length_counters: [LengthCounter; 4],
+    // Pulse 1, pulse 2 and noise, the triangle has no envelope
+    envelopes: [Envelope; 3],
+    cycles: u64,
+    // Set while the emulator is paused, the mixer must output silence
+    muted: bool,
+    output_filter: FilterChain,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        info!("APU is initializing");
+        Self {
+            channel_enables: 0,
+            frame_counter: FrameCounter::new(),
+            frame_counter_register: 0,
+            dmc_irq: false,
+            length_counters: [LengthCounter::new(); 4],
+            envelopes: [Envelope::new(); 3],
+            cycles: 0,
+            muted: false,
+            output_filter: FilterChain::nes(DEFAULT_SAMPLE_RATE),
+        }
+    }
+
+    // Advances the APU by one CPU cycle
+    pub fn step(&mut self) {
+        let clock = self.frame_counter.step();
+        self.clock_units(clock);
+        self.cycles += 1;
+    }
+
+    pub fn tick_cpu_cycles(&mut self, cpu_cycles: u32) {
+        for _ in 0..cpu_cycles {
+            self.step();
+        }
+    }
+
+    // Envelopes run on every quarter frame, length counters on every half frame
+    fn clock_units(&mut self, clock: FrameCounterClock) {
+        if clock.quarter_frame {
+            self.envelopes.iter_mut().for_each(Envelope::clock);
+        }
+        if clock.half_frame {
+            self.length_counters
+                .iter_mut()
+                .for_each(LengthCounter::clock);
+        }
+    }
+
+    fn envelope_index(channel: usize) -> Option<usize> {
+        match channel {
+            PULSE_1 | PULSE_2 => Some(channel),
+            NOISE => Some(2),
+            _ => None,
+        }
+    }
+
+    fn write_control(&mut self, channel: usize, data: u8) {
+        let halt_flag = if channel == TRIANGLE {
+            TRIANGLE_LENGTH_HALT_FLAG
+        } else {
+            LENGTH_HALT_FLAG
+        };
+        self.length_counters[channel].set_halted(data & halt_flag != 0);
+        if let Some(envelope) = Self::envelope_index(channel) {
+            self.envelopes[envelope].write_control(data);
+        }
+    }
+
+    // A disabled channel ignores length loads
+    fn write_length(&mut self, channel: usize, data: u8) {
+        if self.channel_enables & (1 << channel) != 0 {
+            self.length_counters[channel].load(data >> 3);
+        }
+        if let Some(envelope) = Self::envelope_index(channel) {
+            self.envelopes[envelope].restart();
+        }
+    }
+
+    pub fn length_counter(&self, channel: usize) -> &LengthCounter {
+        &self.length_counters[channel]
+    }
+
+    // Level of the APU's IRQ line, held until the frame or DMC flag is acknowledged
+    pub fn irq(&self) -> bool {
+        self.frame_counter.irq_pending() || self.dmc_irq
+    }
+
+    pub fn frame_counter(&self) -> &FrameCounter {
+        &self.frame_counter
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn output_filter_mut(&mut self) -> &mut FilterChain {
+        &mut self.output_filter
+    }
+
+    // Turns a mixed sample into the one heard from the console's audio output
+    pub fn output_sample(&mut self, mixed: f32) -> f32 {
+        let sample = self.output_filter.process(mixed);
+        if self.muted {
+            0.0
+        } else {
+            sample
+        }
+    }
+
+    // Reset silences every channel and acts as if 0x4017 was written again
+    pub fn reset(&mut self) {
+        info!("APU is resetting");
+        self.write_status(0);
+        self.frame_counter.clear_irq();
+        self.frame_counter
+            .write(self.frame_counter_register, self.cycles);
+    }
+
+    // Disabling a channel silences it right away by clearing its length counter
+    fn write_status(&mut self, data: u8) {
+        self.channel_enables = data & CHANNEL_ENABLE_MASK;
+        for (channel, length_counter) in self.length_counters.iter_mut().enumerate() {
+            if self.channel_enables & (1 << channel) == 0 {
+                length_counter.clear();
+            }
+        }
+        self.dmc_irq = false;
+    }
+
+    // The DMC is not emulated yet, so it is reported as playing while enabled
+    fn read_status(&mut self) -> u8 {
+        let mut status = self.channel_enables & DMC_ENABLE_FLAG;
+        for (channel, length_counter) in self.length_counters.iter().enumerate() {
+            if length_counter.is_active() {
+                status |= 1 << channel;
+            }
+        }
+        if self.frame_counter.irq_pending() {
+            status |= FRAME_INTERRUPT_FLAG;
+        }
+        if self.dmc_irq {
+            status |= DMC_INTERRUPT_FLAG;
+        }
+        self.frame_counter.clear_irq();
+        status
+    }
+}
+
+impl Addressable for Apu {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            STATUS_ADDRESS => self.read_status(),
+            _ => {
+                debug!("Ignoring APU read at address {:#06X}", address);
+                0
+            }
+        }
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            PULSE_1_CONTROL_ADDRESS => self.write_control(PULSE_1, data),
+            PULSE_2_CONTROL_ADDRESS => self.write_control(PULSE_2, data),
+            TRIANGLE_CONTROL_ADDRESS => self.write_control(TRIANGLE, data),
+            NOISE_CONTROL_ADDRESS => self.write_control(NOISE, data),
+            PULSE_1_LENGTH_ADDRESS => self.write_length(PULSE_1, data),
+            PULSE_2_LENGTH_ADDRESS => self.write_length(PULSE_2, data),
+            TRIANGLE_LENGTH_ADDRESS => self.write_length(TRIANGLE, data),
+            NOISE_LENGTH_ADDRESS => self.write_length(NOISE, data),
+            STATUS_ADDRESS => self.write_status(data),
+            FRAME_COUNTER_ADDRESS => {
+                self.frame_counter_register = data;
+                self.frame_counter.write(data, self.cycles);
+            }
+            _ => debug!(
+                "Ignoring APU write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+}
+
+impl Debug for Apu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Apu")
+            .field("channel_enables", &self.channel_enables)
+            .field("frame_counter", &self.frame_counter)
+            .field("dmc_irq", &self.dmc_irq)
+            .field("length_counters", &self.length_counters)
+            .field("envelopes", &self.envelopes)
+            .field("cycles", &self.cycles)
+            .field("muted", &self.muted)
+            .field("output_filter", &self.output_filter)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_reports_active_length_counters() {
+        let mut apu = Apu::new();
+
+        apu.write(STATUS_ADDRESS, 0xFF);
+        // Only the DMC reports as playing until a length counter is loaded
+        assert_eq!(apu.read(STATUS_ADDRESS), DMC_ENABLE_FLAG);
+
+        apu.write(PULSE_2_LENGTH_ADDRESS, 0b0000_1000);
+        apu.write(NOISE_LENGTH_ADDRESS, 0b0000_1000);
+
+        assert_eq!(apu.read(STATUS_ADDRESS), DMC_ENABLE_FLAG | 0b1010);
+    }
+
+    #[test]
+    fn test_status_bit_clears_when_length_counter_runs_out() {
+        let mut apu = Apu::new();
+        apu.write(STATUS_ADDRESS, 0b0000_0001);
+        // Length index 3 loads 2, so two half frames silence the channel
+        apu.write(PULSE_1_LENGTH_ADDRESS, 0b0001_1000);
+        assert_eq!(apu.read(STATUS_ADDRESS), 0b0000_0001);
+
+        apu.tick_cpu_cycles(14913);
+        assert_eq!(apu.read(STATUS_ADDRESS), 0b0000_0001);
+
+        apu.tick_cpu_cycles(29829 - 14913);
+        assert_eq!(apu.read(STATUS_ADDRESS) & 0b0000_0001, 0);
+    }
+
+    #[test]
+    fn test_disabled_channel_ignores_length_loads() {
+        let mut apu = Apu::new();
+        apu.write(STATUS_ADDRESS, 0b0000_0001);
+        apu.write(PULSE_1_LENGTH_ADDRESS, 0b0000_1000);
+
+        apu.write(STATUS_ADDRESS, 0);
+        assert_eq!(apu.length_counter(PULSE_1).counter(), 0);
+
+        apu.write(PULSE_1_LENGTH_ADDRESS, 0b0000_1000);
+        assert_eq!(apu.length_counter(PULSE_1).counter(), 0);
+    }
+
+    #[test]
+    fn test_reset_disables_channels() {
+        let mut apu = Apu::new();
+        apu.write(STATUS_ADDRESS, CHANNEL_ENABLE_MASK);
+        apu.write(PULSE_1_LENGTH_ADDRESS, 0b0000_1000);
+
+        apu.reset();
+
+        assert_eq!(apu.read(STATUS_ADDRESS), 0);
+    }
+
+    #[test]
+    fn test_reset_restarts_frame_counter() {
+        let mut apu = Apu::new();
+        for _ in 0..100 {
+            apu.step();
+        }
+
+        apu.reset();
+        for _ in 0..4 {
+            apu.step();
+        }
+
+        assert!(apu.frame_counter.cycle() < 4);
+    }
+
+    #[test]
+    fn test_four_step_mode_sets_frame_irq_on_last_step() {
+        let mut apu = Apu::new();
+
+        apu.tick_cpu_cycles(29828);
+        assert_eq!(apu.read(STATUS_ADDRESS) & FRAME_INTERRUPT_FLAG, 0);
+
+        apu.step();
+        assert_eq!(
+            apu.read(STATUS_ADDRESS) & FRAME_INTERRUPT_FLAG,
+            FRAME_INTERRUPT_FLAG
+        );
+        // Reading the status acknowledges the interrupt
+        assert_eq!(apu.read(STATUS_ADDRESS) & FRAME_INTERRUPT_FLAG, 0);
+    }
+
+    #[test]
+    fn test_five_step_mode_never_sets_frame_irq() {
+        let mut apu = Apu::new();
+        apu.write(FRAME_COUNTER_ADDRESS, 0x80);
+
+        apu.tick_cpu_cycles(2 * 37281);
+
+        assert_eq!(apu.read(STATUS_ADDRESS) & FRAME_INTERRUPT_FLAG, 0);
+    }
+
+    #[test]
+    fn test_half_frame_clocks_length_counters() {
+        let mut apu = Apu::new();
+        apu.write(STATUS_ADDRESS, CHANNEL_ENABLE_MASK);
+        // Length index 1 loads 254
+        apu.write(PULSE_1_LENGTH_ADDRESS, 0b0000_1000);
+        apu.write(TRIANGLE_CONTROL_ADDRESS, TRIANGLE_LENGTH_HALT_FLAG);
+        apu.write(TRIANGLE_LENGTH_ADDRESS, 0b0000_1000);
+
+        // The first quarter frame leaves the length counters alone
+        apu.tick_cpu_cycles(7457);
+        assert_eq!(apu.length_counter(PULSE_1).counter(), 254);
+
+        apu.tick_cpu_cycles(14913 - 7457);
+        assert_eq!(apu.length_counter(PULSE_1).counter(), 253);
+        assert_eq!(apu.length_counter(TRIANGLE).counter(), 254);
+
+        apu.tick_cpu_cycles(29829 - 14913);
+        assert_eq!(apu.length_counter(PULSE_1).counter(), 252);
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt::Debug;
+
+const MAX_DECAY_LEVEL: u8 = 15;
+const VOLUME_MASK: u8 = 0x0F;
+const CONSTANT_VOLUME_FLAG: u8 = 0b0001_0000;
+const LOOP_FLAG: u8 = 0b0010_0000;
+
+// Volume of the pulse and noise channels, clocked on quarter frames
+// See https://www.nesdev.org/wiki/APU_Envelope
+#[derive(Clone, Copy, Default)]
+pub struct Envelope {
+    start: bool,
+    divider: u8,
+    decay_level: u8,
+    // Divider period and constant volume share the low four bits of the register
+    volume: u8,
+    constant_volume: bool,
+    looping: bool,
+}
+
+impl Envelope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Takes the channel's first register, the loop flag doubles as the length counter halt
+    pub fn write_control(&mut self, data: u8) {
+        self.volume = data & VOLUME_MASK;
+        self.constant_volume = data & CONSTANT_VOLUME_FLAG != 0;
+        self.looping = data & LOOP_FLAG != 0;
+    }
+
+    // Writing the channel's fourth register restarts the decay
+    pub fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay_level = MAX_DECAY_LEVEL;
+            self.divider = self.volume;
+            return;
+        }
+
+        if self.divider > 0 {
+            self.divider -= 1;
+            return;
+        }
+        self.divider = self.volume;
+        if self.decay_level > 0 {
+            self.decay_level -= 1;
+        } else if self.looping {
+            self.decay_level = MAX_DECAY_LEVEL;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay_level
+        }
+    }
+}
+
+impl Debug for Envelope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Envelope")
+            .field("start", &self.start)
+            .field("divider", &self.divider)
+            .field("decay_level", &self.decay_level)
+            .field("volume", &self.volume)
+            .field("constant_volume", &self.constant_volume)
+            .field("looping", &self.looping)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restart_decays_from_fifteen() {
+        let mut envelope = Envelope::new();
+        // Divider period 1, every second clock lowers the level
+        envelope.write_control(0x01);
+        envelope.restart();
+
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+        envelope.clock();
+        envelope.clock();
+        assert_eq!(envelope.output(), 14);
+    }
+
+    #[test]
+    fn test_decay_stops_at_zero_unless_looping() {
+        let mut envelope = Envelope::new();
+        envelope.write_control(0x00);
+        envelope.restart();
+        for _ in 0..20 {
+            envelope.clock();
+        }
+        assert_eq!(envelope.output(), 0);
+
+        envelope.write_control(LOOP_FLAG);
+        envelope.clock();
+        assert_eq!(envelope.output(), 15);
+    }
+
+    #[test]
+    fn test_constant_volume() {
+        let mut envelope = Envelope::new();
+        envelope.write_control(CONSTANT_VOLUME_FLAG | 0x07);
+        envelope.restart();
+        envelope.clock();
+
+        assert_eq!(envelope.output(), 7);
+    }
+}
@@ -0,0 +1,162 @@
+use std::f32::consts::PI;
+use std::fmt::Debug;
+
+// Cutoffs of the analog output stage, see https://www.nesdev.org/wiki/APU_Mixer
+pub const NES_FIRST_HIGH_PASS_CUTOFF: f32 = 90.0;
+pub const NES_SECOND_HIGH_PASS_CUTOFF: f32 = 440.0;
+pub const NES_LOW_PASS_CUTOFF: f32 = 14000.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterKind {
+    HighPass,
+    LowPass,
+}
+
+// First order RC filter working on one sample at a time
+#[derive(Clone, Copy, Debug)]
+struct Filter {
+    kind: FilterKind,
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl Filter {
+    fn new(kind: FilterKind, cutoff: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate;
+        let alpha = match kind {
+            FilterKind::HighPass => rc / (rc + dt),
+            FilterKind::LowPass => dt / (rc + dt),
+        };
+
+        Self {
+            kind,
+            alpha,
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = match self.kind {
+            FilterKind::HighPass => {
+                self.alpha * (self.previous_output + input - self.previous_input)
+            }
+            FilterKind::LowPass => {
+                self.previous_output + self.alpha * (input - self.previous_output)
+            }
+        };
+        self.previous_input = input;
+        self.previous_output = output;
+        output
+    }
+}
+
+// Filters applied in order to the mixed APU output
+pub struct FilterChain {
+    sample_rate: f32,
+    filters: Vec<Filter>,
+    enabled: bool,
+}
+
+impl FilterChain {
+    pub fn new(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            filters: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    // Two high-pass filters and a low-pass one, like the console's output stage
+    pub fn nes(sample_rate: f32) -> Self {
+        Self::new(sample_rate)
+            .with_filter(FilterKind::HighPass, NES_FIRST_HIGH_PASS_CUTOFF)
+            .with_filter(FilterKind::HighPass, NES_SECOND_HIGH_PASS_CUTOFF)
+            .with_filter(FilterKind::LowPass, NES_LOW_PASS_CUTOFF)
+    }
+
+    pub fn sample_rate(&self) -> f32 {
+        self.sample_rate
+    }
+
+    pub fn with_filter(mut self, kind: FilterKind, cutoff: f32) -> Self {
+        self.filters
+            .push(Filter::new(kind, cutoff, self.sample_rate));
+        self
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // A disabled chain passes samples through untouched
+    pub fn process(&mut self, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+
+        self.filters
+            .iter_mut()
+            .fold(sample, |sample, filter| filter.process(sample))
+    }
+}
+
+impl Debug for FilterChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilterChain")
+            .field("sample_rate", &self.sample_rate)
+            .field("filters", &self.filters.len())
+            .field("enabled", &self.enabled)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 44100.0;
+    const DC_OFFSET: f32 = 0.5;
+
+    #[test]
+    fn test_high_pass_filters_remove_dc_offset() {
+        let mut chain = FilterChain::nes(SAMPLE_RATE);
+
+        let first = chain.process(DC_OFFSET);
+        let mut last = first;
+        for _ in 0..SAMPLE_RATE as usize / 10 {
+            last = chain.process(DC_OFFSET);
+        }
+
+        assert!(first.abs() > 0.01);
+        assert!(last.abs() < 0.001, "DC offset left: {}", last);
+    }
+
+    #[test]
+    fn test_disabled_chain_passes_samples_through() {
+        let mut chain = FilterChain::nes(SAMPLE_RATE);
+        chain.set_enabled(false);
+
+        for _ in 0..1000 {
+            assert_eq!(chain.process(DC_OFFSET), DC_OFFSET);
+        }
+    }
+
+    #[test]
+    fn test_low_pass_filter_settles_on_dc_offset() {
+        let mut chain = FilterChain::new(SAMPLE_RATE).with_filter(FilterKind::LowPass, 1000.0);
+
+        let mut last = 0.0;
+        for _ in 0..1000 {
+            last = chain.process(DC_OFFSET);
+        }
+
+        assert!((last - DC_OFFSET).abs() < 0.001);
+    }
+}
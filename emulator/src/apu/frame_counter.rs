@@ -0,0 +1,306 @@
+use std::fmt::Debug;
+
+// Sequencer steps in CPU cycles, see https://www.nesdev.org/wiki/APU_Frame_Counter
+const FIRST_QUARTER_FRAME_CYCLE: u32 = 7457;
+const FIRST_HALF_FRAME_CYCLE: u32 = 14913;
+const THIRD_QUARTER_FRAME_CYCLE: u32 = 22371;
+const FOUR_STEP_LAST_CYCLE: u32 = 29829;
+const FIVE_STEP_LAST_CYCLE: u32 = 37281;
+
+const MODE_FLAG: u8 = 0b1000_0000;
+const IRQ_INHIBIT_FLAG: u8 = 0b0100_0000;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameCounterMode {
+    FourStep,
+    FiveStep,
+}
+
+// Which units the sequencer clocked during a step
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct FrameCounterClock {
+    pub quarter_frame: bool,
+    pub half_frame: bool,
+}
+
+impl FrameCounterClock {
+    fn quarter_frame() -> Self {
+        Self {
+            quarter_frame: true,
+            half_frame: false,
+        }
+    }
+
+    fn half_frame() -> Self {
+        Self {
+            quarter_frame: true,
+            half_frame: true,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            quarter_frame: self.quarter_frame || other.quarter_frame,
+            half_frame: self.half_frame || other.half_frame,
+        }
+    }
+}
+
+// A write to 0x4017 takes effect 3 CPU cycles later when it lands during an APU cycle
+// and 4 CPU cycles later when it lands between two of them
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FrameCounterWriteDelay {
+    pub odd_cycle: u8,
+    pub even_cycle: u8,
+}
+
+impl Default for FrameCounterWriteDelay {
+    fn default() -> Self {
+        Self {
+            odd_cycle: 3,
+            even_cycle: 4,
+        }
+    }
+}
+
+impl FrameCounterWriteDelay {
+    fn for_cycle(&self, cpu_cycle: u64) -> u8 {
+        if cpu_cycle % 2 == 1 {
+            self.odd_cycle
+        } else {
+            self.even_cycle
+        }
+    }
+}
+
+pub struct FrameCounter {
+    mode: FrameCounterMode,
+    irq_inhibit: bool,
+    irq_flag: bool,
+    cycle: u32,
+    write_delay: FrameCounterWriteDelay,
+    pending_write: Option<(u8, u8)>,
+}
+
+impl Default for FrameCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameCounter {
+    pub fn new() -> Self {
+        Self {
+            mode: FrameCounterMode::FourStep,
+            irq_inhibit: false,
+            irq_flag: false,
+            cycle: 0,
+            write_delay: FrameCounterWriteDelay::default(),
+            pending_write: None,
+        }
+    }
+
+    pub fn with_write_delay(mut self, write_delay: FrameCounterWriteDelay) -> Self {
+        self.write_delay = write_delay;
+        self
+    }
+
+    pub fn mode(&self) -> FrameCounterMode {
+        self.mode
+    }
+
+    pub fn cycle(&self) -> u32 {
+        self.cycle
+    }
+
+    pub fn irq_pending(&self) -> bool {
+        self.irq_flag
+    }
+
+    pub fn clear_irq(&mut self) {
+        self.irq_flag = false;
+    }
+
+    // The IRQ inhibit flag applies immediately, the mode change and the sequencer
+    // reset wait for the parity dependent delay
+    pub fn write(&mut self, data: u8, cpu_cycle: u64) {
+        self.irq_inhibit = data & IRQ_INHIBIT_FLAG != 0;
+        if self.irq_inhibit {
+            self.irq_flag = false;
+        }
+
+        self.pending_write = Some((data, self.write_delay.for_cycle(cpu_cycle)));
+    }
+
+    // Advances the sequencer by one CPU cycle, it keeps running while a write waits
+    // out its delay
+    pub fn step(&mut self) -> FrameCounterClock {
+        let clock = self.advance();
+
+        match self.pending_write {
+            Some((data, delay)) if delay <= 1 => {
+                self.pending_write = None;
+                clock.merge(self.apply_write(data))
+            }
+            Some((data, delay)) => {
+                self.pending_write = Some((data, delay - 1));
+                clock
+            }
+            None => clock,
+        }
+    }
+
+    fn advance(&mut self) -> FrameCounterClock {
+        self.cycle += 1;
+
+        match (self.mode, self.cycle) {
+            (_, FIRST_QUARTER_FRAME_CYCLE) | (_, THIRD_QUARTER_FRAME_CYCLE) => {
+                FrameCounterClock::quarter_frame()
+            }
+            (_, FIRST_HALF_FRAME_CYCLE) => FrameCounterClock::half_frame(),
+            (FrameCounterMode::FourStep, FOUR_STEP_LAST_CYCLE) => {
+                self.cycle = 0;
+                if !self.irq_inhibit {
+                    self.irq_flag = true;
+                }
+                FrameCounterClock::half_frame()
+            }
+            (FrameCounterMode::FiveStep, FIVE_STEP_LAST_CYCLE) => {
+                self.cycle = 0;
+                FrameCounterClock::half_frame()
+            }
+            _ => FrameCounterClock::default(),
+        }
+    }
+
+    // Resetting in 5-step mode clocks the quarter and half frame units right away
+    fn apply_write(&mut self, data: u8) -> FrameCounterClock {
+        self.cycle = 0;
+
+        if data & MODE_FLAG != 0 {
+            self.mode = FrameCounterMode::FiveStep;
+            FrameCounterClock::half_frame()
+        } else {
+            self.mode = FrameCounterMode::FourStep;
+            FrameCounterClock::default()
+        }
+    }
+}
+
+impl Debug for FrameCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameCounter")
+            .field("mode", &self.mode)
+            .field("irq_inhibit", &self.irq_inhibit)
+            .field("irq_flag", &self.irq_flag)
+            .field("cycle", &self.cycle)
+            .field("pending_write", &self.pending_write)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_counter_at_cycle(cycles: u32) -> FrameCounter {
+        let mut frame_counter = FrameCounter::new();
+        for _ in 0..cycles {
+            frame_counter.step();
+        }
+        frame_counter
+    }
+
+    fn steps_until_reset(frame_counter: &mut FrameCounter) -> u32 {
+        let mut steps = 0;
+        while frame_counter.cycle() != 0 {
+            frame_counter.step();
+            steps += 1;
+        }
+        steps
+    }
+
+    #[test]
+    fn test_write_on_odd_cycle_resets_after_3_cycles() {
+        let mut frame_counter = frame_counter_at_cycle(100);
+
+        frame_counter.write(0x00, 101);
+
+        assert_eq!(steps_until_reset(&mut frame_counter), 3);
+    }
+
+    #[test]
+    fn test_write_on_even_cycle_resets_after_4_cycles() {
+        let mut frame_counter = frame_counter_at_cycle(100);
+
+        frame_counter.write(0x00, 100);
+
+        assert_eq!(steps_until_reset(&mut frame_counter), 4);
+    }
+
+    #[test]
+    fn test_sequencer_keeps_running_during_write_delay() {
+        let mut frame_counter = frame_counter_at_cycle(FIRST_QUARTER_FRAME_CYCLE - 3);
+
+        frame_counter.write(0x00, 1);
+        frame_counter.step();
+        frame_counter.step();
+        assert_eq!(frame_counter.cycle(), FIRST_QUARTER_FRAME_CYCLE - 1);
+
+        // The quarter frame lands on the cycle the write takes effect
+        assert_eq!(frame_counter.step(), FrameCounterClock::quarter_frame());
+        assert_eq!(frame_counter.cycle(), 0);
+    }
+
+    #[test]
+    fn test_configured_write_delay() {
+        let mut frame_counter = FrameCounter::new().with_write_delay(FrameCounterWriteDelay {
+            odd_cycle: 2,
+            even_cycle: 5,
+        });
+        frame_counter.step();
+
+        frame_counter.write(0x00, 7);
+        assert_eq!(steps_until_reset(&mut frame_counter), 2);
+
+        frame_counter.step();
+        frame_counter.write(0x00, 8);
+        assert_eq!(steps_until_reset(&mut frame_counter), 5);
+    }
+
+    #[test]
+    fn test_mode_changes_after_the_delay() {
+        let mut frame_counter = frame_counter_at_cycle(100);
+
+        frame_counter.write(MODE_FLAG, 101);
+        frame_counter.step();
+        frame_counter.step();
+
+        assert_eq!(frame_counter.mode(), FrameCounterMode::FourStep);
+        assert_eq!(frame_counter.step(), FrameCounterClock::half_frame());
+        assert_eq!(frame_counter.mode(), FrameCounterMode::FiveStep);
+    }
+
+    #[test]
+    fn test_four_step_sequence() {
+        let mut frame_counter = frame_counter_at_cycle(FIRST_QUARTER_FRAME_CYCLE - 1);
+
+        assert_eq!(frame_counter.step(), FrameCounterClock::quarter_frame());
+
+        let mut frame_counter = frame_counter_at_cycle(FOUR_STEP_LAST_CYCLE - 1);
+
+        assert_eq!(frame_counter.step(), FrameCounterClock::half_frame());
+        assert!(frame_counter.irq_pending());
+        assert_eq!(frame_counter.cycle(), 0);
+    }
+
+    #[test]
+    fn test_irq_inhibit_clears_irq() {
+        let mut frame_counter = frame_counter_at_cycle(FOUR_STEP_LAST_CYCLE);
+        assert!(frame_counter.irq_pending());
+
+        frame_counter.write(IRQ_INHIBIT_FLAG, 0);
+
+        assert!(!frame_counter.irq_pending());
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt::Debug;
+
+// Lengths loaded by the top five bits of a channel's fourth register,
+// see https://www.nesdev.org/wiki/APU_Length_Counter
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, 12, 16, 24, 18, 48, 20, 96, 22,
+    192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+// Silences a channel once it counts down to zero, clocked on half frames
+#[derive(Clone, Copy, Default)]
+pub struct LengthCounter {
+    counter: u8,
+    halted: bool,
+}
+
+impl LengthCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Index is the value of bits 3-7 of the register write
+    pub fn load(&mut self, index: u8) {
+        self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    pub fn clear(&mut self) {
+        self.counter = 0;
+    }
+
+    pub fn clock(&mut self) {
+        if !self.halted && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn counter(&self) -> u8 {
+        self.counter
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+impl Debug for LengthCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LengthCounter")
+            .field("counter", &self.counter)
+            .field("halted", &self.halted)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_uses_length_table() {
+        let mut length_counter = LengthCounter::new();
+
+        length_counter.load(0b00001);
+        assert_eq!(length_counter.counter(), 254);
+
+        length_counter.load(0b11111);
+        assert_eq!(length_counter.counter(), 30);
+    }
+
+    #[test]
+    fn test_clock_counts_down_to_zero() {
+        let mut length_counter = LengthCounter::new();
+        // Length 2
+        length_counter.load(0b00011);
+
+        length_counter.clock();
+        assert!(length_counter.is_active());
+        length_counter.clock();
+        assert!(!length_counter.is_active());
+        length_counter.clock();
+        assert_eq!(length_counter.counter(), 0);
+    }
+
+    #[test]
+    fn test_halted_counter_keeps_its_value() {
+        let mut length_counter = LengthCounter::new();
+        length_counter.load(0b00011);
+        length_counter.set_halted(true);
+
+        length_counter.clock();
+
+        assert_eq!(length_counter.counter(), 2);
+    }
+}
@@ -0,0 +1,6 @@
+#[allow(clippy::module_inception)]
+pub mod apu;
+pub mod envelope;
+pub mod filter;
+pub mod frame_counter;
+pub mod length_counter;
@@ -0,0 +1,131 @@
+use crate::apu::apu::DEFAULT_SAMPLE_RATE;
+use crate::cartridge::cartridge::Cartridge;
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+use crate::cpu::cpu::CpuVariant;
+use crate::memory::RamInit;
+use crate::nes::{Nes, DEFAULT_CYCLE_BUDGET};
+
+// Collects the host's settings in one place before the console is put together
+pub struct EmulatorBuilder {
+    cartridge: Cartridge,
+    // None keeps the region from the cartridge header
+    region: Option<TimingMode>,
+    ram_init: RamInit,
+    cpu_variant: CpuVariant,
+    sample_rate: f32,
+    cycle_budget: u64,
+    dmc_controller_conflict: bool,
+    accurate_sprite_overflow: bool,
+}
+
+impl EmulatorBuilder {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            region: None,
+            ram_init: RamInit::Zeroed,
+            cpu_variant: CpuVariant::default(),
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            cycle_budget: DEFAULT_CYCLE_BUDGET,
+            dmc_controller_conflict: false,
+            accurate_sprite_overflow: false,
+        }
+    }
+
+    pub fn region(mut self, region: TimingMode) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    pub fn ram_init(mut self, ram_init: RamInit) -> Self {
+        self.ram_init = ram_init;
+        self
+    }
+
+    pub fn cpu_variant(mut self, cpu_variant: CpuVariant) -> Self {
+        self.cpu_variant = cpu_variant;
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: f32) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    pub fn cycle_budget(mut self, cycle_budget: u64) -> Self {
+        self.cycle_budget = cycle_budget;
+        self
+    }
+
+    pub fn dmc_controller_conflict(mut self, enabled: bool) -> Self {
+        self.dmc_controller_conflict = enabled;
+        self
+    }
+
+    pub fn accurate_sprite_overflow(mut self, enabled: bool) -> Self {
+        self.accurate_sprite_overflow = enabled;
+        self
+    }
+
+    pub fn build(self) -> Nes {
+        let mut nes = Nes::with_ram_init(self.cartridge, self.ram_init);
+        if let Some(region) = self.region {
+            nes.set_region(region);
+        }
+        nes.set_cpu_variant(self.cpu_variant);
+        nes.set_sample_rate(self.sample_rate);
+        nes.set_cycle_budget(self.cycle_budget);
+        nes.set_dmc_controller_conflict(self.dmc_controller_conflict);
+        nes.set_accurate_sprite_overflow(self.accurate_sprite_overflow);
+        nes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::common::consts::PRG_UNIT_SIZE;
+    use crate::cartridge::common::enums::mirroring::Mirroring;
+    use crate::cartridge::mappers::nrom::Nrom;
+    use crate::cartridge::registers::prg_rom::PrgRom;
+    use crate::ppu::region::Region;
+
+    fn cartridge() -> Cartridge {
+        let prg = PrgRom::new_with_data(vec![0; PRG_UNIT_SIZE as usize]);
+        Cartridge::new(Box::new(Nrom::new(prg, None, Mirroring::Horizontal)))
+    }
+
+    #[test]
+    fn test_builder_defaults_match_nes_new() {
+        let mut nes = EmulatorBuilder::new(cartridge()).build();
+
+        assert_eq!(nes.region(), TimingMode::Ntsc);
+        assert_eq!(nes.ppu().borrow().region(), Region::Ntsc);
+        assert_eq!(nes.cpu().variant(), CpuVariant::Ricoh2A03);
+        assert_eq!(nes.cpu_read(0x0000), 0x00);
+        assert_eq!(
+            nes.apu().borrow_mut().output_filter_mut().sample_rate(),
+            DEFAULT_SAMPLE_RATE
+        );
+    }
+
+    #[test]
+    fn test_builder_settings_reach_the_console() {
+        let mut nes = EmulatorBuilder::new(cartridge())
+            .region(TimingMode::Pal)
+            .ram_init(RamInit::Filled(0xFF))
+            .cpu_variant(CpuVariant::Nmos6502)
+            .sample_rate(48000.0)
+            .build();
+
+        assert_eq!(nes.ppu().borrow().region(), Region::Pal);
+        assert_eq!(nes.cpu().variant(), CpuVariant::Nmos6502);
+        assert_eq!(nes.cpu_read(0x0000), 0xFF);
+        // Work RAM mirrors see the same contents
+        assert_eq!(nes.cpu_read(0x1FFF), 0xFF);
+        assert_eq!(
+            nes.apu().borrow_mut().output_filter_mut().sample_rate(),
+            48000.0
+        );
+    }
+}
@@ -1,40 +1,240 @@
 use crate::addressing::{AddressRange, Addressable};
+use crate::cartridge::cartridge::Cartridge;
 use crate::empty_device::EmptyDevice;
+use crate::io_registers::IoRegisters;
+use crate::memory::{Memory, Ram, RamInit};
+use crate::ppu::ppu::{OAM_SIZE, PPU};
 use log::{debug, info};
+use std::cell::RefCell;
 use std::fmt::Debug;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
 
 pub trait BusLike {
     fn read(&mut self, address: u16) -> u8;
     fn write(&mut self, address: u16, data: u8);
+
+    // Side effect free read for debugging tools, None where reading would disturb a device
+    fn peek(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    // Little endian word, e.g. an interrupt vector or an absolute operand
+    fn read_u16(&mut self, address: u16) -> u16 {
+        let low = self.read(address) as u16;
+        let high = self.read(address.wrapping_add(1)) as u16;
+        (high << 8) | low
+    }
+
+    // Pointer for the indirect zero page modes, 0xFF takes its high byte from 0x00
+    fn read_u16_zp_wrap(&mut self, address: u8) -> u16 {
+        let low = self.read(address as u16) as u16;
+        let high = self.read(address.wrapping_add(1) as u16) as u16;
+        (high << 8) | low
+    }
+
+    // Cycles stolen from the CPU (DMC fetches, OAM DMA) since the last call
+    fn take_stall_cycles(&mut self) -> u32 {
+        0
+    }
+
+    // Whether a device pulled the NMI line since the last call
+    fn take_nmi(&mut self) -> bool {
+        false
+    }
+
+    // Whether a device holds the (level triggered) IRQ line low
+    fn irq(&self) -> bool {
+        false
+    }
 }
 
 pub const ADDRESS_SPACE: usize = 0xFFFF + 1;
+const RESET_VECTOR_ADDRESS: u16 = 0xFFFC;
+pub const DMC_DMA_STALL_CYCLES: u32 = 4;
+// One extra cycle is taken when the DMA starts on an odd CPU cycle, which is not tracked here
+pub const OAM_DMA_STALL_CYCLES: u32 = 513;
+pub const OAM_DMA_ADDRESS: u16 = 0x4014;
+
+// CPU memory map, see https://www.nesdev.org/wiki/CPU_memory_map
+pub const WORK_RAM_START_ADDRESS: u16 = 0x0000;
+pub const WORK_RAM_END_ADDRESS: u16 = 0x1FFF;
+pub const PPU_REGISTERS_START_ADDRESS: u16 = 0x2000;
+pub const PPU_REGISTERS_END_ADDRESS: u16 = 0x3FFF;
+pub const IO_REGISTERS_START_ADDRESS: u16 = 0x4000;
+pub const IO_REGISTERS_END_ADDRESS: u16 = 0x401F;
+pub const CARTRIDGE_START_ADDRESS: u16 = 0x4020;
+pub const CARTRIDGE_END_ADDRESS: u16 = 0xFFFF;
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq)]
+pub enum BusError {
+    #[error("address {address:#06X} is already mapped to another device")]
+    Overlap { address: u16 },
+}
 
 pub struct Bus {
-    mappings: Vec<usize>,
+    // Index into devices for every address, None where nothing is mapped
+    mappings: Vec<Option<usize>>,
     devices: Vec<Box<dyn Addressable>>,
+    empty_device: EmptyDevice,
+    stall_cycles: u32,
+    // OAM DMA target, the PPU can't read CPU memory on its own
+    ppu: Option<Rc<RefCell<PPU>>>,
+    // Source of the APU frame and DMC interrupts
+    io_registers: Option<Rc<RefCell<IoRegisters>>>,
 }
 
 impl BusLike for Bus {
     fn read(&mut self, address: u16) -> u8 {
-        let device = self.devices[self.mappings[address as usize] as usize].as_mut();
-        device.read(address)
+        let data = match self.mappings[address as usize] {
+            Some(device) => self.devices[device].read(address),
+            None => self.empty_device.read(address),
+        };
+        self.empty_device.latch(data);
+        data
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        match self.mappings[address as usize] {
+            Some(device) => self.devices[device].peek(address),
+            None => self.empty_device.peek(address),
+        }
     }
 
     fn write(&mut self, address: u16, data: u8) {
-        let device = self.devices[self.mappings[address as usize] as usize].as_mut();
-        device.write(address, data);
+        if address == OAM_DMA_ADDRESS && self.ppu.is_some() {
+            self.dma_oam(data);
+            return;
+        }
+
+        self.empty_device.latch(data);
+        match self.mappings[address as usize] {
+            Some(device) => self.devices[device].write(address, data),
+            None => self.empty_device.write(address, data),
+        }
+    }
+
+    fn take_stall_cycles(&mut self) -> u32 {
+        std::mem::take(&mut self.stall_cycles)
+    }
+
+    fn take_nmi(&mut self) -> bool {
+        match &self.ppu {
+            Some(ppu) => ppu.borrow_mut().take_nmi(),
+            None => false,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        match &self.io_registers {
+            Some(io_registers) => io_registers.borrow().irq(),
+            None => false,
+        }
+    }
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Bus {
     pub fn new() -> Self {
         info!("New Bus has been created");
-        let empty_device = EmptyDevice {};
         Bus {
-            mappings: vec![0; ADDRESS_SPACE],
-            devices: vec![Box::new(empty_device)],
+            mappings: vec![None; ADDRESS_SPACE],
+            devices: Vec::new(),
+            empty_device: EmptyDevice::new(),
+            stall_cycles: 0,
+            ppu: None,
+            io_registers: None,
+        }
+    }
+
+    // Bus laid out like the CPU address space, with the 2KB work RAM mirrored up to 0x1FFF
+    pub fn new_cpu() -> Self {
+        Self::new_cpu_with_ram(RamInit::Zeroed)
+    }
+
+    pub fn new_cpu_with_ram(ram_init: RamInit) -> Self {
+        let mut bus = Bus::new();
+        bus.register(
+            Ram::with_init(ram_init),
+            AddressRange::new(WORK_RAM_START_ADDRESS, WORK_RAM_END_ADDRESS),
+        );
+        bus
+    }
+
+    // The whole address space as plain RAM, for CPU programs without a cartridge around them
+    pub fn new_flat() -> Self {
+        let mut bus = Bus::new();
+        bus.register(
+            Memory::new(ADDRESS_SPACE),
+            AddressRange::new(0x0000, CARTRIDGE_END_ADDRESS),
+        );
+        bus
+    }
+
+    // Copies a raw binary to origin and points the reset vector at it,
+    // the addresses written have to be backed by RAM, e.g. on a flat bus
+    pub fn load_flat(&mut self, program: &[u8], origin: u16) {
+        info!(
+            "Loading {} byte flat binary at {:#06X}",
+            program.len(),
+            origin
+        );
+        for (offset, byte) in program.iter().enumerate() {
+            self.write(origin.wrapping_add(offset as u16), *byte);
+        }
+        self.write(RESET_VECTOR_ADDRESS, origin as u8);
+        self.write(RESET_VECTOR_ADDRESS + 1, (origin >> 8) as u8);
+    }
+
+    // PPU registers are mirrored every 8 bytes, the PPU resolves the mirrors itself
+    pub fn with_ppu(mut self, ppu: Rc<RefCell<PPU>>) -> Self {
+        self.register(
+            ppu.clone(),
+            AddressRange::new(PPU_REGISTERS_START_ADDRESS, PPU_REGISTERS_END_ADDRESS),
+        );
+        self.ppu = Some(ppu);
+        self
+    }
+
+    pub fn with_io_registers(mut self, io_registers: Rc<RefCell<IoRegisters>>) -> Self {
+        self.register(
+            io_registers.clone(),
+            AddressRange::new(IO_REGISTERS_START_ADDRESS, IO_REGISTERS_END_ADDRESS),
+        );
+        self.io_registers = Some(io_registers);
+        self
+    }
+
+    pub fn with_cartridge(mut self, cartridge: Rc<RefCell<Cartridge>>) -> Self {
+        self.register(
+            cartridge,
+            AddressRange::new(CARTRIDGE_START_ADDRESS, CARTRIDGE_END_ADDRESS),
+        );
+        self
+    }
+
+    // Copies CPU page 0xNN00-0xNNFF into OAM, halting the CPU meanwhile
+    pub fn dma_oam(&mut self, page: u8) {
+        let start = (page as u16) << 8;
+        let mut data = [0; OAM_SIZE];
+        for (offset, byte) in data.iter_mut().enumerate() {
+            *byte = self.read(start + offset as u16);
         }
+
+        if let Some(ppu) = &self.ppu {
+            ppu.borrow_mut().write_oam_dma(&data);
+        }
+        self.add_stall_cycles(OAM_DMA_STALL_CYCLES);
+    }
+
+    pub fn add_stall_cycles(&mut self, cycles: u32) {
+        debug!("Bus queued {} stall cycles", cycles);
+        self.stall_cycles += cycles;
     }
 
     pub fn register<A: Addressable + Debug + 'static>(
@@ -49,6 +249,267 @@ impl Bus {
 
         self.devices.push(Box::new(addressable));
         self.mappings[address_range.start as usize..=address_range.end as usize]
-            .fill(self.devices.len() - 1);
+            .fill(Some(self.devices.len() - 1));
+    }
+
+    // Like register, but refuses to shadow a device that is already mapped
+    pub fn map(
+        &mut self,
+        range: RangeInclusive<u16>,
+        device: Box<dyn Addressable>,
+    ) -> Result<(), BusError> {
+        let addresses = *range.start() as usize..=*range.end() as usize;
+        if let Some(offset) = self.mappings[addresses.clone()]
+            .iter()
+            .position(Option::is_some)
+        {
+            return Err(BusError::Overlap {
+                address: range.start() + offset as u16,
+            });
+        }
+
+        debug!("Mapping device at address range: {:#06X?}", range);
+        self.devices.push(device);
+        self.mappings[addresses].fill(Some(self.devices.len() - 1));
+        Ok(())
+    }
+
+    // What unmapped addresses read as, open bus unless told otherwise
+    pub fn with_empty_device(mut self, empty_device: EmptyDevice) -> Self {
+        self.empty_device = empty_device;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::cpu::CPU;
+    use crate::ppu::vram::vram::VRAM;
+
+    fn setup_ppu() -> Rc<RefCell<PPU>> {
+        let mut ppu_bus = Bus::new();
+        ppu_bus.register(VRAM::new(), AddressRange::new(0x2000, 0x3EFF));
+        Rc::new(RefCell::new(PPU::new(ppu_bus)))
+    }
+
+    #[test]
+    fn load_flat_runs_from_origin_after_reset() {
+        // LDX #$41, INX
+        let program = [0xA2, 0x41, 0xE8];
+        let mut bus = Bus::new_flat();
+        bus.load_flat(&program, 0x0600);
+        assert_eq!(bus.read_u16(0xFFFC), 0x0600);
+        let mut cpu = CPU::new(bus);
+
+        cpu.reset();
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.x(), 0x42);
+        assert_eq!(cpu.pc(), 0x0603);
+    }
+
+    #[test]
+    fn map_dispatches_to_matching_device() {
+        let mut bus = Bus::new();
+        let mut first = Memory::new(0x0100);
+        first.write(0x0010, 0x11);
+        let mut second = Memory::new(0x0100);
+        second.write(0x0010, 0x22);
+
+        bus.map(0x1000..=0x10FF, Box::new(first)).unwrap();
+        bus.map(0x2000..=0x20FF, Box::new(second)).unwrap();
+
+        assert_eq!(bus.read(0x1010), 0x11);
+        assert_eq!(bus.read(0x2010), 0x22);
+        bus.write(0x2011, 0x33);
+        assert_eq!(bus.read(0x2011), 0x33);
+        // Between the two devices is open bus
+        assert_eq!(bus.read(0x1800), 0x33);
+    }
+
+    #[test]
+    fn map_rejects_overlapping_range() {
+        let mut bus = Bus::new_cpu();
+
+        let result = bus.map(0x1F00..=0x20FF, Box::new(Memory::new(0x0100)));
+
+        assert_eq!(result, Err(BusError::Overlap { address: 0x1F00 }));
+        // The rejected device is not mapped anywhere
+        assert!(bus
+            .map(0x2000..=0x20FF, Box::new(Memory::new(0x0100)))
+            .is_ok());
+    }
+
+    #[test]
+    fn unmapped_read_returns_open_bus() {
+        let mut bus = Bus::new_cpu();
+        bus.write(0x0000, 0x42);
+        bus.read(0x0000);
+
+        assert_eq!(bus.read(0x5000), 0x42);
+        // The last byte written is on the bus as well
+        bus.write(0x5000, 0x37);
+        assert_eq!(bus.read(0x6000), 0x37);
+    }
+
+    #[test]
+    fn unmapped_read_returns_configured_value() {
+        let mut bus = Bus::new_cpu().with_empty_device(EmptyDevice::with_value(0xFF));
+        bus.write(0x0000, 0x42);
+        bus.read(0x0000);
+
+        assert_eq!(bus.read(0x5000), 0xFF);
+    }
+
+    #[test]
+    fn read_u16_is_little_endian() {
+        let mut bus = Bus::new_cpu();
+        bus.write(0x00FF, 0x34);
+        bus.write(0x0100, 0x12);
+
+        assert_eq!(bus.read_u16(0x00FF), 0x1234);
+    }
+
+    #[test]
+    fn read_u16_zp_wrap_stays_in_zero_page() {
+        let mut bus = Bus::new_cpu();
+        bus.write(0x00FF, 0x34);
+        bus.write(0x0000, 0x12);
+        bus.write(0x0100, 0x56);
+
+        assert_eq!(bus.read_u16_zp_wrap(0xFF), 0x1234);
+        assert_eq!(bus.read_u16(0x00FF), 0x5634);
+    }
+
+    #[test]
+    fn cpu_bus_work_ram_is_mirrored() {
+        let mut bus = Bus::new_cpu();
+
+        bus.write(0x0000, 0x42);
+        assert_eq!(bus.read(0x0800), 0x42);
+        assert_eq!(bus.read(0x1000), 0x42);
+        assert_eq!(bus.read(0x1800), 0x42);
+
+        bus.write(0x1FFF, 0x37);
+        assert_eq!(bus.read(0x07FF), 0x37);
+    }
+
+    #[test]
+    fn cpu_bus_routes_ppu_register_writes_to_ppu() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu.clone());
+
+        bus.write(0x2006, 0x23);
+        bus.write(0x2006, 0x06);
+        bus.write(0x2007, 0x66);
+
+        ppu.borrow_mut().write(0x2006, 0x23);
+        ppu.borrow_mut().write(0x2006, 0x06);
+        ppu.borrow_mut().read(0x2007);
+
+        assert_eq!(ppu.borrow_mut().read(0x2007), 0x66);
+    }
+
+    #[test]
+    fn cpu_bus_routes_mirrored_ppu_registers_to_ppu() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu);
+
+        bus.write(0x3FFE, 0x21);
+        bus.write(0x200E, 0x37);
+        bus.write(0x2007, 0x99);
+
+        bus.write(0x2006, 0x21);
+        bus.write(0x2006, 0x37);
+        bus.read(0x2007);
+
+        assert_eq!(bus.read(0x3FFF), 0x99);
+    }
+
+    #[test]
+    fn cpu_bus_write_to_ppu_status_only_updates_io_latch() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu.clone());
+        ppu.borrow_mut().set_vblank(true);
+        ppu.borrow_mut().set_sprite_zero_hit(true);
+
+        bus.write(0x2002, 0x00);
+        bus.write(0x3FFA, 0x0C);
+
+        // Vblank and sprite 0 hit survive, the low bits show the latched 0x0C
+        assert_eq!(bus.read(0x2002), 0xC0 | 0x0C);
+        assert_eq!(bus.read(0x2001), 0xCC);
+    }
+
+    #[test]
+    fn cpu_bus_without_cartridge_reads_zero() {
+        let mut bus = Bus::new_cpu();
+        assert_eq!(bus.read(0x8000), 0);
+    }
+
+    #[test]
+    fn bus_has_no_stall_cycles_by_default() {
+        let mut bus = Bus::new();
+        assert_eq!(bus.take_stall_cycles(), 0);
+    }
+
+    #[test]
+    fn bus_stall_cycles_accumulate_and_are_consumed_once() {
+        let mut bus = Bus::new();
+
+        bus.add_stall_cycles(DMC_DMA_STALL_CYCLES);
+        bus.add_stall_cycles(DMC_DMA_STALL_CYCLES);
+
+        assert_eq!(bus.take_stall_cycles(), 2 * DMC_DMA_STALL_CYCLES);
+        assert_eq!(bus.take_stall_cycles(), 0);
+    }
+
+    #[test]
+    fn cpu_bus_oam_dma_copies_page_into_oam() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu.clone());
+        for offset in 0..=0xFF {
+            bus.write(0x0200 + offset, offset as u8);
+        }
+
+        bus.write(OAM_DMA_ADDRESS, 0x02);
+
+        let oam = *ppu.borrow().oam();
+        for (offset, data) in oam.iter().enumerate() {
+            assert_eq!(*data, offset as u8);
+        }
+        assert_eq!(bus.take_stall_cycles(), OAM_DMA_STALL_CYCLES);
+    }
+
+    #[test]
+    fn cpu_bus_oam_dma_starts_at_oam_address() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu.clone());
+        for offset in 0..=0xFF {
+            bus.write(0x0300 + offset, offset as u8);
+        }
+        bus.write(0x2003, 0x04);
+
+        bus.write(OAM_DMA_ADDRESS, 0x03);
+
+        let oam = *ppu.borrow().oam();
+        assert_eq!(oam[0x04], 0x00);
+        assert_eq!(oam[0xFF], 0xFB);
+        assert_eq!(oam[0x00], 0xFC);
+        assert_eq!(oam[0x03], 0xFF);
+    }
+
+    #[test]
+    fn cpu_bus_forwards_ppu_nmi_once() {
+        let ppu = setup_ppu();
+        let mut bus = Bus::new_cpu().with_ppu(ppu.clone());
+        bus.write(0x2000, 0x80);
+
+        ppu.borrow_mut().tick(241 * 341 + 1);
+
+        assert!(bus.take_nmi());
+        assert!(!bus.take_nmi());
     }
 }
@@ -1,21 +1,51 @@
+use crate::addressing::Addressable;
 use crate::cartridge::common::consts::NES_FILE_MAGIC_BYTES;
 use crate::cartridge::common::enums::errors::NesRomReadError;
+use crate::cartridge::common::enums::mirroring::Mirroring;
 use crate::cartridge::common::enums::nes::Nes;
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
 use crate::cartridge::common::traits::cartridge_data::CartridgeData;
 use crate::cartridge::common::traits::file_loadable::FileLoadable;
+use crate::cartridge::common::traits::mapper::{Mapper, MirroringListener};
 use crate::cartridge::formats::i_nes::Ines;
 use crate::cartridge::formats::nes_2::Nes2;
+use crate::cartridge::registers::chr_ram::ChrRam;
 use crate::cartridge::registers::chr_rom::ChrRom;
 use crate::cartridge::registers::prg_rom::PrgRom;
+use log::{info, warn};
+use std::cell::RefCell;
+use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const SAVE_FILE_EXTENSION: &str = "sav";
 
 pub struct Cartridge {
-    data: Box<dyn CartridgeData>,
+    mapper: Box<dyn Mapper>,
+    format: Nes,
+    // Where battery-backed PRG RAM is kept between sessions, None without a battery
+    save_path: Option<PathBuf>,
+    // Header details dropped together with the file format once the mapper is built
+    timing_mode: TimingMode,
+    vs_system: Option<u8>,
+    expansion_device: u8,
 }
 
 impl Cartridge {
+    pub fn new(mapper: Box<dyn Mapper>) -> Cartridge {
+        Cartridge {
+            mapper,
+            format: Nes::Ines,
+            save_path: None,
+            timing_mode: TimingMode::Ntsc,
+            vs_system: None,
+            expansion_device: 0,
+        }
+    }
+
     // prepare cartridge with FileLoadable trait
 
     pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Cartridge> {
@@ -23,19 +53,70 @@ impl Cartridge {
         let nes_type = Cartridge::nes_type_from_file(&mut file)?;
         // reset file pointer
         file.seek(SeekFrom::Start(0))?;
-        match nes_type {
+        let (mut cartridge, battery) = match nes_type {
             Nes::Ines => {
-                let ines = Ines::from_file(path)?;
-                Ok(Cartridge {
-                    data: Box::new(ines),
-                })
+                let ines = Ines::from_file(&path)?;
+                let battery = ines.has_battery();
+                (Cartridge::new(ines.into_mapper()), battery)
             }
             Nes::Nes2 => {
-                let nes2 = Nes2::from_file(path)?;
-                Ok(Cartridge {
-                    data: Box::new(nes2),
-                })
+                let nes2 = Nes2::from_file(&path)?;
+                let battery = nes2.has_battery();
+                let (timing_mode, vs_system, expansion_device) = (
+                    nes2.timing_mode(),
+                    nes2.vs_system(),
+                    nes2.expansion_device(),
+                );
+                let mut cartridge = Cartridge::new(nes2.into_mapper());
+                cartridge.timing_mode = timing_mode;
+                cartridge.vs_system = vs_system;
+                cartridge.expansion_device = expansion_device;
+                (cartridge, battery)
             }
+        };
+        cartridge.format = nes_type;
+        if battery {
+            let save_path = path.as_ref().with_extension(SAVE_FILE_EXTENSION);
+            if save_path.exists() {
+                cartridge.load_ram(&save_path)?;
+            }
+            cartridge.save_path = Some(save_path);
+        }
+
+        Ok(cartridge)
+    }
+
+    // File format the cartridge was loaded from
+    pub fn format(&self) -> Nes {
+        self.format
+    }
+
+    pub fn save_ram<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let prg_ram = self
+            .mapper
+            .prg_ram()
+            .ok_or(NesRomReadError::MissingPrgRam)?;
+        std::fs::write(&path, prg_ram.data())?;
+        info!("Saved PRG RAM to {}", path.as_ref().display());
+        Ok(())
+    }
+
+    pub fn load_ram<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<()> {
+        let data = std::fs::read(&path)?;
+        let prg_ram = self
+            .mapper
+            .prg_ram_mut()
+            .ok_or(NesRomReadError::MissingPrgRam)?;
+        prg_ram.load(&data);
+        info!("Loaded PRG RAM from {}", path.as_ref().display());
+        Ok(())
+    }
+
+    // Writes battery-backed PRG RAM next to the ROM, does nothing without a battery
+    pub fn flush_ram(&self) -> anyhow::Result<()> {
+        match &self.save_path {
+            Some(save_path) => self.save_ram(save_path),
+            None => Ok(()),
         }
     }
 
@@ -56,13 +137,112 @@ impl Cartridge {
     }
 }
 
+impl Cartridge {
+    pub fn ppu_read(&mut self, address: u16) -> u8 {
+        self.mapper.ppu_read(address)
+    }
+
+    pub fn ppu_write(&mut self, address: u16, data: u8) {
+        self.mapper.ppu_write(address, data)
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper.mirroring()
+    }
+
+    pub fn set_mirroring_listener(&mut self, listener: MirroringListener) {
+        self.mapper.set_mirroring_listener(listener)
+    }
+}
+
+// CPU side view of the cartridge, address translation is left to the mapper
+impl Addressable for Cartridge {
+    fn read(&mut self, address: u16) -> u8 {
+        self.mapper.cpu_read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.mapper.cpu_write(address, data)
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        Some(self.mapper.cpu_peek(address))
+    }
+}
+
+// PPU side view of the cartridge, mapped at 0x0000-0x1FFF for the pattern tables
+pub struct CartridgeChr {
+    cartridge: Rc<RefCell<Cartridge>>,
+}
+
+impl CartridgeChr {
+    pub fn new(cartridge: Rc<RefCell<Cartridge>>) -> CartridgeChr {
+        CartridgeChr { cartridge }
+    }
+}
+
+impl Addressable for CartridgeChr {
+    fn read(&mut self, address: u16) -> u8 {
+        self.cartridge.borrow_mut().ppu_read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.cartridge.borrow_mut().ppu_write(address, data)
+    }
+}
+
+impl Debug for CartridgeChr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CartridgeChr").finish()
+    }
+}
+
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush_ram() {
+            warn!("Failed to save battery-backed PRG RAM: {}", error);
+        }
+    }
+}
+
+impl Debug for Cartridge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cartridge")
+            .field("mapper", &self.mapper)
+            .field("format", &self.format)
+            .field("save_path", &self.save_path)
+            .field("timing_mode", &self.timing_mode)
+            .finish()
+    }
+}
+
 impl CartridgeData for Cartridge {
     fn prg_rom(&self) -> &PrgRom {
-        self.data.prg_rom()
+        self.mapper.prg_rom()
+    }
+
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.mapper.chr_rom()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.mapper.chr_ram()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        self.mapper.memory_config()
+    }
+
+    fn timing_mode(&self) -> TimingMode {
+        self.timing_mode
+    }
+
+    fn vs_system(&self) -> Option<u8> {
+        self.vs_system
     }
 
-    fn chr_rom(&self) -> &ChrRom {
-        self.data.chr_rom()
+    fn expansion_device(&self) -> u8 {
+        self.expansion_device
     }
 }
 
@@ -87,9 +267,197 @@ mod tests {
 
         let prg_rom = cartridge.prg_rom();
 
-        let chr_rom = cartridge.chr_rom();
+        let chr_rom = cartridge.chr_rom().unwrap();
 
         assert_eq!(prg_rom.size(), 2 * PRG_UNIT_SIZE as usize);
-        assert_eq!(chr_rom.size(), 1 * CHR_UNIT_SIZE as usize);
+        assert_eq!(chr_rom.size(), CHR_UNIT_SIZE as usize);
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("baldnes-{}-{}", std::process::id(), name))
+    }
+
+    const NES_2_IDENTIFIER: u8 = 0b00001000;
+
+    // 16KB NROM image, flags 6 bit 1 marks battery-backed PRG RAM
+    fn write_nrom_file(path: &Path, battery: bool) {
+        write_nrom_file_with_flags_7(path, battery, 0x00);
+    }
+
+    fn write_nrom_file_with_flags_7(path: &Path, battery: bool, flags_7: u8) {
+        let flags_6 = if battery { 0b00000010 } else { 0 };
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x01, flags_6, flags_7];
+        data.extend([0; 8]);
+        data.extend(vec![0; PRG_UNIT_SIZE as usize + CHR_UNIT_SIZE as usize]);
+        std::fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_save_and_load_ram() {
+        let rom_path = temp_path("save_and_load.nes");
+        let save_path = temp_path("save_and_load.sav");
+        write_nrom_file(&rom_path, false);
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write(0x6000, 0x12);
+        cartridge.write(0x7FFF, 0x34);
+        cartridge.save_ram(&save_path).unwrap();
+
+        let mut fresh_cartridge = Cartridge::from_file(&rom_path).unwrap();
+        assert_eq!(fresh_cartridge.read(0x6000), 0x00);
+        fresh_cartridge.load_ram(&save_path).unwrap();
+
+        assert_eq!(fresh_cartridge.read(0x6000), 0x12);
+        assert_eq!(fresh_cartridge.read(0x7FFF), 0x34);
+
+        std::fs::remove_file(rom_path).unwrap();
+        std::fs::remove_file(save_path).unwrap();
+    }
+
+    #[test]
+    fn test_battery_ram_survives_reopening() {
+        let rom_path = temp_path("battery.nes");
+        let save_path = rom_path.with_extension(SAVE_FILE_EXTENSION);
+        write_nrom_file(&rom_path, true);
+
+        {
+            let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+            cartridge.write(0x6123, 0xAB);
+        }
+        assert!(save_path.exists());
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        assert_eq!(cartridge.read(0x6123), 0xAB);
+
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+        std::fs::remove_file(save_path).unwrap();
+    }
+
+    #[test]
+    fn test_no_save_file_without_battery() {
+        let rom_path = temp_path("no_battery.nes");
+        write_nrom_file(&rom_path, false);
+
+        {
+            let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+            cartridge.write(0x6000, 0xAB);
+        }
+
+        assert!(!rom_path.with_extension(SAVE_FILE_EXTENSION).exists());
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_chr_ram_when_image_has_no_chr_rom() {
+        let rom_path = temp_path("chr_ram.nes");
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x00, 0x00];
+        data.extend([0; 8]);
+        data.extend(vec![0; PRG_UNIT_SIZE as usize]);
+        std::fs::write(&rom_path, data).unwrap();
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.ppu_write(0x0000, 0x12);
+        cartridge.ppu_write(0x1FFF, 0x34);
+
+        assert!(cartridge.chr_rom().is_none());
+        assert_eq!(
+            cartridge.chr_ram().map(|x| x.size()),
+            Some(CHR_UNIT_SIZE as usize)
+        );
+        assert_eq!(cartridge.ppu_read(0x0000), 0x12);
+        assert_eq!(cartridge.ppu_read(0x1FFF), 0x34);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_nes_2_ram_sizes_reach_the_mapper() {
+        let rom_path = temp_path("nes_2_ram_sizes.nes");
+        // NES 2.0 NROM with 4KB PRG RAM and 2KB CHR RAM
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x00, 0x08];
+        data.extend([0x00, 0x00, 0x06, 0x05, 0x00, 0x00, 0x00, 0x00]);
+        data.extend(vec![0; PRG_UNIT_SIZE as usize]);
+        std::fs::write(&rom_path, data).unwrap();
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        let memory_config = cartridge.memory_config();
+
+        assert_eq!(memory_config.prg_ram, Some(4 * 1024));
+        assert_eq!(memory_config.chr_ram, Some(2 * 1024));
+        // The smaller RAM is mirrored across its window
+        cartridge.write(0x6001, 0x12);
+        assert_eq!(cartridge.read(0x7001), 0x12);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_nes_2_without_prg_ram() {
+        let rom_path = temp_path("nes_2_no_prg_ram.nes");
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x01, 0x00, 0x08];
+        data.extend([0x00; 8]);
+        data.extend(vec![0; PRG_UNIT_SIZE as usize]);
+        data.extend(vec![0; CHR_UNIT_SIZE as usize]);
+        std::fs::write(&rom_path, data).unwrap();
+
+        let mut cartridge = Cartridge::from_file(&rom_path).unwrap();
+        cartridge.write(0x6000, 0x12);
+
+        assert!(!cartridge.memory_config().has_prg_ram());
+        assert!(!cartridge.memory_config().has_chr_ram());
+        assert_eq!(cartridge.read(0x6000), 0x00);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_detects_ines() {
+        let rom_path = temp_path("detect_ines.nes");
+        write_nrom_file(&rom_path, false);
+
+        let cartridge = Cartridge::from_file(&rom_path).unwrap();
+
+        assert_eq!(cartridge.format(), Nes::Ines);
+        assert_eq!(cartridge.prg_rom().size(), PRG_UNIT_SIZE as usize);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_detects_nes_2() {
+        let rom_path = temp_path("detect_nes_2.nes");
+        write_nrom_file_with_flags_7(&rom_path, false, NES_2_IDENTIFIER);
+
+        let cartridge = Cartridge::from_file(&rom_path).unwrap();
+
+        assert_eq!(cartridge.format(), Nes::Nes2);
+        assert_eq!(cartridge.prg_rom().size(), PRG_UNIT_SIZE as usize);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_treats_other_identifier_as_ines() {
+        let rom_path = temp_path("detect_archaic.nes");
+        // Bits 2-3 equal to 0b01 are not a NES 2.0 header
+        write_nrom_file_with_flags_7(&rom_path, false, 0b00000100);
+
+        let cartridge = Cartridge::from_file(&rom_path).unwrap();
+
+        assert_eq!(cartridge.format(), Nes::Ines);
+        drop(cartridge);
+        std::fs::remove_file(rom_path).unwrap();
+    }
+
+    #[test]
+    fn test_from_file_rejects_missing_magic_bytes() {
+        let rom_path = temp_path("no_magic.nes");
+        std::fs::write(&rom_path, [0; 16]).unwrap();
+
+        let cartridge = Cartridge::from_file(&rom_path);
+
+        assert!(cartridge.is_err());
+        std::fs::remove_file(rom_path).unwrap();
     }
 }
@@ -1,3 +1,3 @@
-pub const NES_FILE_MAGIC_BYTES: [u8; 4] = ['N' as u8, 'E' as u8, 'S' as u8, 0x1A];
-pub const PRG_UNIT_SIZE: u16 = 16;
-pub const CHR_UNIT_SIZE: u16 = 8;
+pub const NES_FILE_MAGIC_BYTES: [u8; 4] = [b'N', b'E', b'S', 0x1A];
+pub const PRG_UNIT_SIZE: u16 = 16 * 1024;
+pub const CHR_UNIT_SIZE: u16 = 8 * 1024;
@@ -6,6 +6,15 @@ pub enum NesRomReadError {
     #[error("missing magic bytes")]
     MissingMagicBytes,
 
-    #[error("missing prg rom")]
-    MissingPrgRom,
+    #[error("header declares no prg rom")]
+    NoPrgRom,
+
+    #[error("rom size in header overflows")]
+    RomSizeOverflow,
+
+    #[error("missing prg ram")]
+    MissingPrgRam,
+
+    #[error("unsupported mapper {0}")]
+    UnsupportedMapper(u16),
 }
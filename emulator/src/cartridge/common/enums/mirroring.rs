@@ -1,18 +1,39 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum Mirroring {
     Horizontal,
     Vertical,
-    SingleScreen,
+    SingleScreenLower,
+    SingleScreenUpper,
     FourScreen,
 }
 
+const VERTICAL_MIRRORING_FLAG: u8 = 0b0000_0001;
+const FOUR_SCREEN_FLAG: u8 = 0b0000_1000;
+
+impl Mirroring {
+    // Flags 6 of an iNES or NES 2.0 header, four-screen VRAM on the cartridge overrides
+    // the mirroring bit
+    pub fn from_header_flags(flags_6: u8) -> Mirroring {
+        if flags_6 & FOUR_SCREEN_FLAG != 0 {
+            Mirroring::FourScreen
+        } else if flags_6 & VERTICAL_MIRRORING_FLAG != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+}
+
 impl Debug for Mirroring {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Mirroring::Horizontal => write!(f, "Mirroring::Horizontal"),
             Mirroring::Vertical => write!(f, "Mirroring::Vertical"),
-            Mirroring::SingleScreen => write!(f, "Mirroring::SingleScreen"),
+            Mirroring::SingleScreenLower => write!(f, "Mirroring::SingleScreenLower"),
+            Mirroring::SingleScreenUpper => write!(f, "Mirroring::SingleScreenUpper"),
             Mirroring::FourScreen => write!(f, "Mirroring::FourScreen"),
         }
     }
@@ -24,8 +45,22 @@ impl PartialEq for Mirroring {
             (self, other),
             (Mirroring::Horizontal, Mirroring::Horizontal)
                 | (Mirroring::Vertical, Mirroring::Vertical)
-                | (Mirroring::SingleScreen, Mirroring::SingleScreen)
+                | (Mirroring::SingleScreenLower, Mirroring::SingleScreenLower)
+                | (Mirroring::SingleScreenUpper, Mirroring::SingleScreenUpper)
                 | (Mirroring::FourScreen, Mirroring::FourScreen)
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_header_flags() {
+        assert_eq!(Mirroring::from_header_flags(0x00), Mirroring::Horizontal);
+        assert_eq!(Mirroring::from_header_flags(0x01), Mirroring::Vertical);
+        assert_eq!(Mirroring::from_header_flags(0x08), Mirroring::FourScreen);
+        assert_eq!(Mirroring::from_header_flags(0x09), Mirroring::FourScreen);
+    }
+}
@@ -2,3 +2,4 @@ pub mod mirroring;
 
 pub mod errors;
 pub mod nes;
+pub mod timing_mode;
@@ -1,5 +1,6 @@
 use std::fmt::Debug;
 
+#[derive(Clone, Copy)]
 pub enum Nes {
     Ines,
     Nes2,
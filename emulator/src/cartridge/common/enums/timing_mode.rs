@@ -0,0 +1,45 @@
+use std::fmt::Debug;
+
+// CPU/PPU timing from byte 12 of the NES 2.0 header, see https://www.nesdev.org/wiki/NES_2.0#CPU/PPU_Timing
+#[derive(Clone, Copy)]
+pub enum TimingMode {
+    Ntsc,
+    Pal,
+    // Runs on any console, NTSC is as good as any other
+    MultipleRegion,
+    Dendy,
+}
+
+impl TimingMode {
+    pub fn from_header_bits(bits: u8) -> TimingMode {
+        match bits & 0x03 {
+            0 => TimingMode::Ntsc,
+            1 => TimingMode::Pal,
+            2 => TimingMode::MultipleRegion,
+            _ => TimingMode::Dendy,
+        }
+    }
+}
+
+impl Debug for TimingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimingMode::Ntsc => write!(f, "TimingMode::Ntsc"),
+            TimingMode::Pal => write!(f, "TimingMode::Pal"),
+            TimingMode::MultipleRegion => write!(f, "TimingMode::MultipleRegion"),
+            TimingMode::Dendy => write!(f, "TimingMode::Dendy"),
+        }
+    }
+}
+
+impl PartialEq for TimingMode {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (TimingMode::Ntsc, TimingMode::Ntsc)
+                | (TimingMode::Pal, TimingMode::Pal)
+                | (TimingMode::MultipleRegion, TimingMode::MultipleRegion)
+                | (TimingMode::Dendy, TimingMode::Dendy)
+        )
+    }
+}
@@ -1,4 +1,5 @@
 pub mod consts;
 pub mod enums;
+pub mod structs;
 pub mod traits;
 pub mod utils;
@@ -0,0 +1,35 @@
+use std::fmt::Debug;
+
+// Sizes in bytes of the memories present on the board, None when the chip is missing
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct MemoryConfig {
+    pub prg_rom: Option<usize>,
+    pub prg_ram: Option<usize>,
+    pub chr_rom: Option<usize>,
+    pub chr_ram: Option<usize>,
+}
+
+impl MemoryConfig {
+    pub fn has_prg_ram(&self) -> bool {
+        self.prg_ram.is_some()
+    }
+
+    pub fn has_chr_rom(&self) -> bool {
+        self.chr_rom.is_some()
+    }
+
+    pub fn has_chr_ram(&self) -> bool {
+        self.chr_ram.is_some()
+    }
+}
+
+impl Debug for MemoryConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryConfig")
+            .field("prg_rom", &self.prg_rom)
+            .field("prg_ram", &self.prg_ram)
+            .field("chr_rom", &self.chr_rom)
+            .field("chr_ram", &self.chr_ram)
+            .finish()
+    }
+}
@@ -0,0 +1 @@
+pub mod memory_config;
@@ -1,7 +1,34 @@
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
+use crate::cartridge::registers::chr_ram::ChrRam;
 use crate::cartridge::registers::chr_rom::ChrRom;
 use crate::cartridge::registers::prg_rom::PrgRom;
 
 pub trait CartridgeData {
     fn prg_rom(&self) -> &PrgRom;
-    fn chr_rom(&self) -> &ChrRom;
+    // None on boards which use CHR RAM instead
+    fn chr_rom(&self) -> Option<&ChrRom>;
+    fn memory_config(&self) -> MemoryConfig;
+
+    // 8KB of CHR RAM stands in for missing CHR ROM
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        None
+    }
+
+    // Console details only NES 2.0 headers describe, iNES images get the defaults
+
+    fn timing_mode(&self) -> TimingMode {
+        TimingMode::Ntsc
+    }
+
+    // Raw byte 13 of the header (PPU and hardware type) for VS System games
+    fn vs_system(&self) -> Option<u8> {
+        None
+    }
+
+    // Default expansion device, 0 is unspecified and 1 the standard controllers
+    // See https://www.nesdev.org/wiki/NES_2.0#Default_Expansion_Device
+    fn expansion_device(&self) -> u8 {
+        0
+    }
 }
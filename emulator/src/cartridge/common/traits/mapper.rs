@@ -0,0 +1,33 @@
+use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::traits::cartridge_data::CartridgeData;
+use crate::cartridge::registers::prg_ram::PrgRam;
+use std::fmt::Debug;
+
+// Called by mappers that switch nametable mirroring at runtime, e.g. MMC1
+pub type MirroringListener = Box<dyn FnMut(Mirroring)>;
+
+// Translates CPU (0x4020-0xFFFF) and PPU (0x0000-0x1FFF) addresses into cartridge memory
+pub trait Mapper: CartridgeData + Debug {
+    // Mappers that react to reads (e.g. MMC5 IRQ acknowledge) override this,
+    // cpu_peek must stay free of side effects either way
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+    fn cpu_peek(&self, address: u16) -> u8;
+    fn cpu_write(&mut self, address: u16, data: u8);
+    fn ppu_read(&mut self, address: u16) -> u8;
+    fn ppu_write(&mut self, address: u16, data: u8);
+    fn mirroring(&self) -> Mirroring;
+
+    // Mappers with fixed mirroring never call the listener, so they can drop it
+    fn set_mirroring_listener(&mut self, _listener: MirroringListener) {}
+
+    // PRG RAM at 0x6000-0x7FFF, the part kept alive by a battery on some boards
+    fn prg_ram(&self) -> Option<&PrgRam> {
+        None
+    }
+
+    fn prg_ram_mut(&mut self) -> Option<&mut PrgRam> {
+        None
+    }
+}
@@ -1,2 +1,3 @@
 pub mod cartridge_data;
 pub mod file_loadable;
+pub mod mapper;
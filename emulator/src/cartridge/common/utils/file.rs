@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{ErrorKind, Read};
 
 pub fn read_banks<R: Read>(
     file: &mut R,
@@ -14,9 +14,20 @@ pub fn read_banks<R: Read>(
     Ok(banks)
 }
 
+// The buffer only grows with the data actually read, a size taken from a crafted header
+// can't allocate more than the file holds
+pub fn read_bytes<R: Read>(file: &mut R, size: usize) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    file.take(size as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != size {
+        return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+    }
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::cartridge::common::utils::file::read_banks;
+    use crate::cartridge::common::utils::file::{read_banks, read_bytes};
     #[test]
     fn test_read_banks_2_4() {
         let data = vec![1, 2, 3, 4, 5, 6, 7, 8];
@@ -32,4 +43,26 @@ mod tests {
         let banks = read_banks(&mut cursor, 2, 3).unwrap();
         assert_eq!(banks, vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
     }
+
+    #[test]
+    fn test_read_bytes() {
+        let data = [0x01, 0x02, 0x03, 0x04];
+        let mut cursor = std::io::Cursor::new(data);
+        let bytes = read_bytes(&mut cursor, 3).unwrap();
+        assert_eq!(bytes, vec![0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_read_bytes_past_end() {
+        let data = [0x01, 0x02];
+        let mut cursor = std::io::Cursor::new(data);
+        assert!(read_bytes(&mut cursor, 3).is_err());
+    }
+
+    #[test]
+    fn test_read_bytes_huge_size_past_end() {
+        let data = [0x01, 0x02];
+        let mut cursor = std::io::Cursor::new(data);
+        assert!(read_bytes(&mut cursor, 1 << 62).is_err());
+    }
 }
@@ -0,0 +1,94 @@
+use crate::cartridge::common::consts::CHR_UNIT_SIZE;
+use crate::cartridge::common::enums::errors::NesRomReadError;
+use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::traits::mapper::Mapper;
+use crate::cartridge::mappers::cnrom::Cnrom;
+use crate::cartridge::mappers::mmc1::Mmc1;
+use crate::cartridge::mappers::nrom::Nrom;
+use crate::cartridge::registers::chr_rom::ChrRom;
+use crate::cartridge::registers::prg_rom::PrgRom;
+
+pub const NROM_MAPPER_NUMBER: u16 = 0;
+pub const MMC1_MAPPER_NUMBER: u16 = 1;
+pub const CNROM_MAPPER_NUMBER: u16 = 3;
+
+// iNES headers leave PRG RAM out, every board is given the usual 8KB
+pub const DEFAULT_PRG_RAM_SIZE: usize = 0x2000;
+
+// Boards without CHR ROM carry 8KB of CHR RAM unless a NES 2.0 header says otherwise
+pub fn default_chr_ram_size(chr_rom: &Option<ChrRom>) -> usize {
+    match chr_rom {
+        Some(_) => 0,
+        None => CHR_UNIT_SIZE as usize,
+    }
+}
+
+// RAM sizes are in bytes, 0 leaves the chip off the board
+pub fn mapper_from_number(
+    mapper_number: u16,
+    prg_rom: PrgRom,
+    chr_rom: Option<ChrRom>,
+    mirroring: Mirroring,
+    prg_ram_size: usize,
+    chr_ram_size: usize,
+) -> anyhow::Result<Box<dyn Mapper>> {
+    match mapper_number {
+        NROM_MAPPER_NUMBER => Ok(Box::new(Nrom::with_ram_sizes(
+            prg_rom,
+            chr_rom,
+            mirroring,
+            prg_ram_size,
+            chr_ram_size,
+        ))),
+        MMC1_MAPPER_NUMBER => Ok(Box::new(Mmc1::with_ram_sizes(
+            prg_rom,
+            chr_rom,
+            prg_ram_size,
+            chr_ram_size,
+        ))),
+        CNROM_MAPPER_NUMBER => Ok(Box::new(Cnrom::with_ram_sizes(
+            prg_rom,
+            chr_rom,
+            mirroring,
+            prg_ram_size,
+            chr_ram_size,
+        ))),
+        _ => Err(NesRomReadError::UnsupportedMapper(mapper_number).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapper_with_number(mapper_number: u16) -> anyhow::Result<Box<dyn Mapper>> {
+        mapper_from_number(
+            mapper_number,
+            PrgRom::new_with_data(vec![0; 16]),
+            None,
+            Mirroring::Vertical,
+            0x2000,
+            0x2000,
+        )
+    }
+
+    #[test]
+    fn test_mapper_from_number_nrom() {
+        assert!(mapper_with_number(NROM_MAPPER_NUMBER).is_ok());
+    }
+
+    #[test]
+    fn test_mapper_from_number_mmc1() {
+        assert!(mapper_with_number(MMC1_MAPPER_NUMBER).is_ok());
+    }
+
+    #[test]
+    fn test_mapper_from_number_cnrom() {
+        assert!(mapper_with_number(CNROM_MAPPER_NUMBER).is_ok());
+    }
+
+    #[test]
+    fn test_mapper_from_number_unsupported() {
+        assert!(mapper_with_number(0xFFF).is_err());
+    }
+}
@@ -1,7 +1,13 @@
 use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
 use crate::cartridge::common::traits::cartridge_data::CartridgeData;
 use crate::cartridge::common::traits::file_loadable::FileLoadable;
+use crate::cartridge::common::traits::mapper::Mapper;
 use crate::cartridge::common::utils::file::read_banks;
+use crate::cartridge::common::utils::mapper::{
+    default_chr_ram_size, mapper_from_number, DEFAULT_PRG_RAM_SIZE,
+};
+use crate::cartridge::registers::chr_ram::ChrRam;
 use crate::cartridge::registers::chr_rom::ChrRom;
 use crate::cartridge::registers::prg_rom::PrgRom;
 use std::fs::File;
@@ -12,6 +18,11 @@ use crate::cartridge::common::consts::{CHR_UNIT_SIZE, NES_FILE_MAGIC_BYTES, PRG_
 use crate::cartridge::common::enums::errors::NesRomReadError;
 use std::fmt::Debug;
 
+const PLAY_CHOICE_INST_ROM_SIZE: usize = 8 * 1024;
+// 16 bytes of data followed by 16 bytes of CounterOut
+const PLAY_CHOICE_PROM_SIZE: usize = 32;
+const TITLE_SIZE: usize = 128;
+
 // Bytes 	Description
 // 0-3 	Constant $4E $45 $53 $1A (ASCII "NES" followed by MS-DOS end-of-file)
 // 4 	Size of PRG ROM in 16 KB units
@@ -61,9 +72,8 @@ pub struct Ines {
     mirroring: Mirroring,
     battery: bool,
     four_screen_vram: bool,
-    prg_rom: PrgRom,
-    chr_rom: Option<ChrRom>,
-    mapper: u8,
+    mapper_number: u8,
+    mapper: Box<dyn Mapper>,
     play_choice_inst_rom: Option<Vec<u8>>,
     play_choice_10: Option<Vec<u8>>,
     title: Option<[u8; 128]>,
@@ -77,8 +87,7 @@ impl Debug for Ines {
             .field("mirroring", &self.mirroring)
             .field("battery", &self.battery)
             .field("four_screen_vram", &self.four_screen_vram)
-            .field("prg_rom", &self.prg_rom)
-            .field("chr_rom", &self.chr_rom)
+            .field("mapper_number", &self.mapper_number)
             .field("mapper", &self.mapper)
             .field("play_choice_inst_rom", &self.play_choice_inst_rom)
             .field("play_choice_10", &self.play_choice_10)
@@ -125,15 +134,21 @@ impl Ines {
 impl FileLoadable for Ines {
     fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Ines> {
         let mut file = BufReader::new(File::open(path)?);
-        let header = Ines::header_from_file(&mut file)?;
+        Ines::from_reader(&mut file)
+    }
+}
+
+impl Ines {
+    fn from_reader<R: Read>(file: &mut R) -> anyhow::Result<Ines> {
+        let header = Ines::header_from_file(file)?;
+        // Without PRG ROM there are no vectors and no code to run
+        if header.prg_rom_size == 0 {
+            return Err(NesRomReadError::NoPrgRom.into());
+        }
 
         let is_trainer_present = header.flags_6 & 0b00000100 != 0;
 
-        let mirroring = if header.flags_6 & 0b00000001 != 0 {
-            Mirroring::Vertical
-        } else {
-            Mirroring::Horizontal
-        };
+        let mirroring = Mirroring::from_header_flags(header.flags_6);
 
         let battery = header.flags_6 & 0b00000010 != 0;
 
@@ -146,25 +161,53 @@ impl FileLoadable for Ines {
 
         let four_screen_vram = header.flags_6 & 0b00001000 != 0;
 
-        let prg_rom =
-            PrgRom::new_with_data(read_banks(&mut file, header.prg_rom_size, PRG_UNIT_SIZE)?);
+        let prg_rom = PrgRom::new_with_data(read_banks(file, header.prg_rom_size, PRG_UNIT_SIZE)?);
 
         let chr_rom = if header.chr_rom_size != 0 {
             Some(ChrRom::new_with_data(read_banks(
-                &mut file,
+                file,
                 header.chr_rom_size,
                 CHR_UNIT_SIZE,
             )?))
         } else {
+            // 8KB of CHR RAM takes the place of the missing CHR ROM
             None
         };
 
-        let mapper = (header.flags_6 & 0xF0) | (header.flags_7 & 0xF0);
+        let mapper_number = (header.flags_6 >> 4) | (header.flags_7 & 0xF0);
+        let chr_ram_size = default_chr_ram_size(&chr_rom);
+        let mapper = mapper_from_number(
+            mapper_number as u16,
+            prg_rom,
+            chr_rom,
+            mirroring,
+            DEFAULT_PRG_RAM_SIZE,
+            chr_ram_size,
+        )?;
+
+        let mut trailing = Vec::new();
+        file.read_to_end(&mut trailing)?;
+        let mut trailing = trailing.as_slice();
 
-        let play_choice_inst_rom = None;
+        let mut play_choice_inst_rom = None;
+        let mut play_choice_10 = None;
+        if header.flags_7 & 0b00000010 != 0 && trailing.len() >= PLAY_CHOICE_INST_ROM_SIZE {
+            play_choice_inst_rom = Some(trailing[..PLAY_CHOICE_INST_ROM_SIZE].to_vec());
+            trailing = &trailing[PLAY_CHOICE_INST_ROM_SIZE..];
+            // The PROM is often missing from dumps, so only a title may follow
+            if trailing.len() >= PLAY_CHOICE_PROM_SIZE && !Ines::is_title(trailing) {
+                play_choice_10 = Some(trailing[..PLAY_CHOICE_PROM_SIZE].to_vec());
+                trailing = &trailing[PLAY_CHOICE_PROM_SIZE..];
+            }
+        }
 
-        let play_choice_10 = None;
-        let title = None;
+        let title = if Ines::is_title(trailing) {
+            let mut title = [0; TITLE_SIZE];
+            title[..trailing.len()].copy_from_slice(trailing);
+            Some(title)
+        } else {
+            None
+        };
 
         Ok(Ines {
             header,
@@ -172,8 +215,7 @@ impl FileLoadable for Ines {
             mirroring,
             battery,
             four_screen_vram,
-            prg_rom,
-            chr_rom,
+            mapper_number,
             mapper,
             play_choice_inst_rom,
             play_choice_10,
@@ -182,16 +224,36 @@ impl FileLoadable for Ines {
     }
 }
 
+impl Ines {
+    // Titles are 128 bytes, or sometimes 127, and always end the file
+    fn is_title(trailing: &[u8]) -> bool {
+        trailing.len() == TITLE_SIZE || trailing.len() == TITLE_SIZE - 1
+    }
+
+    pub fn into_mapper(self) -> Box<dyn Mapper> {
+        self.mapper
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.battery
+    }
+}
+
 impl CartridgeData for Ines {
     fn prg_rom(&self) -> &PrgRom {
-        &self.prg_rom
+        self.mapper.prg_rom()
     }
 
-    fn chr_rom(&self) -> &ChrRom {
-        match self.chr_rom.as_ref() {
-            Some(x) => x,
-            None => panic!("CHR ROM is not present"),
-        }
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.mapper.chr_rom()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.mapper.chr_ram()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        self.mapper.memory_config()
     }
 }
 #[cfg(test)]
@@ -243,27 +305,162 @@ mod tests {
         let ines = Ines::from_file("resources/smb.nes").unwrap();
 
         // mapper
-        assert_eq!(ines.mapper, 0);
+        assert_eq!(ines.mapper_number, 0);
         // mirroring
         assert_eq!(ines.mirroring, Mirroring::Vertical);
         // battery
-        assert_eq!(ines.battery, false);
+        assert!(!ines.battery);
 
         // prg_rom
         // inary operation `==` cannot be applied to type `usize`
-        assert_eq!(ines.prg_rom.size(), 2 * PRG_UNIT_SIZE as usize);
+        assert_eq!(ines.prg_rom().size(), 2 * PRG_UNIT_SIZE as usize);
         assert_eq!(ines.header.prg_rom_size, 2);
 
         // chr_rom
-        assert_eq!(
-            ines.chr_rom.as_ref().unwrap().size(),
-            1 * CHR_UNIT_SIZE as usize
-        );
+        assert_eq!(ines.chr_rom().unwrap().size(), CHR_UNIT_SIZE as usize);
         assert_eq!(ines.header.chr_rom_size, 1);
 
         // trainer
-        assert_eq!(ines.trainer.is_none(), true);
+        assert!(ines.trainer.is_none());
 
         println!("{:?}", ines);
     }
+
+    fn nrom_file(prg_banks: u8, reset_vector: u16) -> Vec<u8> {
+        let mut data = vec![0x4E, 0x45, 0x53, 0x1A, prg_banks, 0x01, 0x01, 0x00];
+        data.extend([0; 8]);
+        let prg_size = prg_banks as usize * PRG_UNIT_SIZE as usize;
+        let mut prg = vec![0; prg_size];
+        prg[prg_size - 4] = (reset_vector & 0xFF) as u8;
+        prg[prg_size - 3] = (reset_vector >> 8) as u8;
+        data.extend(prg);
+        data.extend(vec![0; CHR_UNIT_SIZE as usize]);
+        data
+    }
+
+    #[test]
+    fn test_title_after_chr_rom() {
+        let mut data = nrom_file(1, 0xC004);
+        let mut title = [0; TITLE_SIZE];
+        title[..5].copy_from_slice(b"TITLE");
+        data.extend(title);
+        let mut cursor = Cursor::new(data);
+
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(ines.title, Some(title));
+        assert!(ines.play_choice_inst_rom.is_none());
+        assert!(ines.play_choice_10.is_none());
+    }
+
+    #[test]
+    fn test_127_byte_title() {
+        let mut data = nrom_file(1, 0xC004);
+        data.extend([b'A'; TITLE_SIZE - 1]);
+        let mut cursor = Cursor::new(data);
+
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+        let title = ines.title.unwrap();
+
+        assert_eq!(title[..TITLE_SIZE - 1], [b'A'; TITLE_SIZE - 1]);
+        assert_eq!(title[TITLE_SIZE - 1], 0);
+    }
+
+    #[test]
+    fn test_play_choice_data() {
+        let mut data = nrom_file(1, 0xC004);
+        data[7] |= 0b00000010;
+        data.extend(vec![0x11; PLAY_CHOICE_INST_ROM_SIZE]);
+        data.extend([0x22; PLAY_CHOICE_PROM_SIZE]);
+        data.extend([0x33; TITLE_SIZE]);
+        let mut cursor = Cursor::new(data);
+
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(
+            ines.play_choice_inst_rom,
+            Some(vec![0x11; PLAY_CHOICE_INST_ROM_SIZE])
+        );
+        assert_eq!(ines.play_choice_10, Some(vec![0x22; PLAY_CHOICE_PROM_SIZE]));
+        assert_eq!(ines.title, Some([0x33; TITLE_SIZE]));
+    }
+
+    #[test]
+    fn test_play_choice_without_prom() {
+        let mut data = nrom_file(1, 0xC004);
+        data[7] |= 0b00000010;
+        data.extend(vec![0x11; PLAY_CHOICE_INST_ROM_SIZE]);
+        let mut cursor = Cursor::new(data);
+
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+
+        assert!(ines.play_choice_inst_rom.is_some());
+        assert!(ines.play_choice_10.is_none());
+        assert!(ines.title.is_none());
+    }
+
+    #[test]
+    fn test_from_reader_rejects_empty_prg_rom() {
+        let mut data = vec![0x4E, 0x45, 0x53, 0x1A, 0x00, 0x01, 0x00, 0x00];
+        data.extend([0; 8]);
+        data.extend(vec![0; CHR_UNIT_SIZE as usize]);
+        let mut cursor = Cursor::new(data);
+
+        let error = Ines::from_reader(&mut cursor).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<NesRomReadError>(),
+            Some(NesRomReadError::NoPrgRom)
+        ));
+    }
+
+    #[test]
+    fn test_nrom_reset_vector_through_mapper() {
+        let mut cursor = Cursor::new(nrom_file(2, 0x8000));
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+        let mut mapper = ines.into_mapper();
+
+        assert_eq!(mapper.cpu_read(0xFFFC), 0x00);
+        assert_eq!(mapper.cpu_read(0xFFFD), 0x80);
+    }
+
+    #[test]
+    fn test_nrom_16kb_reset_vector_through_mapper() {
+        let mut cursor = Cursor::new(nrom_file(1, 0xC004));
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+        let mut mapper = ines.into_mapper();
+
+        assert_eq!(mapper.cpu_read(0xFFFC), 0x04);
+        assert_eq!(mapper.cpu_read(0xFFFD), 0xC0);
+        assert_eq!(mapper.cpu_read(0xBFFC), 0x04);
+        assert_eq!(mapper.cpu_read(0xBFFD), 0xC0);
+    }
+
+    #[test]
+    fn test_mapper_number_from_both_nibbles() {
+        let mut data = nrom_file(1, 0xC004);
+        data[6] = 0x11;
+        data[7] = 0x20;
+        let mut cursor = Cursor::new(data);
+        let ines = Ines::from_reader(&mut cursor);
+
+        // mapper 0x21 is not implemented
+        assert!(ines.is_err());
+    }
+
+    #[test]
+    fn test_memory_config_of_chr_ram_cartridge() {
+        let mut data = nrom_file(1, 0xC004);
+        data[5] = 0x00;
+        data.truncate(data.len() - CHR_UNIT_SIZE as usize);
+        let mut cursor = Cursor::new(data);
+        let ines = Ines::from_reader(&mut cursor).unwrap();
+
+        let memory_config = ines.memory_config();
+
+        assert!(memory_config.has_chr_ram());
+        assert!(!memory_config.has_chr_rom());
+        assert_eq!(memory_config.chr_ram, Some(CHR_UNIT_SIZE as usize));
+        assert_eq!(memory_config.prg_rom, Some(PRG_UNIT_SIZE as usize));
+    }
 }
@@ -1,31 +1,45 @@
 use crate::cartridge::common::consts::NES_FILE_MAGIC_BYTES;
-use crate::cartridge::common::consts::PRG_UNIT_SIZE;
+use crate::cartridge::common::consts::{CHR_UNIT_SIZE, PRG_UNIT_SIZE};
 use crate::cartridge::common::enums::errors::NesRomReadError;
 use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
 use crate::cartridge::common::traits::cartridge_data::CartridgeData;
 use crate::cartridge::common::traits::file_loadable::FileLoadable;
-use crate::cartridge::common::utils::file::read_banks;
+use crate::cartridge::common::traits::mapper::Mapper;
+use crate::cartridge::common::utils::file::read_bytes;
+use crate::cartridge::common::utils::mapper::mapper_from_number;
 use crate::cartridge::registers::chr_ram::ChrRam;
 use crate::cartridge::registers::chr_rom::ChrRom;
-use crate::cartridge::registers::prg_ram::PrgRam;
 use crate::cartridge::registers::prg_rom::PrgRom;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
-// TODO: implement CartridgeData for Nes2
-// TODO: implement FileLoadable for Nes2
-// TODO: Fix the code
-// TODO: Extended Console Type
-// TODO: VS Unisystem
+// Header layout taken from https://www.nesdev.org/wiki/NES_2.0
+// Bytes 	Description
+// 0-3 	Constant $4E $45 $53 $1A (ASCII "NES" followed by MS-DOS end-of-file)
+// 4 	PRG-ROM size LSB
+// 5 	CHR-ROM size LSB
+// 6 	Flags 6 – Mapper D3..D0, mirroring, battery, trainer
+// 7 	Flags 7 – Mapper D7..D4, NES 2.0 identifier, console type
+// 8 	Mapper MSB (D11..D8) / Submapper
+// 9 	PRG-ROM / CHR-ROM size MSB
+// 10 	PRG-RAM / EEPROM size
+// 11 	CHR-RAM size
+// 12 	CPU/PPU timing
+// 13 	VS System type / Extended console type
+// 14 	Miscellaneous ROMs
+// 15 	Default expansion device
 struct Nes2Header {
     prg_rom_size: u8,
     chr_rom_size: u8,
     flags_6: u8,
     flags_7: u8,
-    mapper: u8,
+    mapper: u16,
     submapper: u8,
+    rom_size_msb: u8,
     prg_ram_size: u8,
     chr_ram_size: u8,
     cpu_ppu_timing_mode: u8,
@@ -44,6 +58,7 @@ impl Debug for Nes2Header {
             .field("flags_7", &self.flags_7)
             .field("mapper", &self.mapper)
             .field("submapper", &self.submapper)
+            .field("rom_size_msb", &self.rom_size_msb)
             .field("prg_ram_size", &self.prg_ram_size)
             .field("chr_ram_size", &self.chr_ram_size)
             .field("cpu_ppu_timing_mode", &self.cpu_ppu_timing_mode)
@@ -57,11 +72,8 @@ impl Debug for Nes2Header {
 
 pub struct Nes2 {
     header: Nes2Header,
-    prg_rom: PrgRom,
-    chr_rom: Option<ChrRom>,
+    mapper: Box<dyn Mapper>,
     trainer: Option<[u8; 512]>,
-    prg_ram: Option<PrgRam>,
-    chr_ram: Option<ChrRam>,
     mirroring: Mirroring,
 }
 
@@ -69,16 +81,69 @@ impl Debug for Nes2 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Nes2")
             .field("header", &self.header)
-            .field("prg_rom", &self.prg_rom)
-            .field("chr_rom", &self.chr_rom)
+            .field("mapper", &self.mapper)
             .field("trainer", &self.trainer)
-            .field("prg_ram", &self.prg_ram)
-            .field("chr_ram", &self.chr_ram)
             .field("mirroring", &self.mirroring)
             .finish()
     }
 }
 
+const EXPONENT_SIZE_NIBBLE: u8 = 0x0F;
+const VS_SYSTEM_CONSOLE_TYPE: u8 = 0x01;
+const EXTENDED_CONSOLE_TYPE: u8 = 0x03;
+
+// ROM sizes are either a 12-bit count of units, or when the MSB nibble is 0xF,
+// an exponent-multiplier pair packed in the LSB as EEEEEEMM: 2^E * (MM * 2 + 1) bytes
+// A crafted header can ask for up to 2^63 * 7 bytes, which is rejected instead of wrapping
+fn rom_size(size_lsb: u8, size_msb: u8, unit_size: u16) -> Result<usize, NesRomReadError> {
+    if size_msb == EXPONENT_SIZE_NIBBLE {
+        let exponent = (size_lsb >> 2) as u32;
+        let multiplier = (size_lsb & 0b11) as usize * 2 + 1;
+        2usize
+            .checked_pow(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .ok_or(NesRomReadError::RomSizeOverflow)
+    } else {
+        Ok((((size_msb as usize) << 8) | size_lsb as usize) * unit_size as usize)
+    }
+}
+
+// RAM sizes are stored as shift counts: 64 << shift bytes, 0 meaning no RAM
+fn ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+impl Nes2Header {
+    // Volatile RAM in the low nibble, battery-backed NVRAM in the high one
+    fn prg_ram_bytes(&self) -> usize {
+        ram_size(self.prg_ram_size & 0x0F)
+    }
+
+    fn prg_nvram_bytes(&self) -> usize {
+        ram_size(self.prg_ram_size >> 4)
+    }
+
+    fn chr_ram_bytes(&self) -> usize {
+        ram_size(self.chr_ram_size & 0x0F)
+    }
+
+    fn chr_nvram_bytes(&self) -> usize {
+        ram_size(self.chr_ram_size >> 4)
+    }
+
+    fn prg_rom_bytes(&self) -> Result<usize, NesRomReadError> {
+        rom_size(self.prg_rom_size, self.rom_size_msb & 0x0F, PRG_UNIT_SIZE)
+    }
+
+    fn chr_rom_bytes(&self) -> Result<usize, NesRomReadError> {
+        rom_size(self.chr_rom_size, self.rom_size_msb >> 4, CHR_UNIT_SIZE)
+    }
+}
+
 impl Nes2 {
     fn header_from_file<R: Read>(file: &mut R) -> anyhow::Result<Nes2Header> {
         let mut header = [0; 16];
@@ -96,23 +161,26 @@ impl Nes2 {
         let chr_rom_size = header[5];
         let flags_6 = header[6];
         let flags_7 = header[7];
-        let mapper = (flags_6 & 0xF0) | (flags_7 >> 4);
-        let submapper = (flags_6 & 0x0F) | (flags_7 & 0x0F);
-        let prg_ram_size = header[8];
-        let chr_ram_size = header[9];
-        let cpu_ppu_timing_mode = header[10];
-        let vs_unisystem = if header[11] != 0 {
-            Some(header[11])
+        let mapper =
+            ((header[8] as u16 & 0x0F) << 8) | (flags_7 & 0xF0) as u16 | (flags_6 >> 4) as u16;
+        let submapper = header[8] >> 4;
+        let rom_size_msb = header[9];
+        let prg_ram_size = header[10];
+        let chr_ram_size = header[11];
+        let cpu_ppu_timing_mode = header[12] & 0x03;
+        let console_type = flags_7 & 0x03;
+        let vs_unisystem = if console_type == VS_SYSTEM_CONSOLE_TYPE {
+            Some(header[13])
         } else {
             None
         };
-        let extended_console_type = if header[12] != 0 {
-            Some(header[12])
+        let extended_console_type = if console_type == EXTENDED_CONSOLE_TYPE {
+            Some(header[13] & 0x0F)
         } else {
             None
         };
-        let misc_rom_count = header[13];
-        let default_expansion_device = header[14];
+        let misc_rom_count = header[14] & 0x03;
+        let default_expansion_device = header[15] & 0x3F;
 
         Ok(Nes2Header {
             prg_rom_size,
@@ -121,6 +189,7 @@ impl Nes2 {
             flags_7,
             mapper,
             submapper,
+            rom_size_msb,
             prg_ram_size,
             chr_ram_size,
             cpu_ppu_timing_mode,
@@ -132,31 +201,68 @@ impl Nes2 {
     }
 }
 
+impl Nes2 {
+    pub fn into_mapper(self) -> Box<dyn Mapper> {
+        self.mapper
+    }
+
+    // NVRAM is battery-backed even when the flags 6 bit is left clear
+    pub fn has_battery(&self) -> bool {
+        self.header.flags_6 & 0b00000010 != 0
+            || self.header.prg_nvram_bytes() != 0
+            || self.header.chr_nvram_bytes() != 0
+    }
+}
+
 impl CartridgeData for Nes2 {
     fn prg_rom(&self) -> &PrgRom {
-        &self.prg_rom
+        self.mapper.prg_rom()
     }
 
-    fn chr_rom(&self) -> &ChrRom {
-        match self.chr_rom.as_ref() {
-            Some(x) => x,
-            None => panic!("CHR ROM is not present"),
-        }
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.mapper.chr_rom()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.mapper.chr_ram()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        self.mapper.memory_config()
+    }
+
+    fn timing_mode(&self) -> TimingMode {
+        TimingMode::from_header_bits(self.header.cpu_ppu_timing_mode)
+    }
+
+    fn vs_system(&self) -> Option<u8> {
+        self.header.vs_unisystem
+    }
+
+    fn expansion_device(&self) -> u8 {
+        self.header.default_expansion_device
     }
 }
 
 impl FileLoadable for Nes2 {
     fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Nes2> {
         let mut file = BufReader::new(File::open(path)?);
-        let header = Nes2::header_from_file(&mut file)?;
+        Nes2::from_reader(&mut file)
+    }
+}
+
+impl Nes2 {
+    fn from_reader<R: Read>(file: &mut R) -> anyhow::Result<Nes2> {
+        let header = Nes2::header_from_file(file)?;
+        // Without PRG ROM there are no vectors and no code to run
+        let prg_rom_bytes = header.prg_rom_bytes()?;
+        if prg_rom_bytes == 0 {
+            return Err(NesRomReadError::NoPrgRom.into());
+        }
 
         let is_trainer_present = header.flags_6 & 0b00000100 != 0;
 
-        let mirroring = if header.flags_6 & 0b00000001 != 0 {
-            Mirroring::Vertical
-        } else {
-            Mirroring::Horizontal
-        };
+        let mirroring = Mirroring::from_header_flags(header.flags_6);
 
         let mut trainer = None;
         if is_trainer_present {
@@ -165,38 +271,32 @@ impl FileLoadable for Nes2 {
             trainer = Some(trainer_data);
         }
 
-        let prg_rom =
-            PrgRom::new_with_data(read_banks(&mut file, header.prg_rom_size, PRG_UNIT_SIZE)?);
+        let prg_rom = PrgRom::new_with_data(read_bytes(file, prg_rom_bytes)?);
 
-        let chr_rom = if header.chr_rom_size != 0 {
-            Some(ChrRom::new_with_data(read_banks(
-                &mut file,
-                header.chr_rom_size,
-                PRG_UNIT_SIZE,
-            )?))
+        let chr_rom_bytes = header.chr_rom_bytes()?;
+        let chr_rom = if chr_rom_bytes != 0 {
+            Some(ChrRom::new_with_data(read_bytes(file, chr_rom_bytes)?))
         } else {
             None
         };
 
-        let prg_ram = if header.prg_ram_size != 0 {
-            Some(PrgRam::new(header.prg_ram_size as usize))
-        } else {
-            None
-        };
+        // The mappers have a single RAM window, volatile RAM and NVRAM are allocated together
+        let prg_ram_bytes = header.prg_ram_bytes() + header.prg_nvram_bytes();
+        let chr_ram_bytes = header.chr_ram_bytes() + header.chr_nvram_bytes();
 
-        let chr_ram = if header.chr_ram_size != 0 {
-            Some(ChrRam::new(header.chr_ram_size as usize))
-        } else {
-            None
-        };
+        let mapper = mapper_from_number(
+            header.mapper,
+            prg_rom,
+            chr_rom,
+            mirroring,
+            prg_ram_bytes,
+            chr_ram_bytes,
+        )?;
 
         Ok(Nes2 {
             header,
-            prg_rom,
-            chr_rom,
+            mapper,
             trainer,
-            prg_ram,
-            chr_ram,
             mirroring,
         })
     }
@@ -209,9 +309,271 @@ mod tests {
     #[test]
     fn test_header_from_file() {
         let data = [
-            'N' as u8, 'E' as u8, 'S' as u8, 0x1A, 0, 0, 0, 0x08, 0, 0, 0, 0, 0, 0, 0, 0,
+            b'N', b'E', b'S', 0x1A, 0, 0, 0, 0x08, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let mut cursor = std::io::Cursor::new(data);
+        Nes2::header_from_file(&mut cursor).unwrap();
+    }
+
+    #[test]
+    fn test_header_fields_from_file() {
+        let data = [
+            b'N', b'E', b'S', 0x1A, 0x02, 0x01, 0x41, 0x29, 0x31, 0x00, 0x07, 0x09, 0x01, 0x05,
+            0x01, 0x02,
         ];
         let mut cursor = std::io::Cursor::new(data);
         let header = Nes2::header_from_file(&mut cursor).unwrap();
+
+        assert_eq!(header.mapper, 0x124);
+        assert_eq!(header.submapper, 0x03);
+        assert_eq!(header.prg_ram_size, 0x07);
+        assert_eq!(header.chr_ram_size, 0x09);
+        assert_eq!(header.cpu_ppu_timing_mode, 0x01);
+        assert_eq!(header.vs_unisystem, Some(0x05));
+        assert_eq!(header.extended_console_type, None);
+        assert_eq!(header.misc_rom_count, 0x01);
+        assert_eq!(header.default_expansion_device, 0x02);
+    }
+
+    #[test]
+    fn test_console_details_from_reader() {
+        // PAL VS System game with the standard controllers
+        let mut data = vec![
+            b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x01, 0x23,
+            0x00, 0x01,
+        ];
+        data.extend(vec![0xAA; PRG_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let nes2 = Nes2::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(nes2.timing_mode(), TimingMode::Pal);
+        assert_eq!(nes2.vs_system(), Some(0x23));
+        assert_eq!(nes2.expansion_device(), 0x01);
+    }
+
+    fn nes_2_with_ram_sizes(prg_ram_size: u8, chr_ram_size: u8) -> Nes2 {
+        let mut data = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            0x01,
+            0x00,
+            0x00,
+            0x08,
+            0x00,
+            0x00,
+            prg_ram_size,
+            chr_ram_size,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        data.extend(vec![0xAA; PRG_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+        Nes2::from_reader(&mut cursor).unwrap()
+    }
+
+    #[test]
+    fn test_ram_size_from_shift() {
+        assert_eq!(ram_size(0), 0);
+        assert_eq!(ram_size(1), 128);
+        assert_eq!(ram_size(7), 8 * 1024);
+    }
+
+    #[test]
+    fn test_volatile_ram_sizes() {
+        // 8KB PRG RAM, 8KB CHR RAM
+        let nes2 = nes_2_with_ram_sizes(0x07, 0x07);
+
+        assert_eq!(nes2.header.prg_ram_bytes(), 8 * 1024);
+        assert_eq!(nes2.header.prg_nvram_bytes(), 0);
+        let memory_config = nes2.memory_config();
+        assert_eq!(memory_config.prg_ram, Some(8 * 1024));
+        assert_eq!(memory_config.chr_ram, Some(8 * 1024));
+        assert!(!nes2.has_battery());
+    }
+
+    #[test]
+    fn test_nvram_sizes_imply_battery() {
+        // 8KB PRG NVRAM next to 2KB PRG RAM, no CHR RAM
+        let nes2 = nes_2_with_ram_sizes(0x75, 0x00);
+
+        assert_eq!(nes2.header.prg_ram_bytes(), 2 * 1024);
+        assert_eq!(nes2.header.prg_nvram_bytes(), 8 * 1024);
+        let memory_config = nes2.memory_config();
+        assert_eq!(memory_config.prg_ram, Some(10 * 1024));
+        assert!(!memory_config.has_chr_ram());
+        assert!(nes2.has_battery());
+    }
+
+    #[test]
+    fn test_chr_nvram_implies_battery() {
+        let nes2 = nes_2_with_ram_sizes(0x00, 0x70);
+
+        assert_eq!(nes2.header.chr_nvram_bytes(), 8 * 1024);
+        let memory_config = nes2.memory_config();
+        assert!(!memory_config.has_prg_ram());
+        assert_eq!(memory_config.chr_ram, Some(8 * 1024));
+        assert!(nes2.has_battery());
+    }
+
+    #[test]
+    fn test_rom_size_in_units() {
+        assert_eq!(rom_size(2, 0, PRG_UNIT_SIZE).unwrap(), 2 * 16 * 1024);
+        assert_eq!(rom_size(1, 0, CHR_UNIT_SIZE).unwrap(), 8 * 1024);
+        assert_eq!(
+            rom_size(0x00, 0x01, PRG_UNIT_SIZE).unwrap(),
+            0x100 * 16 * 1024
+        );
+    }
+
+    #[test]
+    fn test_rom_size_in_exponent_form() {
+        // E = 5, MM = 1: 2^5 * 3
+        assert_eq!(rom_size(0b0001_0101, 0x0F, PRG_UNIT_SIZE).unwrap(), 96);
+        // E = 10, MM = 0: 2^10 * 1
+        assert_eq!(rom_size(0b0010_1000, 0x0F, CHR_UNIT_SIZE).unwrap(), 1024);
+    }
+
+    #[test]
+    fn test_rom_size_overflow_is_an_error() {
+        // 2^63 * 3 does not fit in usize
+        assert!(matches!(
+            rom_size(0b1111_1101, 0x0F, PRG_UNIT_SIZE),
+            Err(NesRomReadError::RomSizeOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_from_reader_rejects_overflowing_rom_size() {
+        let mut data = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            0b1111_1111,
+            0x00,
+            0x00,
+            0x08,
+            0x00,
+            0x0F,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        data.extend(vec![0xAA; PRG_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let error = Nes2::from_reader(&mut cursor).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<NesRomReadError>(),
+            Some(NesRomReadError::RomSizeOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_from_reader_rejects_rom_size_past_end_of_file() {
+        // 2^62 bytes fits in usize but not in the file
+        let mut data = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            0b1111_1000,
+            0x00,
+            0x00,
+            0x08,
+            0x00,
+            0x0F,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        data.extend(vec![0xAA; PRG_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let error = Nes2::from_reader(&mut cursor).unwrap_err();
+
+        assert_eq!(
+            error
+                .downcast_ref::<std::io::Error>()
+                .map(|error| error.kind()),
+            Some(std::io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_from_reader_with_exponent_sizes() {
+        let mut data = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            0b0001_0101,
+            0b0010_1000,
+            0x00,
+            0x08,
+            0x00,
+            0xFF,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+            0x00,
+        ];
+        data.extend(vec![0xAA; 96]);
+        data.extend(vec![0xBB; 1024]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let nes2 = Nes2::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(nes2.header.prg_rom_bytes().unwrap(), 96);
+        assert_eq!(nes2.header.chr_rom_bytes().unwrap(), 1024);
+        assert_eq!(nes2.prg_rom().size(), 96);
+        assert_eq!(nes2.chr_rom().unwrap().size(), 1024);
+    }
+
+    #[test]
+    fn test_from_reader_reads_chr_in_chr_units() {
+        let mut data = vec![
+            b'N', b'E', b'S', 0x1A, 0x01, 0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        data.extend(vec![0xAA; PRG_UNIT_SIZE as usize]);
+        data.extend(vec![0xBB; CHR_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let nes2 = Nes2::from_reader(&mut cursor).unwrap();
+
+        assert_eq!(nes2.prg_rom().size(), PRG_UNIT_SIZE as usize);
+        assert_eq!(nes2.chr_rom().unwrap().size(), CHR_UNIT_SIZE as usize);
+    }
+
+    #[test]
+    fn test_from_reader_rejects_empty_prg_rom() {
+        let mut data = vec![
+            b'N', b'E', b'S', 0x1A, 0x00, 0x01, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+        data.extend(vec![0xBB; CHR_UNIT_SIZE as usize]);
+        let mut cursor = std::io::Cursor::new(data);
+
+        let error = Nes2::from_reader(&mut cursor).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<NesRomReadError>(),
+            Some(NesRomReadError::NoPrgRom)
+        ));
     }
 }
@@ -0,0 +1,235 @@
+use crate::addressing::Addressable;
+use crate::cartridge::common::consts::CHR_UNIT_SIZE;
+use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
+use crate::cartridge::common::traits::cartridge_data::CartridgeData;
+use crate::cartridge::common::traits::mapper::Mapper;
+use crate::cartridge::common::utils::mapper::default_chr_ram_size;
+use crate::cartridge::registers::chr_ram::ChrRam;
+use crate::cartridge::registers::chr_rom::ChrRom;
+use crate::cartridge::registers::prg_ram::PrgRam;
+use crate::cartridge::registers::prg_rom::PrgRom;
+use log::warn;
+use std::fmt::Debug;
+
+const PRG_RAM_START_ADDRESS: u16 = 0x6000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const PRG_ROM_START_ADDRESS: u16 = 0x8000;
+
+// Mapper 3, see https://www.nesdev.org/wiki/CNROM
+// CPU 0x8000-0xFFFF: 16KB PRG ROM mirrored twice, or 32KB PRG ROM, as on NROM
+// PPU 0x0000-0x1FFF: 8KB switchable CHR ROM bank, or CHR RAM when the image has no CHR ROM
+// Any write to 0x8000-0xFFFF selects the CHR bank, the unconnected high bits are dropped
+pub struct Cnrom {
+    prg_rom: PrgRom,
+    prg_ram: Option<PrgRam>,
+    chr_rom: Option<ChrRom>,
+    chr_ram: Option<ChrRam>,
+    chr_bank: u8,
+    mirroring: Mirroring,
+}
+
+impl Cnrom {
+    // 8KB PRG RAM, and 8KB CHR RAM when the board has no CHR ROM
+    pub fn new(prg_rom: PrgRom, chr_rom: Option<ChrRom>, mirroring: Mirroring) -> Cnrom {
+        let chr_ram_size = default_chr_ram_size(&chr_rom);
+        Cnrom::with_ram_sizes(prg_rom, chr_rom, mirroring, PRG_RAM_SIZE, chr_ram_size)
+    }
+
+    // RAM sizes as given by a NES 2.0 header, 0 leaves the chip off the board
+    pub fn with_ram_sizes(
+        prg_rom: PrgRom,
+        chr_rom: Option<ChrRom>,
+        mirroring: Mirroring,
+        prg_ram_size: usize,
+        chr_ram_size: usize,
+    ) -> Cnrom {
+        Cnrom {
+            prg_rom,
+            prg_ram: (prg_ram_size != 0).then(|| PrgRam::new(prg_ram_size)),
+            chr_rom,
+            chr_ram: (chr_ram_size != 0).then(|| ChrRam::new(chr_ram_size)),
+            chr_bank: 0,
+            mirroring,
+        }
+    }
+
+    fn chr_bank_count(&self) -> usize {
+        let chr_rom_size = self.chr_rom.as_ref().map_or(0, |x| x.size());
+        (chr_rom_size / CHR_UNIT_SIZE as usize).max(1)
+    }
+
+    fn select_chr_bank(&mut self, data: u8) {
+        self.chr_bank = (data as usize % self.chr_bank_count()) as u8;
+    }
+}
+
+impl Debug for Cnrom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cnrom")
+            .field("prg_rom_size", &self.prg_rom.size())
+            .field("chr_rom_size", &self.chr_rom.as_ref().map(|x| x.size()))
+            .field("chr_bank", &self.chr_bank)
+            .field("mirroring", &self.mirroring)
+            .finish()
+    }
+}
+
+impl CartridgeData for Cnrom {
+    fn prg_rom(&self) -> &PrgRom {
+        &self.prg_rom
+    }
+
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.chr_rom.as_ref()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.chr_ram.as_ref()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        MemoryConfig {
+            prg_rom: Some(self.prg_rom.size()),
+            prg_ram: self.prg_ram.as_ref().map(|x| x.size()),
+            chr_rom: self.chr_rom.as_ref().map(|x| x.size()),
+            chr_ram: self.chr_ram.as_ref().map(|x| x.size()),
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            PRG_ROM_START_ADDRESS..=0xFFFF => self
+                .prg_rom
+                .read_mirrored((address - PRG_ROM_START_ADDRESS) as usize),
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS => self
+                .prg_ram
+                .as_ref()
+                .and_then(|prg_ram| prg_ram.peek(address))
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_ROM_START_ADDRESS..=0xFFFF => self.select_chr_bank(data),
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS if self.prg_ram.is_some() => {
+                if let Some(prg_ram) = self.prg_ram.as_mut() {
+                    prg_ram.write(address, data);
+                }
+            }
+            _ => warn!(
+                "Ignoring CNROM write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let offset =
+            self.chr_bank as usize * CHR_UNIT_SIZE as usize + (address % CHR_UNIT_SIZE) as usize;
+        match (self.chr_rom.as_ref(), self.chr_ram.as_mut()) {
+            (Some(chr_rom), _) => chr_rom.read_mirrored(offset),
+            (None, Some(chr_ram)) => chr_ram.read(address % CHR_UNIT_SIZE),
+            (None, None) => 0,
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        match self.chr_ram.as_mut() {
+            Some(chr_ram) => chr_ram.write(address % CHR_UNIT_SIZE, data),
+            None => warn!(
+                "Ignoring CNROM CHR ROM write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> Option<&PrgRam> {
+        self.prg_ram.as_ref()
+    }
+
+    fn prg_ram_mut(&mut self) -> Option<&mut PrgRam> {
+        self.prg_ram.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::common::consts::PRG_UNIT_SIZE;
+
+    // Every byte of a bank holds the bank number
+    fn cnrom_with_chr_banks(banks: usize) -> Cnrom {
+        let chr = (0..banks)
+            .flat_map(|bank| vec![bank as u8; CHR_UNIT_SIZE as usize])
+            .collect();
+        Cnrom::new(
+            PrgRom::new_with_data(vec![0xEA; PRG_UNIT_SIZE as usize]),
+            Some(ChrRom::new_with_data(chr)),
+            Mirroring::Vertical,
+        )
+    }
+
+    #[test]
+    fn test_power_on_selects_first_chr_bank() {
+        let mut cnrom = cnrom_with_chr_banks(4);
+
+        assert_eq!(cnrom.ppu_read(0x0000), 0);
+        assert_eq!(cnrom.ppu_read(0x1FFF), 0);
+    }
+
+    #[test]
+    fn test_write_selects_chr_bank() {
+        let mut cnrom = cnrom_with_chr_banks(4);
+
+        cnrom.cpu_write(0x8000, 2);
+        assert_eq!(cnrom.ppu_read(0x0000), 2);
+        assert_eq!(cnrom.ppu_read(0x1FFF), 2);
+
+        cnrom.cpu_write(0xFFFF, 3);
+        assert_eq!(cnrom.ppu_read(0x1000), 3);
+    }
+
+    #[test]
+    fn test_bank_select_is_masked_to_bank_count() {
+        let mut cnrom = cnrom_with_chr_banks(4);
+
+        cnrom.cpu_write(0x8000, 0x07);
+        assert_eq!(cnrom.ppu_read(0x0000), 3);
+
+        cnrom.cpu_write(0x8000, 0xFC);
+        assert_eq!(cnrom.ppu_read(0x0000), 0);
+    }
+
+    #[test]
+    fn test_chr_ram_without_chr_rom() {
+        let mut cnrom = Cnrom::new(
+            PrgRom::new_with_data(vec![0xEA; PRG_UNIT_SIZE as usize]),
+            None,
+            Mirroring::Vertical,
+        );
+
+        cnrom.ppu_write(0x0123, 0x45);
+
+        assert_eq!(cnrom.ppu_read(0x0123), 0x45);
+        assert!(cnrom.memory_config().has_chr_ram());
+    }
+
+    #[test]
+    fn test_prg_rom_is_fixed() {
+        let mut cnrom = cnrom_with_chr_banks(4);
+
+        cnrom.cpu_write(0x8000, 1);
+
+        assert_eq!(cnrom.cpu_read(0x8000), 0xEA);
+        assert_eq!(cnrom.cpu_read(0xC000), 0xEA);
+    }
+}
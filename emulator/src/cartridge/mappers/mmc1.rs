@@ -0,0 +1,441 @@
+use crate::addressing::Addressable;
+use crate::cartridge::common::consts::{CHR_UNIT_SIZE, PRG_UNIT_SIZE};
+use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
+use crate::cartridge::common::traits::cartridge_data::CartridgeData;
+use crate::cartridge::common::traits::mapper::{Mapper, MirroringListener};
+use crate::cartridge::common::utils::mapper::default_chr_ram_size;
+use crate::cartridge::registers::chr_ram::ChrRam;
+use crate::cartridge::registers::chr_rom::ChrRom;
+use crate::cartridge::registers::prg_ram::PrgRam;
+use crate::cartridge::registers::prg_rom::PrgRom;
+use log::warn;
+use std::fmt::Debug;
+
+const PRG_RAM_START_ADDRESS: u16 = 0x6000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const PRG_ROM_START_ADDRESS: u16 = 0x8000;
+const PRG_ROM_UPPER_BANK_ADDRESS: u16 = 0xC000;
+const CHR_BANK_SIZE: usize = 0x1000;
+
+// The shift register is full once the marker bit reaches bit 0
+const SHIFT_REGISTER_RESET: u8 = 0b1_0000;
+const SHIFT_REGISTER_RESET_BIT: u8 = 0b1000_0000;
+// PRG mode 3: 0x8000 switchable, 0xC000 fixed to the last bank
+const CONTROL_PRG_MODE_FIX_LAST: u8 = 0b0_1100;
+
+const CONTROL_REGISTER_ADDRESS: u16 = 0x8000;
+const CHR_BANK_0_REGISTER_ADDRESS: u16 = 0xA000;
+const CHR_BANK_1_REGISTER_ADDRESS: u16 = 0xC000;
+const PRG_BANK_REGISTER_ADDRESS: u16 = 0xE000;
+
+// Mapper 1, see https://www.nesdev.org/wiki/MMC1
+// CPU 0x6000-0x7FFF: 8KB PRG RAM, can be disabled with bit 4 of the PRG bank register
+// CPU 0x8000-0xFFFF: two 16KB PRG ROM windows, or one 32KB window
+// PPU 0x0000-0x1FFF: two 4KB CHR windows, or one 8KB window
+// Registers are loaded serially, one bit per write, through a 5-bit shift register
+pub struct Mmc1 {
+    prg_rom: PrgRom,
+    prg_ram: Option<PrgRam>,
+    chr_rom: Option<ChrRom>,
+    chr_ram: Option<ChrRam>,
+    shift_register: u8,
+    // 4bit0
+    // -----
+    // CPPMM
+    // |||||
+    // |||++- Mirroring (0: one-screen, lower bank; 1: one-screen, upper bank;
+    // |||               2: vertical; 3: horizontal)
+    // |++--- PRG ROM bank mode (0, 1: switch 32 KB at $8000, ignoring low bit of bank number;
+    // |                         2: fix first bank at $8000 and switch 16 KB bank at $C000;
+    // |                         3: fix last bank at $C000 and switch 16 KB bank at $8000)
+    // +----- CHR ROM bank mode (0: switch 8 KB at a time; 1: switch two separate 4 KB banks)
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    // 4bit0
+    // -----
+    // RPPPP
+    // |||||
+    // |++++- Select 16 KB PRG ROM bank (low bit ignored in 32 KB mode)
+    // +----- PRG RAM chip enable (0: enabled; 1: disabled)
+    prg_bank: u8,
+    mirroring_listener: Option<MirroringListener>,
+}
+
+impl Mmc1 {
+    // 8KB PRG RAM, and 8KB CHR RAM when the board has no CHR ROM
+    pub fn new(prg_rom: PrgRom, chr_rom: Option<ChrRom>) -> Mmc1 {
+        let chr_ram_size = default_chr_ram_size(&chr_rom);
+        Mmc1::with_ram_sizes(prg_rom, chr_rom, PRG_RAM_SIZE, chr_ram_size)
+    }
+
+    // RAM sizes as given by a NES 2.0 header, 0 leaves the chip off the board
+    pub fn with_ram_sizes(
+        prg_rom: PrgRom,
+        chr_rom: Option<ChrRom>,
+        prg_ram_size: usize,
+        chr_ram_size: usize,
+    ) -> Mmc1 {
+        Mmc1 {
+            prg_rom,
+            prg_ram: (prg_ram_size != 0).then(|| PrgRam::new(prg_ram_size)),
+            chr_rom,
+            chr_ram: (chr_ram_size != 0).then(|| ChrRam::new(chr_ram_size)),
+            shift_register: SHIFT_REGISTER_RESET,
+            control: CONTROL_PRG_MODE_FIX_LAST,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            mirroring_listener: None,
+        }
+    }
+
+    fn write_shift_register(&mut self, address: u16, data: u8) {
+        if data & SHIFT_REGISTER_RESET_BIT != 0 {
+            self.shift_register = SHIFT_REGISTER_RESET;
+            self.control |= CONTROL_PRG_MODE_FIX_LAST;
+            return;
+        }
+
+        let is_full = self.shift_register & 1 != 0;
+        self.shift_register = (self.shift_register >> 1) | ((data & 1) << 4);
+
+        if is_full {
+            let value = self.shift_register;
+            match address {
+                CONTROL_REGISTER_ADDRESS..CHR_BANK_0_REGISTER_ADDRESS => self.write_control(value),
+                CHR_BANK_0_REGISTER_ADDRESS..CHR_BANK_1_REGISTER_ADDRESS => self.chr_bank_0 = value,
+                CHR_BANK_1_REGISTER_ADDRESS..PRG_BANK_REGISTER_ADDRESS => self.chr_bank_1 = value,
+                _ => self.prg_bank = value,
+            }
+            self.shift_register = SHIFT_REGISTER_RESET;
+        }
+    }
+
+    fn write_control(&mut self, value: u8) {
+        let mirroring = self.mirroring();
+        self.control = value;
+        if self.mirroring() != mirroring {
+            let mirroring = self.mirroring();
+            if let Some(listener) = self.mirroring_listener.as_mut() {
+                listener(mirroring);
+            }
+        }
+    }
+
+    fn prg_rom_bank_count(&self) -> usize {
+        (self.prg_rom.size() / PRG_UNIT_SIZE as usize).max(1)
+    }
+
+    fn prg_rom_offset(&self, address: u16) -> usize {
+        let bank = (self.prg_bank & 0x0F) as usize;
+        let offset = (address & 0x3FFF) as usize;
+        let is_upper_window = address >= PRG_ROM_UPPER_BANK_ADDRESS;
+
+        let bank = match (self.control >> 2) & 0b11 {
+            0 | 1 => (bank & !1) | is_upper_window as usize,
+            2 if is_upper_window => bank,
+            2 => 0,
+            _ if is_upper_window => self.prg_rom_bank_count() - 1,
+            _ => bank,
+        };
+
+        bank * PRG_UNIT_SIZE as usize + offset
+    }
+
+    fn is_prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0b1_0000 == 0
+    }
+
+    fn chr_offset(&self, address: u16) -> usize {
+        let address = (address % CHR_UNIT_SIZE) as usize;
+
+        if self.control & 0b1_0000 == 0 {
+            // 8KB mode, low bit of the bank number is ignored
+            (self.chr_bank_0 & !1) as usize * CHR_BANK_SIZE + address
+        } else if address < CHR_BANK_SIZE {
+            self.chr_bank_0 as usize * CHR_BANK_SIZE + address
+        } else {
+            self.chr_bank_1 as usize * CHR_BANK_SIZE + (address - CHR_BANK_SIZE)
+        }
+    }
+}
+
+impl Debug for Mmc1 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mmc1")
+            .field("prg_rom_size", &self.prg_rom.size())
+            .field("chr_rom_size", &self.chr_rom.as_ref().map(|x| x.size()))
+            .field("shift_register", &self.shift_register)
+            .field("control", &self.control)
+            .field("chr_bank_0", &self.chr_bank_0)
+            .field("chr_bank_1", &self.chr_bank_1)
+            .field("prg_bank", &self.prg_bank)
+            .field(
+                "mirroring_listener",
+                &self
+                    .mirroring_listener
+                    .as_ref()
+                    .map(|_| "MirroringListener"),
+            )
+            .finish()
+    }
+}
+
+impl CartridgeData for Mmc1 {
+    fn prg_rom(&self) -> &PrgRom {
+        &self.prg_rom
+    }
+
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.chr_rom.as_ref()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.chr_ram.as_ref()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        MemoryConfig {
+            prg_rom: Some(self.prg_rom.size()),
+            prg_ram: self.prg_ram.as_ref().map(|x| x.size()),
+            chr_rom: self.chr_rom.as_ref().map(|x| x.size()),
+            chr_ram: self.chr_ram.as_ref().map(|x| x.size()),
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            PRG_ROM_START_ADDRESS..=0xFFFF => {
+                self.prg_rom.read_mirrored(self.prg_rom_offset(address))
+            }
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS if self.is_prg_ram_enabled() => self
+                .prg_ram
+                .as_ref()
+                .and_then(|prg_ram| prg_ram.peek(address))
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_ROM_START_ADDRESS..=0xFFFF => self.write_shift_register(address, data),
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS if self.is_prg_ram_enabled() => {
+                if let Some(prg_ram) = self.prg_ram.as_mut() {
+                    prg_ram.write(address, data);
+                }
+            }
+            _ => warn!(
+                "Ignoring MMC1 write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let offset = self.chr_offset(address);
+        match (self.chr_rom.as_ref(), self.chr_ram.as_ref()) {
+            (Some(chr_rom), _) => chr_rom.read_mirrored(offset),
+            (None, Some(chr_ram)) => chr_ram.read_mirrored(offset),
+            (None, None) => 0,
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        let offset = self.chr_offset(address);
+        match self.chr_ram.as_mut() {
+            Some(chr_ram) => chr_ram.write_mirrored(offset, data),
+            None => warn!(
+                "Ignoring MMC1 CHR ROM write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn set_mirroring_listener(&mut self, listener: MirroringListener) {
+        self.mirroring_listener = Some(listener);
+    }
+
+    fn prg_ram(&self) -> Option<&PrgRam> {
+        self.prg_ram.as_ref()
+    }
+
+    fn prg_ram_mut(&mut self) -> Option<&mut PrgRam> {
+        self.prg_ram.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every byte of a bank holds the bank number
+    fn prg_rom_with_banks(banks: usize) -> PrgRom {
+        let data = (0..banks)
+            .flat_map(|bank| vec![bank as u8; PRG_UNIT_SIZE as usize])
+            .collect();
+        PrgRom::new_with_data(data)
+    }
+
+    fn chr_rom_with_banks(banks: usize) -> ChrRom {
+        let data = (0..banks)
+            .flat_map(|bank| vec![bank as u8; CHR_BANK_SIZE])
+            .collect();
+        ChrRom::new_with_data(data)
+    }
+
+    fn write_register(mmc1: &mut Mmc1, address: u16, value: u8) {
+        for bit in 0..5 {
+            mmc1.cpu_write(address, (value >> bit) & 1);
+        }
+    }
+
+    #[test]
+    fn test_power_on_fixes_last_bank() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+
+        assert_eq!(mmc1.cpu_read(0x8000), 0);
+        assert_eq!(mmc1.cpu_read(0xC000), 7);
+        assert_eq!(mmc1.cpu_read(0xFFFF), 7);
+    }
+
+    #[test]
+    fn test_serial_write_selects_prg_bank() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+
+        write_register(&mut mmc1, PRG_BANK_REGISTER_ADDRESS, 5);
+
+        assert_eq!(mmc1.cpu_read(0x8000), 5);
+        assert_eq!(mmc1.cpu_read(0xBFFF), 5);
+        assert_eq!(mmc1.cpu_read(0xC000), 7);
+    }
+
+    #[test]
+    fn test_partial_serial_write_does_not_switch_bank() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+
+        for _ in 0..4 {
+            mmc1.cpu_write(PRG_BANK_REGISTER_ADDRESS, 1);
+        }
+
+        assert_eq!(mmc1.cpu_read(0x8000), 0);
+    }
+
+    #[test]
+    fn test_reset_bit_clears_shift_register_and_sets_prg_mode_3() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+        // PRG mode 2: fix first bank at 0x8000
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1000);
+        mmc1.cpu_write(PRG_BANK_REGISTER_ADDRESS, 1);
+        mmc1.cpu_write(PRG_BANK_REGISTER_ADDRESS, 1);
+
+        mmc1.cpu_write(0x8000, SHIFT_REGISTER_RESET_BIT);
+        write_register(&mut mmc1, PRG_BANK_REGISTER_ADDRESS, 2);
+
+        assert_eq!(mmc1.shift_register, SHIFT_REGISTER_RESET);
+        assert_eq!(mmc1.cpu_read(0x8000), 2);
+        assert_eq!(mmc1.cpu_read(0xC000), 7);
+    }
+
+    #[test]
+    fn test_prg_mode_2_fixes_first_bank() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1000);
+        write_register(&mut mmc1, PRG_BANK_REGISTER_ADDRESS, 3);
+
+        assert_eq!(mmc1.cpu_read(0x8000), 0);
+        assert_eq!(mmc1.cpu_read(0xC000), 3);
+    }
+
+    #[test]
+    fn test_prg_mode_32kb_ignores_low_bit() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(8), None);
+
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_0000);
+        write_register(&mut mmc1, PRG_BANK_REGISTER_ADDRESS, 5);
+
+        assert_eq!(mmc1.cpu_read(0x8000), 4);
+        assert_eq!(mmc1.cpu_read(0xC000), 5);
+    }
+
+    #[test]
+    fn test_chr_4kb_banks() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(2), Some(chr_rom_with_banks(8)));
+
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b1_1100);
+        write_register(&mut mmc1, CHR_BANK_0_REGISTER_ADDRESS, 3);
+        write_register(&mut mmc1, CHR_BANK_1_REGISTER_ADDRESS, 6);
+
+        assert_eq!(mmc1.ppu_read(0x0000), 3);
+        assert_eq!(mmc1.ppu_read(0x1000), 6);
+    }
+
+    #[test]
+    fn test_chr_8kb_bank() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(2), Some(chr_rom_with_banks(8)));
+
+        write_register(&mut mmc1, CHR_BANK_0_REGISTER_ADDRESS, 5);
+
+        assert_eq!(mmc1.ppu_read(0x0000), 4);
+        assert_eq!(mmc1.ppu_read(0x1000), 5);
+    }
+
+    #[test]
+    fn test_mirroring_selection() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(2), None);
+
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1100);
+        assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenLower);
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1101);
+        assert_eq!(mmc1.mirroring(), Mirroring::SingleScreenUpper);
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1110);
+        assert_eq!(mmc1.mirroring(), Mirroring::Vertical);
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1111);
+        assert_eq!(mmc1.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn test_mirroring_listener_is_notified_on_change() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(2), None);
+        let changes = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let listener_changes = changes.clone();
+        mmc1.set_mirroring_listener(Box::new(move |mirroring| {
+            listener_changes.borrow_mut().push(mirroring)
+        }));
+
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_1110);
+        // Same mirroring, only the PRG mode changes
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_0010);
+        write_register(&mut mmc1, CONTROL_REGISTER_ADDRESS, 0b0_0001);
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![Mirroring::Vertical, Mirroring::SingleScreenUpper]
+        );
+    }
+
+    #[test]
+    fn test_prg_ram_can_be_disabled() {
+        let mut mmc1 = Mmc1::new(prg_rom_with_banks(2), None);
+        mmc1.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc1.cpu_read(0x6000), 0x42);
+
+        write_register(&mut mmc1, PRG_BANK_REGISTER_ADDRESS, 0b1_0000);
+
+        assert_eq!(mmc1.cpu_read(0x6000), 0);
+    }
+}
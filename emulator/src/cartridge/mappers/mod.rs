@@ -0,0 +1,3 @@
+pub mod cnrom;
+pub mod mmc1;
+pub mod nrom;
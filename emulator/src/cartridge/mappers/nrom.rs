@@ -0,0 +1,262 @@
+use crate::addressing::Addressable;
+use crate::cartridge::common::consts::CHR_UNIT_SIZE;
+use crate::cartridge::common::enums::mirroring::Mirroring;
+use crate::cartridge::common::structs::memory_config::MemoryConfig;
+use crate::cartridge::common::traits::cartridge_data::CartridgeData;
+use crate::cartridge::common::traits::mapper::Mapper;
+use crate::cartridge::common::utils::mapper::default_chr_ram_size;
+use crate::cartridge::registers::chr_ram::ChrRam;
+use crate::cartridge::registers::chr_rom::ChrRom;
+use crate::cartridge::registers::prg_ram::PrgRam;
+use crate::cartridge::registers::prg_rom::PrgRom;
+use log::warn;
+use std::fmt::Debug;
+
+const PRG_RAM_START_ADDRESS: u16 = 0x6000;
+const PRG_RAM_SIZE: usize = 0x2000;
+const PRG_ROM_START_ADDRESS: u16 = 0x8000;
+
+// Mapper 0, see https://www.nesdev.org/wiki/NROM
+// CPU 0x6000-0x7FFF: PRG RAM (Family Basic only, harmless for other boards)
+// CPU 0x8000-0xFFFF: 16KB PRG ROM mirrored twice, or 32KB PRG ROM
+// PPU 0x0000-0x1FFF: 8KB CHR ROM, or CHR RAM when the board has no CHR ROM
+pub struct Nrom {
+    prg_rom: PrgRom,
+    prg_ram: Option<PrgRam>,
+    chr_rom: Option<ChrRom>,
+    chr_ram: Option<ChrRam>,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    // 8KB PRG RAM, and 8KB CHR RAM when the board has no CHR ROM
+    pub fn new(prg_rom: PrgRom, chr_rom: Option<ChrRom>, mirroring: Mirroring) -> Nrom {
+        let chr_ram_size = default_chr_ram_size(&chr_rom);
+        Nrom::with_ram_sizes(prg_rom, chr_rom, mirroring, PRG_RAM_SIZE, chr_ram_size)
+    }
+
+    // RAM sizes as given by a NES 2.0 header, 0 leaves the chip off the board
+    pub fn with_ram_sizes(
+        prg_rom: PrgRom,
+        chr_rom: Option<ChrRom>,
+        mirroring: Mirroring,
+        prg_ram_size: usize,
+        chr_ram_size: usize,
+    ) -> Nrom {
+        Nrom {
+            prg_rom,
+            prg_ram: (prg_ram_size != 0).then(|| PrgRam::new(prg_ram_size)),
+            chr_rom,
+            chr_ram: (chr_ram_size != 0).then(|| ChrRam::new(chr_ram_size)),
+            mirroring,
+        }
+    }
+}
+
+impl Debug for Nrom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nrom")
+            .field("prg_rom_size", &self.prg_rom.size())
+            .field("chr_rom_size", &self.chr_rom.as_ref().map(|x| x.size()))
+            .field("mirroring", &self.mirroring)
+            .finish()
+    }
+}
+
+impl CartridgeData for Nrom {
+    fn prg_rom(&self) -> &PrgRom {
+        &self.prg_rom
+    }
+
+    fn chr_rom(&self) -> Option<&ChrRom> {
+        self.chr_rom.as_ref()
+    }
+
+    fn chr_ram(&self) -> Option<&ChrRam> {
+        self.chr_ram.as_ref()
+    }
+
+    fn memory_config(&self) -> MemoryConfig {
+        MemoryConfig {
+            prg_rom: Some(self.prg_rom.size()),
+            prg_ram: self.prg_ram.as_ref().map(|x| x.size()),
+            chr_rom: self.chr_rom.as_ref().map(|x| x.size()),
+            chr_ram: self.chr_ram.as_ref().map(|x| x.size()),
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            PRG_ROM_START_ADDRESS..=0xFFFF => self
+                .prg_rom
+                .read_mirrored((address - PRG_ROM_START_ADDRESS) as usize),
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS => self
+                .prg_ram
+                .as_ref()
+                .and_then(|prg_ram| prg_ram.peek(address))
+                .unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            PRG_RAM_START_ADDRESS..PRG_ROM_START_ADDRESS if self.prg_ram.is_some() => {
+                if let Some(prg_ram) = self.prg_ram.as_mut() {
+                    prg_ram.write(address, data);
+                }
+            }
+            _ => warn!(
+                "Ignoring NROM write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        let address = address % CHR_UNIT_SIZE;
+        match (self.chr_rom.as_mut(), self.chr_ram.as_mut()) {
+            (Some(chr_rom), _) => chr_rom.read(address),
+            (None, Some(chr_ram)) => chr_ram.read(address),
+            (None, None) => 0,
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        let address = address % CHR_UNIT_SIZE;
+        match self.chr_ram.as_mut() {
+            Some(chr_ram) => chr_ram.write(address, data),
+            None => warn!(
+                "Ignoring NROM CHR ROM write at address {:#06X} with data {:#04X}",
+                address, data
+            ),
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> Option<&PrgRam> {
+        self.prg_ram.as_ref()
+    }
+
+    fn prg_ram_mut(&mut self) -> Option<&mut PrgRam> {
+        self.prg_ram.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::common::consts::PRG_UNIT_SIZE;
+
+    fn prg_rom_with_reset_vector(banks: usize, reset_vector: u16) -> PrgRom {
+        let size = banks * PRG_UNIT_SIZE as usize;
+        let mut data = vec![0; size];
+        data[size - 4] = (reset_vector & 0xFF) as u8;
+        data[size - 3] = (reset_vector >> 8) as u8;
+        PrgRom::new_with_data(data)
+    }
+
+    #[test]
+    fn test_reset_vector_16kb_is_mirrored() {
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(1, 0xC123),
+            None,
+            Mirroring::Horizontal,
+        );
+
+        assert_eq!(nrom.cpu_read(0xFFFC), 0x23);
+        assert_eq!(nrom.cpu_read(0xFFFD), 0xC1);
+        assert_eq!(nrom.cpu_read(0xBFFC), 0x23);
+        assert_eq!(nrom.cpu_read(0xBFFD), 0xC1);
+    }
+
+    #[test]
+    fn test_reset_vector_32kb() {
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(2, 0x8042),
+            None,
+            Mirroring::Horizontal,
+        );
+
+        assert_eq!(nrom.cpu_read(0xFFFC), 0x42);
+        assert_eq!(nrom.cpu_read(0xFFFD), 0x80);
+        assert_eq!(nrom.cpu_read(0xBFFC), 0x00);
+    }
+
+    #[test]
+    fn test_prg_rom_is_read_only() {
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(1, 0xC123),
+            None,
+            Mirroring::Horizontal,
+        );
+
+        nrom.cpu_write(0xFFFC, 0xFF);
+
+        assert_eq!(nrom.cpu_read(0xFFFC), 0x23);
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(1, 0xC123),
+            None,
+            Mirroring::Horizontal,
+        );
+
+        nrom.cpu_write(0x6000, 0x12);
+        nrom.cpu_write(0x7FFF, 0x34);
+
+        assert_eq!(nrom.cpu_read(0x6000), 0x12);
+        assert_eq!(nrom.cpu_read(0x7FFF), 0x34);
+    }
+
+    #[test]
+    fn test_chr_rom() {
+        let mut chr = vec![0; CHR_UNIT_SIZE as usize];
+        chr[0x1FFF] = 0xAB;
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(1, 0xC123),
+            Some(ChrRom::new_with_data(chr)),
+            Mirroring::Horizontal,
+        );
+
+        nrom.ppu_write(0x1FFF, 0x00);
+
+        assert_eq!(nrom.ppu_read(0x1FFF), 0xAB);
+    }
+
+    #[test]
+    fn test_chr_ram_without_chr_rom() {
+        let mut nrom = Nrom::new(
+            prg_rom_with_reset_vector(1, 0xC123),
+            None,
+            Mirroring::Horizontal,
+        );
+
+        nrom.ppu_write(0x0010, 0xCD);
+
+        assert_eq!(nrom.ppu_read(0x0010), 0xCD);
+    }
+
+    #[test]
+    fn test_memory_config_with_chr_rom() {
+        let nrom = Nrom::new(
+            prg_rom_with_reset_vector(2, 0xC123),
+            Some(ChrRom::new_with_data(vec![0; CHR_UNIT_SIZE as usize])),
+            Mirroring::Horizontal,
+        );
+
+        let memory_config = nrom.memory_config();
+
+        assert_eq!(memory_config.prg_rom, Some(2 * PRG_UNIT_SIZE as usize));
+        assert_eq!(memory_config.prg_ram, Some(PRG_RAM_SIZE));
+        assert_eq!(memory_config.chr_rom, Some(CHR_UNIT_SIZE as usize));
+        assert!(!memory_config.has_chr_ram());
+    }
+}
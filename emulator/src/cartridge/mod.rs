@@ -1,5 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod cartridge;
 
 pub mod common;
 mod formats;
-mod registers;
+pub mod mappers;
+pub mod registers;
@@ -1,6 +1,10 @@
 use crate::addressing::Addressable;
 use std::fmt::Debug;
 
+pub const CHR_RAM_START_ADDRESS: u16 = 0x0000;
+pub const CHR_RAM_END_ADDRESS: u16 = 0x1FFF;
+
+// Pattern table RAM used by boards without CHR ROM, addressed with PPU addresses 0x0000-0x1FFF
 pub struct ChrRam {
     ram: Vec<u8>,
 }
@@ -13,18 +17,66 @@ impl Debug for ChrRam {
 
 impl Addressable for ChrRam {
     fn read(&mut self, address: u16) -> u8 {
-        self.ram[address as usize]
+        self.ram[self.offset(address)]
     }
 
     fn write(&mut self, address: u16, data: u8) {
-        self.ram[address as usize] = data;
+        let offset = self.offset(address);
+        self.ram[offset] = data;
     }
 }
 
 impl ChrRam {
+    pub fn size(&self) -> usize {
+        self.ram.len()
+    }
+
     pub fn new(size: usize) -> ChrRam {
-        ChrRam {
-            ram: Vec::with_capacity(size),
+        ChrRam { ram: vec![0; size] }
+    }
+
+    pub fn read_mirrored(&self, offset: usize) -> u8 {
+        self.ram[offset % self.ram.len()]
+    }
+
+    pub fn write_mirrored(&mut self, offset: usize, data: u8) {
+        let len = self.ram.len();
+        self.ram[offset % len] = data;
+    }
+
+    fn offset(&self, address: u16) -> usize {
+        match address {
+            CHR_RAM_START_ADDRESS..=CHR_RAM_END_ADDRESS => address as usize % self.ram.len(),
+            _ => panic!("CHR RAM access at address {:#06X} out of range", address),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chr_ram_read_write() {
+        let mut chr_ram = ChrRam::new(0x2000);
+
+        chr_ram.write(0x0000, 0x12);
+        chr_ram.write(0x1FFF, 0x34);
+
+        assert_eq!(chr_ram.read(0x0000), 0x12);
+        assert_eq!(chr_ram.read(0x1FFF), 0x34);
+        assert_eq!(chr_ram.read_mirrored(0x1FFF), 0x34);
+    }
+
+    #[test]
+    #[should_panic(expected = "CHR RAM access at address 0x2000 out of range")]
+    fn test_chr_ram_rejects_address_above_range() {
+        ChrRam::new(0x2000).read(0x2000);
+    }
+
+    #[test]
+    #[should_panic(expected = "CHR RAM access at address 0x3000 out of range")]
+    fn test_chr_ram_write_rejects_address_above_range() {
+        ChrRam::new(0x2000).write(0x3000, 0x01);
+    }
+}
@@ -32,4 +32,43 @@ impl ChrRom {
     pub fn size(&self) -> usize {
         self.rom.len()
     }
+
+    pub fn read_mirrored(&self, offset: usize) -> u8 {
+        self.rom[offset % self.rom.len()]
+    }
+
+    pub fn read(&self, offset: usize) -> u8 {
+        self.get(offset)
+            .unwrap_or_else(|| panic!("CHR ROM read at offset {:#06X} out of range", offset))
+    }
+
+    pub fn get(&self, offset: usize) -> Option<u8> {
+        self.rom.get(offset).copied()
+    }
+
+    // Bank numbers past the end wrap around, the way mappers ignore unconnected bank lines
+    pub fn bank(&self, index: usize, bank_size: usize) -> &[u8] {
+        let bank_count = (self.rom.len() / bank_size).max(1);
+        let start = (index % bank_count) * bank_size;
+        &self.rom[start..(start + bank_size).min(self.rom.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHR_BANK_SIZE: usize = 0x2000;
+
+    #[test]
+    fn test_bank_and_get() {
+        let mut data = vec![0x00; 2 * CHR_BANK_SIZE];
+        data[CHR_BANK_SIZE] = 0x34;
+        let chr_rom = ChrRom::new_with_data(data);
+
+        assert_eq!(chr_rom.bank(1, CHR_BANK_SIZE)[0], 0x34);
+        assert_eq!(chr_rom.bank(1, CHR_BANK_SIZE).len(), CHR_BANK_SIZE);
+        assert_eq!(chr_rom.get(CHR_BANK_SIZE), Some(0x34));
+        assert_eq!(chr_rom.get(2 * CHR_BANK_SIZE), None);
+    }
 }
@@ -1,6 +1,11 @@
 use crate::addressing::Addressable;
 use std::fmt::Debug;
 
+pub const PRG_RAM_START_ADDRESS: u16 = 0x6000;
+pub const PRG_RAM_END_ADDRESS: u16 = 0x7FFF;
+
+// Battery or work RAM on the cartridge, addressed with CPU addresses 0x6000-0x7FFF
+// Boards with less than 8KB see it mirrored across the whole window
 pub struct PrgRam {
     ram: Vec<u8>,
 }
@@ -13,18 +18,84 @@ impl Debug for PrgRam {
 
 impl Addressable for PrgRam {
     fn read(&mut self, address: u16) -> u8 {
-        self.ram[address as usize]
+        self.ram[self.offset(address)]
     }
 
     fn write(&mut self, address: u16, data: u8) {
-        self.ram[address as usize] = data;
+        let offset = self.offset(address);
+        self.ram[offset] = data;
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        Some(self.ram[self.offset(address)])
     }
 }
 
 impl PrgRam {
+    pub fn size(&self) -> usize {
+        self.ram.len()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.ram
+    }
+
+    // Copies as much of the data as fits, the rest of the RAM is left untouched
+    pub fn load(&mut self, data: &[u8]) {
+        let size = data.len().min(self.ram.len());
+        self.ram[..size].copy_from_slice(&data[..size]);
+    }
+
     pub fn new(size: usize) -> PrgRam {
-        PrgRam {
-            ram: Vec::with_capacity(size),
+        PrgRam { ram: vec![0; size] }
+    }
+
+    fn offset(&self, address: u16) -> usize {
+        match address {
+            PRG_RAM_START_ADDRESS..=PRG_RAM_END_ADDRESS => {
+                (address - PRG_RAM_START_ADDRESS) as usize % self.ram.len()
+            }
+            _ => panic!("PRG RAM access at address {:#06X} out of range", address),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prg_ram_read_write() {
+        let mut prg_ram = PrgRam::new(0x2000);
+
+        prg_ram.write(0x6000, 0x12);
+        prg_ram.write(0x7FFF, 0x34);
+
+        assert_eq!(prg_ram.read(0x6000), 0x12);
+        assert_eq!(prg_ram.read(0x7FFF), 0x34);
+        assert_eq!(prg_ram.data()[0], 0x12);
+        assert_eq!(prg_ram.data()[0x1FFF], 0x34);
+    }
+
+    #[test]
+    fn test_small_prg_ram_is_mirrored() {
+        let mut prg_ram = PrgRam::new(0x800);
+
+        prg_ram.write(0x6001, 0x56);
+
+        assert_eq!(prg_ram.read(0x6801), 0x56);
+        assert_eq!(prg_ram.read(0x7801), 0x56);
+    }
+
+    #[test]
+    #[should_panic(expected = "PRG RAM access at address 0x8000 out of range")]
+    fn test_prg_ram_rejects_address_above_range() {
+        PrgRam::new(0x2000).read(0x8000);
+    }
+
+    #[test]
+    #[should_panic(expected = "PRG RAM access at address 0x5FFF out of range")]
+    fn test_prg_ram_rejects_address_below_range() {
+        PrgRam::new(0x2000).write(0x5FFF, 0x01);
+    }
+}
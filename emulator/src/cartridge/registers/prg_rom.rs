@@ -1,5 +1,12 @@
 use crate::addressing::Addressable;
+use crate::cpu::cpu::RESET_VECTOR_ADDRESS;
+use crate::cpu::operations::Operation;
 use std::fmt::Debug;
+
+const PRG_ROM_START_ADDRESS: u16 = 0x8000;
+const FIXED_BANK_START_ADDRESS: u16 = 0xC000;
+const FIXED_BANK_SIZE: usize = 0x4000;
+
 pub struct PrgRom {
     rom: Vec<u8>,
 }
@@ -33,4 +40,166 @@ impl PrgRom {
     pub fn size(&self) -> usize {
         self.rom.len()
     }
+
+    pub fn read_mirrored(&self, offset: usize) -> u8 {
+        self.rom[offset % self.rom.len()]
+    }
+
+    pub fn read(&self, offset: usize) -> u8 {
+        self.get(offset)
+            .unwrap_or_else(|| panic!("PRG ROM read at offset {:#06X} out of range", offset))
+    }
+
+    pub fn get(&self, offset: usize) -> Option<u8> {
+        self.rom.get(offset).copied()
+    }
+
+    // Bank numbers past the end wrap around, the way mappers ignore unconnected bank lines
+    pub fn bank(&self, index: usize, bank_size: usize) -> &[u8] {
+        let bank_count = (self.rom.len() / bank_size).max(1);
+        let start = (index % bank_count) * bank_size;
+        &self.rom[start..(start + bank_size).min(self.rom.len())]
+    }
+
+    // CPU view of the ROM most mappers power up with, the first 16KB at 0x8000
+    // and the last 16KB fixed at 0xC000
+    pub fn read_cpu(&self, address: u16) -> u8 {
+        if address >= FIXED_BANK_START_ADDRESS {
+            let bank = self.rom.len().saturating_sub(FIXED_BANK_SIZE);
+            self.read_mirrored(bank + (address - FIXED_BANK_START_ADDRESS) as usize)
+        } else {
+            self.read_mirrored(address.wrapping_sub(PRG_ROM_START_ADDRESS) as usize)
+        }
+    }
+
+    // Decodes instructions one after another from the reset vector, for tooling
+    pub fn instructions(&self) -> Instructions<'_> {
+        let address = u16::from_le_bytes([
+            self.read_cpu(RESET_VECTOR_ADDRESS),
+            self.read_cpu(RESET_VECTOR_ADDRESS + 1),
+        ]);
+        Instructions {
+            prg_rom: self,
+            address: Some(address),
+        }
+    }
+}
+
+// Walks the ROM linearly without following jumps, stops at the first unknown opcode
+// or at the end of the address space
+pub struct Instructions<'a> {
+    prg_rom: &'a PrgRom,
+    address: Option<u16>,
+}
+
+impl Iterator for Instructions<'_> {
+    // Address, operation and the operand bytes after the opcode
+    type Item = (u16, Operation, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let address = self.address?;
+        let operation = Operation::get_operation(self.prg_rom.read_cpu(address));
+        let Some(operation) = operation else {
+            self.address = None;
+            return None;
+        };
+        let length = operation.length();
+        let operand_end = address.checked_add(length - 1);
+        let Some(operand_end) = operand_end else {
+            self.address = None;
+            return None;
+        };
+
+        let operand_bytes = (address + 1..=operand_end)
+            .map(|operand_address| self.prg_rom.read_cpu(operand_address))
+            .collect();
+        self.address = operand_end.checked_add(1);
+        Some((address, operation, operand_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 16KB ROM seen at both 0x8000 and 0xC000
+    fn prg_rom_with_program(program: &[u8]) -> PrgRom {
+        let mut data = vec![0x02; FIXED_BANK_SIZE];
+        data[..program.len()].copy_from_slice(program);
+        data[0x3FFC] = 0x00;
+        data[0x3FFD] = 0xC0;
+        PrgRom::new_with_data(data)
+    }
+
+    #[test]
+    fn test_instructions_from_reset_vector() {
+        let prg_rom = prg_rom_with_program(&[0xA9, 0x01, 0x8D, 0x00, 0x02, 0xE8, 0x0A]);
+
+        let instructions: Vec<_> = prg_rom.instructions().collect();
+
+        assert_eq!(
+            instructions,
+            vec![
+                (0xC000, Operation::LoadAccImm, vec![0x01]),
+                (0xC002, Operation::StoreAccAbsolute, vec![0x00, 0x02]),
+                (0xC005, Operation::IncX, vec![]),
+                (0xC006, Operation::AslA, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instructions_stop_at_end_of_address_space() {
+        let mut prg_rom = prg_rom_with_program(&[]);
+        prg_rom.write(0x3FFC, 0xFE);
+        prg_rom.write(0x3FFD, 0xFF);
+        // INX fits at 0xFFFE, LDA absolute at 0xFFFF would run past the end
+        prg_rom.write(0x3FFE, 0xE8);
+        prg_rom.write(0x3FFF, 0xAD);
+
+        let instructions: Vec<_> = prg_rom.instructions().collect();
+
+        assert_eq!(instructions, vec![(0xFFFE, Operation::IncX, vec![])]);
+    }
+
+    #[test]
+    fn test_bank_returns_16kb_window() {
+        let data = (0..3 * FIXED_BANK_SIZE)
+            .map(|offset| (offset / FIXED_BANK_SIZE) as u8)
+            .collect();
+        let prg_rom = PrgRom::new_with_data(data);
+
+        for index in 0..3 {
+            let bank = prg_rom.bank(index, FIXED_BANK_SIZE);
+            assert_eq!(bank.len(), FIXED_BANK_SIZE);
+            assert!(bank.iter().all(|&value| value == index as u8));
+        }
+        assert_eq!(prg_rom.bank(4, FIXED_BANK_SIZE)[0], 1);
+    }
+
+    #[test]
+    fn test_get_past_the_end() {
+        let prg_rom = PrgRom::new_with_data(vec![0x12; FIXED_BANK_SIZE]);
+
+        assert_eq!(prg_rom.get(FIXED_BANK_SIZE - 1), Some(0x12));
+        assert_eq!(prg_rom.get(FIXED_BANK_SIZE), None);
+        assert_eq!(prg_rom.read(0), 0x12);
+    }
+
+    #[test]
+    #[should_panic(expected = "PRG ROM read at offset 0x4000 out of range")]
+    fn test_read_past_the_end_panics() {
+        PrgRom::new_with_data(vec![0; FIXED_BANK_SIZE]).read(FIXED_BANK_SIZE);
+    }
+
+    #[test]
+    fn test_read_cpu_uses_last_bank_at_0xc000() {
+        let mut data = vec![0x00; 2 * FIXED_BANK_SIZE];
+        data[0] = 0x11;
+        data[FIXED_BANK_SIZE] = 0x22;
+        let prg_rom = PrgRom::new_with_data(data);
+
+        assert_eq!(prg_rom.read_cpu(0x8000), 0x11);
+        assert_eq!(prg_rom.read_cpu(0xC000), 0x22);
+    }
 }
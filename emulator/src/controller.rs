@@ -0,0 +1,195 @@
+use crate::addressing::Addressable;
+use bitflags::bitflags;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+bitflags! {
+    // Standard controller, see https://www.nesdev.org/wiki/Standard_controller
+    // Buttons are reported one bit per read in the order of the shift register
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Buttons: u8 {
+        const A = 1 << 0;
+        const B = 1 << 1;
+        const SELECT = 1 << 2;
+        const START = 1 << 3;
+        const UP = 1 << 4;
+        const DOWN = 1 << 5;
+        const LEFT = 1 << 6;
+        const RIGHT = 1 << 7;
+    }
+}
+
+const STROBE_BIT: u8 = 0b0000_0001;
+// After the 8 buttons an official controller keeps returning 1
+const SHIFT_REGISTER_FILL: u8 = 0b1000_0000;
+
+pub struct Controller {
+    buttons: Buttons,
+    shift_register: u8,
+    strobe: bool,
+    sequence: VecDeque<Buttons>,
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Self {
+            buttons: Buttons::empty(),
+            shift_register: 0,
+            strobe: false,
+            sequence: VecDeque::new(),
+        }
+    }
+
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        self.buttons = buttons;
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        self.buttons
+    }
+
+    // Queues button states consumed one per strobe, so scripted input advances
+    // exactly once per frame for games polling the controller once per frame
+    pub fn load_sequence(&mut self, sequence: &[Buttons]) {
+        self.sequence.extend(sequence);
+    }
+
+    pub fn remaining_sequence(&self) -> usize {
+        self.sequence.len()
+    }
+
+    fn write_strobe(&mut self, data: u8) {
+        let strobe = data & STROBE_BIT != 0;
+
+        // Falling edge latches the buttons into the shift register
+        if self.strobe && !strobe {
+            if let Some(buttons) = self.sequence.pop_front() {
+                self.buttons = buttons;
+            }
+            self.shift_register = self.buttons.bits();
+        }
+
+        self.strobe = strobe;
+    }
+
+    fn read_bit(&mut self) -> u8 {
+        // While strobe is high the register keeps reloading, so only A is reported
+        if self.strobe {
+            return self.buttons.contains(Buttons::A) as u8;
+        }
+
+        let bit = self.shift_register & 1;
+        self.shift_register = (self.shift_register >> 1) | SHIFT_REGISTER_FILL;
+        bit
+    }
+}
+
+impl Addressable for Controller {
+    fn read(&mut self, _address: u16) -> u8 {
+        self.read_bit()
+    }
+
+    fn write(&mut self, _address: u16, data: u8) {
+        self.write_strobe(data);
+    }
+}
+
+impl Debug for Controller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Controller")
+            .field("buttons", &self.buttons)
+            .field("shift_register", &self.shift_register)
+            .field("strobe", &self.strobe)
+            .field("sequence", &self.sequence)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Polls the controller the way games do once per frame
+    fn poll(controller: &mut Controller) -> Buttons {
+        controller.write(0x4016, 1);
+        controller.write(0x4016, 0);
+
+        let bits = (0..8).fold(0, |buttons, bit| buttons | (controller.read(0x4016) << bit));
+        Buttons::from_bits_truncate(bits)
+    }
+
+    #[test]
+    fn test_poll_reports_buttons() {
+        let mut controller = Controller::new();
+        controller.set_buttons(Buttons::A | Buttons::START | Buttons::RIGHT);
+
+        assert_eq!(
+            poll(&mut controller),
+            Buttons::A | Buttons::START | Buttons::RIGHT
+        );
+    }
+
+    #[test]
+    fn test_a_and_start_are_shifted_out_in_order() {
+        let mut controller = Controller::new();
+        controller.write(0x4016, 1);
+        controller.set_buttons(Buttons::A | Buttons::START);
+        controller.write(0x4016, 0);
+
+        let bits: Vec<u8> = (0..8).map(|_| controller.read(0x4016)).collect();
+
+        // A, B, Select, Start, Up, Down, Left, Right
+        assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_reads_after_8_buttons_return_1() {
+        let mut controller = Controller::new();
+        poll(&mut controller);
+
+        assert_eq!(controller.read(0x4016), 1);
+    }
+
+    #[test]
+    fn test_strobe_high_reports_a() {
+        let mut controller = Controller::new();
+        controller.set_buttons(Buttons::A);
+        controller.write(0x4016, 1);
+
+        assert_eq!(controller.read(0x4016), 1);
+        assert_eq!(controller.read(0x4016), 1);
+    }
+
+    #[test]
+    fn test_load_sequence_is_consumed_one_per_strobe() {
+        let sequence = [
+            Buttons::START,
+            Buttons::empty(),
+            Buttons::A | Buttons::B,
+            Buttons::UP | Buttons::LEFT,
+        ];
+        let mut controller = Controller::new();
+        controller.load_sequence(&sequence);
+
+        let polled: Vec<Buttons> = (0..sequence.len()).map(|_| poll(&mut controller)).collect();
+
+        assert_eq!(polled, sequence);
+        assert_eq!(controller.remaining_sequence(), 0);
+    }
+
+    #[test]
+    fn test_last_sequence_byte_is_held() {
+        let mut controller = Controller::new();
+        controller.load_sequence(&[Buttons::SELECT]);
+
+        poll(&mut controller);
+
+        assert_eq!(poll(&mut controller), Buttons::SELECT);
+    }
+}
@@ -1,6 +1,10 @@
 use crate::bus::BusLike;
+use crate::cpu::disassembly::disassemble;
 use crate::cpu::micro_instructions::{MicroInstruction, MicroInstructionSequence};
 use crate::cpu::registers::Registers;
+use crate::cpu::trace::TraceEntry;
+use crate::save_state::{self, SaveStateError};
+use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
 pub struct CPU<T: BusLike> {
@@ -9,9 +13,42 @@ pub struct CPU<T: BusLike> {
     state: CPUState,
     fetching_operation: MicroInstructionSequence,
     current_micro_instruction: Option<MicroInstruction>,
+    cycles: u64,
+    // Set once the addressing sequence of the current instruction has read its operand
+    operand_loaded: bool,
+    nmi_pending: bool,
+    // IRQ seen by the poll before the last cycle of the previous instruction
+    irq_polled: bool,
+    // Set when an IRQ is serviced, until the host takes it
+    irq_serviced: bool,
+    variant: CpuVariant,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+// The NES CPU is a 6502 with the BCD circuitry cut out, D can be set but ADC/SBC ignore it
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CpuVariant {
+    #[default]
+    Ricoh2A03,
+    // A stock NMOS 6502, with decimal mode
+    Nmos6502,
+}
+
+impl CpuVariant {
+    pub fn has_decimal_mode(&self) -> bool {
+        *self == CpuVariant::Nmos6502
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq)]
+pub enum CpuError {
+    #[error("unknown opcode {opcode:#04X} at {address:#06X}")]
+    UnknownOpcode { opcode: u8, address: u16 },
+
+    #[error("no stop condition within {cycles} CPU cycles")]
+    CycleBudgetExhausted { cycles: u64 },
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum CPUFlag {
     CarryBit,
     Zero,
@@ -23,14 +60,44 @@ pub enum CPUFlag {
     Negative,
 }
 
-#[derive(PartialEq, Debug)]
+pub const RESET_VECTOR_ADDRESS: u16 = 0xFFFC;
+const RESET_CYCLES: u64 = 7;
+const NMI_VECTOR_ADDRESS: u16 = 0xFFFA;
+const IRQ_VECTOR_ADDRESS: u16 = 0xFFFE;
+const INTERRUPT_CYCLES: u64 = 7;
+
+// Copy of the programmer visible registers, for debuggers and test harnesses
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RegisterSnapshot {
+    pub pc: u16,
+    pub sp: u8,
+    pub status: u8,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum CPUState {
     Fetching,
     Execution,
 }
+// Everything the CPU holds but the bus, which the caller saves and restores separately
+#[derive(Serialize, Deserialize)]
+struct CpuSaveState {
+    registers: Registers,
+    state: CPUState,
+    fetching_operation: MicroInstructionSequence,
+    current_micro_instruction: Option<MicroInstruction>,
+    cycles: u64,
+    operand_loaded: bool,
+    nmi_pending: bool,
+    irq_polled: bool,
+}
+
 #[allow(dead_code)]
 impl<T: BusLike> CPU<T> {
-    fn new(bus: T) -> Self {
+    pub fn new(bus: T) -> Self {
         let registers = Registers::new();
         let state = CPUState::Fetching;
         let fetching_operations = MicroInstructionSequence::new(vec![
@@ -44,23 +111,310 @@ impl<T: BusLike> CPU<T> {
             state,
             fetching_operation: fetching_operations,
             current_micro_instruction: None,
+            cycles: 0,
+            operand_loaded: false,
+            nmi_pending: false,
+            irq_polled: false,
+            irq_serviced: false,
+            variant: CpuVariant::default(),
+        }
+    }
+
+    pub fn variant(&self) -> CpuVariant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: CpuVariant) {
+        self.variant = variant;
+    }
+
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn bus(&mut self) -> &mut T {
+        &mut self.bus
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.registers.program_counter()
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.registers.program_counter()
+    }
+
+    pub fn sp(&self) -> u8 {
+        self.registers.stack_ptr()
+    }
+
+    pub fn status(&self) -> u8 {
+        self.registers.status()
+    }
+
+    pub fn a(&self) -> u8 {
+        self.registers.a
+    }
+
+    pub fn x(&self) -> u8 {
+        self.registers.x
+    }
+
+    pub fn y(&self) -> u8 {
+        self.registers.y
+    }
+
+    pub fn flag(&self, flag: CPUFlag) -> bool {
+        self.registers.is_flag_set(flag)
+    }
+
+    pub fn set_flag(&mut self, flag: CPUFlag, value: bool) {
+        self.registers.set_flag_value(flag, value);
+    }
+
+    pub fn snapshot(&self) -> RegisterSnapshot {
+        RegisterSnapshot {
+            pc: self.pc(),
+            sp: self.sp(),
+            status: self.status(),
+            a: self.a(),
+            x: self.x(),
+            y: self.y(),
+        }
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        save_state::encode(&CpuSaveState {
+            registers: self.registers.clone(),
+            state: self.state.clone(),
+            fetching_operation: self.fetching_operation.clone(),
+            current_micro_instruction: self.current_micro_instruction.clone(),
+            cycles: self.cycles,
+            operand_loaded: self.operand_loaded,
+            nmi_pending: self.nmi_pending,
+            irq_polled: self.irq_polled,
+        })
+    }
+
+    // A malformed blob leaves the CPU untouched
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        let saved: CpuSaveState = save_state::decode(bytes)?;
+        self.registers = saved.registers;
+        self.state = saved.state;
+        self.fetching_operation = saved.fetching_operation;
+        self.current_micro_instruction = saved.current_micro_instruction;
+        self.cycles = saved.cycles;
+        self.operand_loaded = saved.operand_loaded;
+        self.nmi_pending = saved.nmi_pending;
+        self.irq_polled = saved.irq_polled;
+        Ok(())
+    }
+
+    // The next instruction as a line of nestest.log, without the PPU and cycle columns:
+    // C000  A9 42     LDA #$42                        A:00 X:00 Y:00 P:24 SP:FD
+    // Memory is only peeked, operands the bus can't peek (e.g. PPU registers) get no value
+    pub fn trace(&self) -> String {
+        let disassembly = disassemble(
+            &self.bus,
+            self.registers.program_counter(),
+            self.registers.x,
+            self.registers.y,
+        );
+        let bytes = disassembly
+            .bytes
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        format!(
+            "{:04X}  {:<8}  {:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.registers.program_counter(),
+            bytes,
+            disassembly.text,
+            self.registers.a,
+            self.registers.x,
+            self.registers.y,
+            // The unused bit always reads back as set
+            self.registers.status() | CPUFlag::Unused.value(),
+            self.registers.stack_ptr()
+        )
+    }
+
+    // Runs count whole instructions and returns the trace line of each one, for diffing
+    // against reference logs. An instruction that fails ends the run, its line comes last
+    pub fn run_traced(&mut self, count: usize) -> Vec<String> {
+        let mut lines = Vec::with_capacity(count);
+        for _ in 0..count {
+            lines.push(self.trace());
+            if self.step_instruction().is_err() {
+                break;
+            }
+        }
+        lines
+    }
+
+    // Interrupt vectors at the top of the address space, read through the bus
+    pub fn nmi_vector(&mut self) -> u16 {
+        self.bus.read_u16(NMI_VECTOR_ADDRESS)
+    }
+
+    pub fn reset_vector(&mut self) -> u16 {
+        self.bus.read_u16(RESET_VECTOR_ADDRESS)
+    }
+
+    pub fn irq_vector(&mut self) -> u16 {
+        self.bus.read_u16(IRQ_VECTOR_ADDRESS)
+    }
+
+    // Abandons the current instruction and continues from the reset vector
+    pub fn reset(&mut self) {
+        let address = self.reset_vector();
+        self.registers.reset(address);
+
+        self.state = CPUState::Fetching;
+        self.fetching_operation.reset();
+        self.current_micro_instruction = None;
+        self.nmi_pending = false;
+        self.irq_polled = false;
+        self.cycles += RESET_CYCLES;
+    }
+
+    // The NMI is taken once the current instruction completes
+    pub fn request_nmi(&mut self) {
+        self.nmi_pending = true;
+    }
+
+    fn service_nmi(&mut self) {
+        self.nmi_pending = false;
+        self.service_interrupt(NMI_VECTOR_ADDRESS);
+    }
+
+    fn service_interrupt(&mut self, vector: u16) {
+        self.irq_polled = false;
+        self.registers.interrupt(&mut self.bus, vector);
+        self.current_micro_instruction = None;
+        self.cycles += INTERRUPT_CYCLES;
+    }
+
+    // Whether an IRQ was serviced since the last call, for notifying the host
+    pub fn take_irq_serviced(&mut self) -> bool {
+        std::mem::take(&mut self.irq_serviced)
+    }
+
+    // The 6502 polls interrupts before the last cycle of an instruction, so the I flag
+    // written by that cycle (CLI, SEI, PLP) only affects the poll of the next instruction
+    // See https://www.nesdev.org/wiki/CPU_interrupts#Delayed_IRQ_response_after_CLI,_SEI,_and_PLP
+    fn poll_interrupts(&mut self) {
+        self.irq_polled = self.bus.irq() && !self.registers.is_flag_set(CPUFlag::InterruptDisable);
+    }
+
+    // Runs micro-instructions until the next instruction (or interrupt) is about to start,
+    // returns the cycles it took
+    pub fn step_instruction(&mut self) -> Result<u64, CpuError> {
+        let cycles = self.cycles;
+        self.step()?;
+        while !self.is_at_instruction_boundary() {
+            self.step()?;
+        }
+        Ok(self.cycles - cycles)
+    }
+
+    // Steps whole instructions until the predicate holds, returning the cycles spent.
+    // A watchdog for unknown ROMs, giving up once more than max_cycles have passed
+    pub fn run_until<F: Fn(&CPU<T>) -> bool>(
+        &mut self,
+        predicate: F,
+        max_cycles: u64,
+    ) -> Result<u64, CpuError> {
+        let mut cycles = 0;
+        while !predicate(self) {
+            if cycles > max_cycles {
+                return Err(CpuError::CycleBudgetExhausted { cycles });
+            }
+            cycles += self.step_instruction()?;
+        }
+        Ok(cycles)
+    }
+
+    fn is_at_instruction_boundary(&self) -> bool {
+        self.state == CPUState::Fetching && self.fetching_operation.is_at_start()
+    }
+
+    // Registers as they are before the next instruction, in the trace log format
+    pub fn trace_entry(&self) -> TraceEntry {
+        TraceEntry {
+            program_counter: self.registers.program_counter(),
+            a: self.registers.a,
+            x: self.registers.x,
+            y: self.registers.y,
+            // The unused bit always reads back as set
+            status: self.registers.status() | CPUFlag::Unused.value(),
+            stack_ptr: self.registers.stack_ptr(),
+            cycles: Some(self.cycles),
         }
     }
 
-    fn step(&mut self) {
+    pub fn last_page_crossed(&self) -> bool {
+        self.registers.is_page_crossed()
+    }
+
+    // Executes a single micro-instruction. An unknown opcode leaves the CPU about to fetch
+    // it again, so every following step reports the same error
+    pub fn step(&mut self) -> Result<(), CpuError> {
+        self.cycles += self.bus.take_stall_cycles() as u64;
+        if self.bus.take_nmi() {
+            self.request_nmi();
+        }
+
+        if self.is_at_instruction_boundary() {
+            if self.nmi_pending {
+                self.service_nmi();
+                return Ok(());
+            }
+            if self.irq_polled {
+                self.service_interrupt(IRQ_VECTOR_ADDRESS);
+                self.irq_serviced = true;
+                return Ok(());
+            }
+        }
+
         match self.state {
             CPUState::Fetching => {
                 self.fetch_step();
             }
             CPUState::Execution => {
                 self.execute_step();
+                // The micro-instruction about to run is the last one of the instruction
+                if self.state == CPUState::Fetching {
+                    self.poll_interrupts();
+                }
             }
         }
 
         let current_micro_instruction = self.current_micro_instruction.clone();
         if let Some(micro_instruction) = current_micro_instruction {
-            self.execute_micro_instruction(&micro_instruction);
+            if let Err(error) = self.execute_micro_instruction(&micro_instruction) {
+                self.state = CPUState::Fetching;
+                self.fetching_operation.reset();
+                self.current_micro_instruction = None;
+                return Err(error);
+            }
+
+            if micro_instruction.consumes_cycle() {
+                self.cycles += 1;
+            }
+
+            // Indexed reads re-read the effective address once the high byte is fixed up
+            if micro_instruction.is_indexed_read() && self.registers.is_page_crossed() {
+                self.cycles += 1;
+            }
+
+            if micro_instruction.is_branch() && self.registers.is_branch_taken() {
+                self.cycles += 1 + self.registers.is_page_crossed() as u64;
+            }
         }
+        Ok(())
     }
 
     fn fetch_step(&mut self) {
@@ -91,13 +445,28 @@ impl<T: BusLike> CPU<T> {
         }
     }
 
-    fn execute_micro_instruction(&mut self, micro_instruction: &MicroInstruction) {
+    fn execute_micro_instruction(
+        &mut self,
+        micro_instruction: &MicroInstruction,
+    ) -> Result<(), CpuError> {
+        debug_assert!(
+            !micro_instruction.requires_operand() || self.operand_loaded,
+            "{:?} executed before the addressing sequence read its operand",
+            micro_instruction
+        );
+
+        if micro_instruction.reads_operand() {
+            self.operand_loaded = true;
+        } else if *micro_instruction == MicroInstruction::DecodeOperation {
+            self.operand_loaded = false;
+        }
+
         match micro_instruction {
             MicroInstruction::Empty => (),
             MicroInstruction::ReadOperationCode => {
                 self.registers.read_operation_code(&mut self.bus)
             }
-            MicroInstruction::DecodeOperation => self.registers.decode_operation(&mut self.bus),
+            MicroInstruction::DecodeOperation => self.registers.decode_operation(&self.bus)?,
             MicroInstruction::ImmediateRead => self.registers.immediate_read(&mut self.bus),
             MicroInstruction::ReadAdh => self.registers.read_adh(&mut self.bus),
             MicroInstruction::ReadAdl => self.registers.read_adl(&mut self.bus),
@@ -130,12 +499,22 @@ impl<T: BusLike> CPU<T> {
             MicroInstruction::ReadBahIndirectIal => {
                 self.registers.read_bah_indirect_ial(&mut self.bus)
             }
+            MicroInstruction::CalculateAdlAdhAbsoluteX => {
+                self.registers.calculate_adl_adh_absolute_x()
+            }
+            MicroInstruction::CalculateAdlAdhAbsoluteY => {
+                self.registers.calculate_adl_adh_absolute_y()
+            }
             MicroInstruction::WriteZeroPage => self.registers.write_zero_page(&mut self.bus),
             MicroInstruction::WriteAbsolute => self.registers.write_absolute(&mut self.bus),
             MicroInstruction::WriteZeroPageBalX => {
                 self.registers.write_zero_page_bal_x(&mut self.bus)
             }
+            MicroInstruction::WriteZeroPageBalY => {
+                self.registers.write_zero_page_bal_y(&mut self.bus)
+            }
             MicroInstruction::ShiftLeftAccumulator => self.registers.shift_left_accumulator(),
+            MicroInstruction::RotateRightAccumulator => self.registers.rotate_right_accumulator(),
             MicroInstruction::ShiftLeftMemoryBuffer => self.registers.shift_left_memory_buffer(),
             MicroInstruction::IncrementMemoryBuffer => self.registers.increment_memory_buffer(),
             MicroInstruction::IncrementX => self.registers.increment_x(),
@@ -146,8 +525,30 @@ impl<T: BusLike> CPU<T> {
             MicroInstruction::LoadAccumulator => self.registers.load_accumulator(),
             MicroInstruction::LoadX => self.registers.load_x(),
             MicroInstruction::LoadY => self.registers.load_y(),
+            MicroInstruction::StoreAccumulator => self.registers.store_accumulator(),
+            MicroInstruction::StoreX => self.registers.store_x(),
+            MicroInstruction::StoreY => self.registers.store_y(),
             MicroInstruction::And => self.registers.and(),
+            MicroInstruction::Or => self.registers.or(),
+            MicroInstruction::AddWithCarry => self.registers.adc(self.variant.has_decimal_mode()),
+            MicroInstruction::SubtractWithCarry => {
+                self.registers.sbc(self.variant.has_decimal_mode())
+            }
+            MicroInstruction::TransferAccumulatorToX => self.registers.transfer_accumulator_to_x(),
+            MicroInstruction::TransferAccumulatorToY => self.registers.transfer_accumulator_to_y(),
+            MicroInstruction::TransferXToAccumulator => self.registers.transfer_x_to_accumulator(),
+            MicroInstruction::TransferYToAccumulator => self.registers.transfer_y_to_accumulator(),
+            MicroInstruction::TransferStackptrToX => self.registers.transfer_stack_ptr_to_x(),
+            MicroInstruction::TransferXToStackptr => self.registers.transfer_x_to_stack_ptr(),
+            MicroInstruction::PushAccumulator => self.registers.push_accumulator(&mut self.bus),
+            MicroInstruction::PushStatus => self.registers.push_status(&mut self.bus),
+            MicroInstruction::PullAccumulator => self.registers.pull_accumulator(&mut self.bus),
+            MicroInstruction::PullStatus => self.registers.pull_status(&mut self.bus),
+            MicroInstruction::Branch(flag, set) => self.registers.branch(flag.clone(), *set),
+            MicroInstruction::SetFlag(flag) => self.registers.set_flag(flag.clone()),
+            MicroInstruction::ClearFlag(flag) => self.registers.clear_flag(flag.clone()),
         }
+        Ok(())
     }
 }
 
@@ -168,23 +569,38 @@ impl CPUFlag {
 
 #[cfg(test)]
 mod tests {
-    use crate::cpu::operations::Operation;
-    use std::collections::btree_map::Values;
+    use crate::cpu::operations::{flag_micro_instruction, AddressingMode, Operation};
 
     use crate::bus;
+    use crate::bus::DMC_DMA_STALL_CYCLES;
+    use crate::logging::nes_logging::CPU_LOG_TARGET;
+    use log::debug;
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
     struct TestBus {
         memory: Vec<usize>,
+        stall_cycles: u32,
+        nmi: bool,
+        irq: bool,
+        // Every write in order, as (address, data)
+        writes: Vec<(u16, u8)>,
     }
 
     impl TestBus {
         pub fn new() -> Self {
             Self {
                 memory: vec![0; bus::ADDRESS_SPACE],
+                writes: Vec::new(),
+                stall_cycles: 0,
+                nmi: false,
+                irq: false,
             }
         }
+
+        pub fn queue_dmc_fetch(&mut self) {
+            self.stall_cycles += DMC_DMA_STALL_CYCLES;
+        }
     }
 
     impl BusLike for TestBus {
@@ -193,13 +609,30 @@ mod tests {
         }
 
         fn write(&mut self, address: u16, data: u8) {
-            println!("Writing {:#X} to address {:#X}", data, address);
+            debug!(target: CPU_LOG_TARGET, "Writing {:#X} to address {:#X}", data, address);
             self.memory[address as usize] = data as usize;
+            self.writes.push((address, data));
+        }
+
+        fn peek(&self, address: u16) -> Option<u8> {
+            Some(self.memory[address as usize] as u8)
+        }
+
+        fn take_stall_cycles(&mut self) -> u32 {
+            std::mem::take(&mut self.stall_cycles)
+        }
+
+        fn take_nmi(&mut self) -> bool {
+            std::mem::take(&mut self.nmi)
+        }
+
+        fn irq(&self) -> bool {
+            self.irq
         }
     }
 
     fn _test_read_and_decode_operation(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -207,7 +640,7 @@ mod tests {
             Some(MicroInstruction::ReadOperationCode)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -217,7 +650,7 @@ mod tests {
     }
 
     fn _test_immediate_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -227,7 +660,7 @@ mod tests {
     }
 
     fn _test_zero_page_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -235,7 +668,7 @@ mod tests {
             Some(MicroInstruction::ReadAdl)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -245,7 +678,7 @@ mod tests {
     }
 
     fn _test_zero_page_x_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -253,12 +686,12 @@ mod tests {
             Some(MicroInstruction::ReadBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Empty));
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -268,7 +701,7 @@ mod tests {
     }
 
     fn _test_zero_page_y_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -276,12 +709,12 @@ mod tests {
             Some(MicroInstruction::ReadBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Empty));
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -291,7 +724,7 @@ mod tests {
     }
 
     fn _test_absolute_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -299,7 +732,7 @@ mod tests {
             Some(MicroInstruction::ReadAdl)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -307,7 +740,7 @@ mod tests {
             Some(MicroInstruction::ReadAdh)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -317,7 +750,7 @@ mod tests {
     }
 
     fn _test_absolute_x_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -325,7 +758,7 @@ mod tests {
             Some(MicroInstruction::ReadBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -333,7 +766,7 @@ mod tests {
             Some(MicroInstruction::ReadBah)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -342,8 +775,35 @@ mod tests {
         );
     }
 
+    fn _test_absolute_x_rmw_read(cpu: &mut CPU<TestBus>) {
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadBal)
+        );
+
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadBah)
+        );
+
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::CalculateAdlAdhAbsoluteX)
+        );
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadAbsolute)
+        );
+    }
+
     fn _test_absolute_y_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -351,7 +811,7 @@ mod tests {
             Some(MicroInstruction::ReadBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -359,7 +819,7 @@ mod tests {
             Some(MicroInstruction::ReadBah)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -369,7 +829,7 @@ mod tests {
     }
 
     fn _test_indirect_x_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -377,12 +837,12 @@ mod tests {
             Some(MicroInstruction::ReadBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Empty));
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -390,7 +850,7 @@ mod tests {
             Some(MicroInstruction::ReadAdlIndirectBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -398,7 +858,7 @@ mod tests {
             Some(MicroInstruction::ReadAdhIndirectBal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -408,7 +868,7 @@ mod tests {
     }
 
     fn _test_indirect_y_read(cpu: &mut CPU<TestBus>) {
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -416,7 +876,7 @@ mod tests {
             Some(MicroInstruction::ReadIal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -424,7 +884,7 @@ mod tests {
             Some(MicroInstruction::ReadBalIndirectIal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -432,7 +892,7 @@ mod tests {
             Some(MicroInstruction::ReadBahIndirectIal)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -455,7 +915,7 @@ mod tests {
         let bus = TestBus::new();
         let mut cpu = CPU::new(bus);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -464,6 +924,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cpu_counts_cycles_of_an_instruction() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0001, 0x42);
+        let mut cpu = CPU::new(bus);
+
+        for _ in 0..4 {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.cycle_count(), 2);
+    }
+
+    #[test]
+    fn test_cpu_adds_stall_cycles_of_queued_dmc_fetch() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::IncX.get_opcode());
+        let mut cpu = CPU::new(bus);
+
+        cpu.step().unwrap();
+        let cycles_before_fetch = cpu.cycle_count();
+
+        cpu.bus.queue_dmc_fetch();
+        cpu.step().unwrap();
+
+        assert_eq!(
+            cpu.cycle_count(),
+            cycles_before_fetch + DMC_DMA_STALL_CYCLES as u64
+        );
+
+        cpu.step().unwrap();
+
+        assert_eq!(
+            cpu.cycle_count(),
+            cycles_before_fetch + DMC_DMA_STALL_CYCLES as u64 + 1
+        );
+    }
+
     #[test]
     fn test_cpu_asl_a() {
         const OPCODE: u8 = 0x0A;
@@ -471,13 +971,13 @@ mod tests {
         bus.write(0, OPCODE);
         let mut cpu = CPU::new(bus);
 
-        cpu.step();
-        cpu.step();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.registers.a, 0);
         assert_eq!(cpu.state, CPUState::Execution);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.registers.a, 0);
         assert_eq!(cpu.state, CPUState::Fetching);
@@ -496,13 +996,13 @@ mod tests {
 
         cpu.registers.a = 0b10000000;
 
-        cpu.step();
-        cpu.step();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.registers.a, 0b10000000);
         assert_eq!(cpu.state, CPUState::Execution);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.registers.a, 0b00000000);
         assert_eq!(cpu.state, CPUState::Fetching);
@@ -530,8 +1030,9 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
-        cpu.step();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -561,7 +1062,15 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPage)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -569,7 +1078,7 @@ mod tests {
             Some(MicroInstruction::IncrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -601,7 +1110,15 @@ mod tests {
 
         _test_zero_page_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPageBalX)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -609,7 +1126,7 @@ mod tests {
             Some(MicroInstruction::IncrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -641,7 +1158,15 @@ mod tests {
 
         _test_absolute_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -649,7 +1174,7 @@ mod tests {
             Some(MicroInstruction::IncrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -682,9 +1207,17 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        _test_absolute_x_read(&mut cpu);
+        _test_absolute_x_rmw_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -692,7 +1225,7 @@ mod tests {
             Some(MicroInstruction::IncrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -704,6 +1237,71 @@ mod tests {
         assert_eq!(read_value, expected_value);
     }
 
+    fn _test_rmw_absolute_x_page_crossing(operation: Operation, value: u8, expected_value: u8) {
+        let base_address: u16 = 0x12F0;
+        let x_value: u8 = 0x20;
+        let indexed_address: u16 = 0x1310;
+        // Where the write would land if the carry into the high byte were dropped
+        let uncarried_address: u16 = 0x1210;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, operation.get_opcode());
+        bus.write(0x0001, (base_address & 0xFF) as u8);
+        bus.write(0x0002, (base_address >> 8) as u8);
+        bus.write(indexed_address, value);
+        bus.write(uncarried_address, value);
+        bus.write(base_address, value);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.x = x_value;
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.bus.read(indexed_address), expected_value);
+        assert_eq!(cpu.bus.read(uncarried_address), value);
+        assert_eq!(cpu.bus.read(base_address), value);
+    }
+
+    #[test]
+    fn test_cpu_inc_mem_absolute_x_page_crossing_writes_indexed_address() {
+        _test_rmw_absolute_x_page_crossing(Operation::IncMemAbsoluteX, 0x41, 0x42);
+    }
+
+    #[test]
+    fn test_cpu_dec_mem_absolute_x_page_crossing_writes_indexed_address() {
+        _test_rmw_absolute_x_page_crossing(Operation::DecMemAbsoluteX, 0x43, 0x42);
+    }
+
+    #[test]
+    fn test_cpu_rmw_writes_unmodified_value_first() {
+        let operations = [
+            (Operation::AslZeroPage, 0x42),
+            (Operation::AslAbsolute, 0x42),
+            (Operation::IncMemZeroPageX, 0x22),
+            (Operation::IncMemAbsoluteX, 0x22),
+            (Operation::DecMemZeroPage, 0x20),
+            (Operation::DecMemAbsolute, 0x20),
+        ];
+
+        for (operation, expected_value) in operations {
+            let mut bus = TestBus::new();
+            bus.write(0x0200, operation.get_opcode());
+            bus.write(0x0201, 0x10);
+            bus.write(0x0010, 0x21);
+            bus.writes.clear();
+            let mut cpu = CPU::new(bus);
+            cpu.registers.reset(0x0200);
+
+            cpu.step_instruction().unwrap();
+
+            assert_eq!(
+                cpu.bus.writes,
+                vec![(0x0010, 0x21), (0x0010, expected_value)],
+                "{:?}",
+                operation
+            );
+        }
+    }
+
     #[test]
     fn test_cpu_inc_x() {
         let opcode = Operation::IncX.get_opcode();
@@ -717,7 +1315,7 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -741,7 +1339,7 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -771,7 +1369,15 @@ mod tests {
 
         println!("{}", cpu.registers.memory_buffer);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPage)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -781,7 +1387,7 @@ mod tests {
 
         println!("{}", cpu.registers.memory_buffer);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -813,7 +1419,15 @@ mod tests {
 
         _test_zero_page_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPageBalX)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -821,7 +1435,7 @@ mod tests {
             Some(MicroInstruction::DecrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -853,7 +1467,15 @@ mod tests {
 
         _test_absolute_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
+
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -861,7 +1483,7 @@ mod tests {
             Some(MicroInstruction::DecrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -894,9 +1516,17 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        _test_absolute_x_read(&mut cpu);
+        _test_absolute_x_rmw_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Execution);
         assert_eq!(
@@ -904,7 +1534,7 @@ mod tests {
             Some(MicroInstruction::DecrementMemoryBuffer)
         );
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -929,7 +1559,7 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -953,7 +1583,7 @@ mod tests {
 
         _test_read_and_decode_operation(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -979,7 +1609,7 @@ mod tests {
 
         _test_immediate_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1007,7 +1637,7 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1038,7 +1668,7 @@ mod tests {
 
         _test_zero_page_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1069,7 +1699,7 @@ mod tests {
 
         _test_absolute_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1101,7 +1731,7 @@ mod tests {
 
         _test_absolute_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1135,7 +1765,7 @@ mod tests {
 
         _test_absolute_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1171,7 +1801,7 @@ mod tests {
 
         _test_indirect_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1207,7 +1837,7 @@ mod tests {
 
         _test_indirect_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(
@@ -1233,7 +1863,7 @@ mod tests {
 
         _test_immediate_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
@@ -1258,7 +1888,7 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
@@ -1286,7 +1916,7 @@ mod tests {
 
         _test_zero_page_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
@@ -1295,36 +1925,63 @@ mod tests {
     }
 
     #[test]
-    fn test_cpu_load_x_absolute() {
-        let opcode = Operation::LoadXAbsolute.get_opcode();
-        let adl: u8 = 0x2F;
-        let adh: u8 = 0xBB;
+    fn test_cpu_load_x_zero_page_y_wraps_around() {
+        let opcode = Operation::LoadXZeroPageY.get_opcode();
+        let adl: u8 = 0xF0;
         let value: u8 = 4;
-        let address: u16 = 0xBB2F;
+        let y_value: u8 = 0x20;
+        let expected_address: u16 = 0x0010;
 
         let mut bus = TestBus::new();
         bus.write(0x0000, opcode);
         bus.write(0x0001, adl);
-        bus.write(0x0002, adh);
-        bus.write(address, value);
+        bus.write(expected_address, value);
+        bus.write(0x0110, 0xFF);
 
         let mut cpu = CPU::new(bus);
+        cpu.registers.y = y_value;
 
         _test_read_and_decode_operation(&mut cpu);
 
-        _test_absolute_read(&mut cpu);
+        _test_zero_page_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
-        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
-
         assert_eq!(cpu.registers.x, value);
     }
 
     #[test]
-    fn test_cpu_load_x_absolute_y() {
-        let opcode = Operation::LoadXAbsoluteY.get_opcode();
+    fn test_cpu_load_x_absolute() {
+        let opcode = Operation::LoadXAbsolute.get_opcode();
+        let adl: u8 = 0x2F;
+        let adh: u8 = 0xBB;
+        let value: u8 = 4;
+        let address: u16 = 0xBB2F;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, adh);
+        bus.write(address, value);
+
+        let mut cpu = CPU::new(bus);
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_absolute_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
+
+        assert_eq!(cpu.registers.x, value);
+    }
+
+    #[test]
+    fn test_cpu_load_x_absolute_y() {
+        let opcode = Operation::LoadXAbsoluteY.get_opcode();
         let adl: u8 = 0x2F;
         let adh: u8 = 0xBB;
         let value: u8 = 4;
@@ -1345,7 +2002,7 @@ mod tests {
 
         _test_absolute_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadX));
@@ -1368,7 +2025,7 @@ mod tests {
 
         _test_immediate_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadY));
@@ -1393,7 +2050,7 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadY));
@@ -1421,7 +2078,7 @@ mod tests {
 
         _test_zero_page_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadY));
@@ -1429,6 +2086,32 @@ mod tests {
         assert_eq!(cpu.registers.y, value);
     }
 
+    #[test]
+    fn test_cpu_load_y_zero_page_x_wraps_around() {
+        let opcode = Operation::LoadYZeroPageX.get_opcode();
+        let adl: u8 = 0xFF;
+        let unwrapped_value: u8 = 4;
+        let x_value: u8 = 0x01;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        // The opcode at 0x0000 is what the wrapped address points at
+        bus.write(0x0100, unwrapped_value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_zero_page_x_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.registers.y, opcode);
+    }
+
     #[test]
     fn test_cpu_load_y_absolute() {
         let opcode = Operation::LoadYAbsolute.get_opcode();
@@ -1449,7 +2132,7 @@ mod tests {
 
         _test_absolute_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadY));
@@ -1480,7 +2163,7 @@ mod tests {
 
         _test_absolute_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::LoadY));
@@ -1506,7 +2189,7 @@ mod tests {
 
         _test_immediate_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1534,7 +2217,7 @@ mod tests {
 
         _test_zero_page_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1565,7 +2248,7 @@ mod tests {
 
         _test_zero_page_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1596,7 +2279,7 @@ mod tests {
 
         _test_absolute_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1630,7 +2313,7 @@ mod tests {
 
         _test_absolute_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1664,7 +2347,7 @@ mod tests {
 
         _test_absolute_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1700,7 +2383,7 @@ mod tests {
 
         _test_indirect_x_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
@@ -1736,11 +2419,1377 @@ mod tests {
 
         _test_indirect_y_read(&mut cpu);
 
-        cpu.step();
+        cpu.step().unwrap();
 
         assert_eq!(cpu.state, CPUState::Fetching);
         assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::And));
 
         assert_eq!(cpu.registers.a, expected_value);
     }
+
+    #[test]
+    fn test_cpu_ora_imm() {
+        let opcode = Operation::OraImm.get_opcode();
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let expected_value: u8 = 0b1111_1011;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_immediate_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_zero_page() {
+        let opcode = Operation::OraZeroPage.get_opcode();
+        let adl: u8 = 0xAA;
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let expected_value: u8 = 0b1111_1011;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(adl as u16, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_zero_page_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_zero_page_x() {
+        let opcode = Operation::OraZeroPageX.get_opcode();
+        let adl: u8 = 0xAA;
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let x_value: u8 = 3;
+        let expected_value: u8 = 0b1111_1011;
+        let expected_address: u8 = adl + x_value;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(expected_address as u16, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_zero_page_x_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_absolute() {
+        let opcode = Operation::OraAbsolute.get_opcode();
+        let adl: u8 = 0xAA;
+        let adh: u8 = 0x11;
+        let address: u16 = 0x11AA;
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let expected_value: u8 = 0b1111_1011;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, adh);
+        bus.write(address, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_absolute_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_absolute_x() {
+        let opcode = Operation::OraAbsoluteX.get_opcode();
+        let adl: u8 = 0xAA;
+        let adh: u8 = 0x11;
+        let address: u16 = 0x11AA;
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let x_value: u8 = 2;
+        let expected_value: u8 = 0b1111_1011;
+        let expected_address: u16 = address + x_value as u16;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, adh);
+        bus.write(expected_address, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_absolute_x_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_absolute_y() {
+        let opcode = Operation::OraAbsoluteY.get_opcode();
+        let adl: u8 = 0xAA;
+        let adh: u8 = 0x11;
+        let address: u16 = 0x11AA;
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let y_value: u8 = 200;
+        let expected_value: u8 = 0b1111_1011;
+        let expected_address: u16 = address + y_value as u16;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, adh);
+        bus.write(expected_address, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.y = y_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_absolute_y_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_indirect_x() {
+        let opcode = Operation::OraIndirectX.get_opcode();
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let expected_value: u8 = 0b1111_1011;
+        let x_value: u8 = 10;
+        let adl: u8 = 0x22;
+        let expected_address: u16 = (adl + x_value) as u16;
+        let indirect_adl: u8 = 0xBB;
+        let indirect_adh: u8 = 0xAA;
+        let indirect_address: u16 = 0xAABB;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(expected_address, indirect_adl);
+        bus.write(expected_address + 1, indirect_adh);
+        bus.write(indirect_address, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_indirect_x_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    #[test]
+    fn test_cpu_ora_indirect_y() {
+        let opcode = Operation::OraIndirectY.get_opcode();
+        let value: u8 = 0b0000_1010;
+        let a_value: u8 = 0b1111_0011;
+        let expected_value: u8 = 0b1111_1011;
+        let y_value: u8 = 20;
+        let adl: u8 = 0x22;
+        let indirect_adl: u8 = 0xBB;
+        let indirect_adh: u8 = 0xAA;
+        let indirect_address: u16 = 0xAABB;
+        let expected_address: u16 = indirect_address + y_value as u16;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(adl as u16, indirect_adl);
+        bus.write((adl + 1) as u16, indirect_adh);
+        bus.write(expected_address, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.y = y_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        _test_indirect_y_read(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.current_micro_instruction, Some(MicroInstruction::Or));
+
+        assert_eq!(cpu.registers.a, expected_value);
+    }
+
+    fn setup_arithmetic_cpu(
+        operation: Operation,
+        a_value: u8,
+        value: u8,
+        carry: bool,
+    ) -> CPU<TestBus> {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, operation.get_opcode());
+        bus.write(0x0001, value);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.set_flag_value(CPUFlag::CarryBit, carry);
+        cpu
+    }
+
+    // Operands of zero keep every indexed access on its page, branches are left to their own tests
+    #[test]
+    fn test_cpu_cycles_match_base_cycles() {
+        for operation in Operation::all() {
+            if operation.addressing_mode() == AddressingMode::Relative {
+                continue;
+            }
+            let mut bus = TestBus::new();
+            bus.write(0x0200, operation.get_opcode());
+            let mut cpu = CPU::new(bus);
+            cpu.registers.reset(0x0200);
+
+            let cycles = cpu.step_instruction().unwrap();
+
+            assert_eq!(
+                cycles,
+                operation.base_cycles() as u64,
+                "{}",
+                operation.mnemonic()
+            );
+        }
+    }
+
+    fn setup_branch_cpu(origin: u16, offset: u8, zero: bool) -> CPU<TestBus> {
+        let mut bus = TestBus::new();
+        bus.write(origin, Operation::BranchIfEqual.get_opcode());
+        bus.write(origin.wrapping_add(1), offset);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(origin);
+        cpu.registers.set_flag_value(CPUFlag::Zero, zero);
+        cpu
+    }
+
+    #[test]
+    fn test_cpu_branch_not_taken_takes_2_cycles() {
+        let mut cpu = setup_branch_cpu(0x0200, 0x10, false);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.pc(), 0x0202);
+    }
+
+    #[test]
+    fn test_cpu_branch_taken_takes_3_cycles() {
+        let mut cpu = setup_branch_cpu(0x0200, 0x10, true);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 3);
+        assert_eq!(cpu.pc(), 0x0212);
+    }
+
+    #[test]
+    fn test_cpu_branch_taken_across_page_takes_4_cycles() {
+        let mut cpu = setup_branch_cpu(0x02F0, 0x10, true);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 4);
+        assert_eq!(cpu.pc(), 0x0302);
+    }
+
+    #[test]
+    fn test_cpu_branch_backwards_across_page() {
+        // -4 from 0x0202 lands on 0x01FE
+        let mut cpu = setup_branch_cpu(0x0200, 0xFC, true);
+        let cycles_before = cpu.cycle_count();
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.cycle_count() - cycles_before, 4);
+        assert_eq!(cpu.pc(), 0x01FE);
+    }
+
+    #[test]
+    fn test_cpu_branches_test_their_flags() {
+        let cases = [
+            (Operation::BranchIfPositive, CPUFlag::Negative, false),
+            (Operation::BranchIfMinus, CPUFlag::Negative, true),
+            (Operation::BranchIfOverflowClear, CPUFlag::Overflow, false),
+            (Operation::BranchIfOverflowSet, CPUFlag::Overflow, true),
+            (Operation::BranchIfCarryClear, CPUFlag::CarryBit, false),
+            (Operation::BranchIfCarrySet, CPUFlag::CarryBit, true),
+            (Operation::BranchIfNotEqual, CPUFlag::Zero, false),
+            (Operation::BranchIfEqual, CPUFlag::Zero, true),
+        ];
+
+        for (operation, flag, set) in cases {
+            let mnemonic = operation.mnemonic();
+            let mut bus = TestBus::new();
+            bus.write(0x0200, operation.get_opcode());
+            bus.write(0x0201, 0x10);
+            let mut cpu = CPU::new(bus);
+            cpu.registers.reset(0x0200);
+            cpu.registers.set_flag_value(flag, set);
+
+            cpu.step_instruction().unwrap();
+
+            assert_eq!(cpu.pc(), 0x0212, "{}", mnemonic);
+        }
+    }
+
+    fn setup_transfer_cpu(operation: Operation) -> CPU<TestBus> {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, operation.get_opcode());
+        CPU::new(bus)
+    }
+
+    #[test]
+    fn test_cpu_tax_with_zero_sets_zero_flag() {
+        let mut cpu = setup_transfer_cpu(Operation::TransferAccToX);
+        cpu.registers.a = 0x00;
+        cpu.registers.x = 0x12;
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.registers.x, 0x00);
+        assert!(cpu.registers.is_flag_set(CPUFlag::Zero));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Negative));
+    }
+
+    #[test]
+    fn test_cpu_txs_with_zero_leaves_flags_alone() {
+        let mut cpu = setup_transfer_cpu(Operation::TransferXToStackptr);
+        cpu.registers.x = 0x00;
+        cpu.registers.set_flag(CPUFlag::Negative);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.registers.stack_ptr(), 0x00);
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Zero));
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+    }
+
+    #[test]
+    fn test_cpu_transfers_set_negative_from_destination() {
+        let cases = [
+            Operation::TransferAccToY,
+            Operation::TransferXToAcc,
+            Operation::TransferYToAcc,
+            Operation::TransferStackptrToX,
+        ];
+
+        for operation in cases {
+            let mnemonic = operation.mnemonic();
+            let mut cpu = setup_transfer_cpu(operation);
+            cpu.registers.a = 0x80;
+            cpu.registers.x = 0x80;
+            cpu.registers.y = 0x80;
+            cpu.registers.transfer_x_to_stack_ptr();
+            cpu.registers.set_flag(CPUFlag::Zero);
+
+            let cycles = cpu.step_instruction().unwrap();
+
+            assert_eq!(cycles, 2, "{}", mnemonic);
+            assert!(cpu.registers.is_flag_set(CPUFlag::Negative), "{}", mnemonic);
+            assert!(!cpu.registers.is_flag_set(CPUFlag::Zero), "{}", mnemonic);
+        }
+    }
+
+    #[test]
+    fn test_cpu_tsx_copies_stack_pointer() {
+        let mut cpu = setup_transfer_cpu(Operation::TransferStackptrToX);
+        cpu.registers.x = 0xFD;
+        cpu.registers.transfer_x_to_stack_ptr();
+        cpu.registers.x = 0x00;
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.x, 0xFD);
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+    }
+
+    fn setup_stack_cpu(operation: Operation, stack_ptr: u8) -> CPU<TestBus> {
+        let mut cpu = setup_transfer_cpu(operation);
+        cpu.registers.x = stack_ptr;
+        cpu.registers.transfer_x_to_stack_ptr();
+        cpu.registers.x = 0x00;
+        cpu
+    }
+
+    #[test]
+    fn test_cpu_pha_wraps_stack_pointer() {
+        let mut cpu = setup_stack_cpu(Operation::PushAcc, 0x00);
+        cpu.registers.a = 0x42;
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 3);
+        assert_eq!(cpu.bus.read(0x0100), 0x42);
+        assert_eq!(cpu.registers.stack_ptr(), 0xFF);
+    }
+
+    #[test]
+    fn test_cpu_pla_wraps_stack_pointer() {
+        let mut cpu = setup_stack_cpu(Operation::PullAcc, 0xFF);
+        cpu.bus.write(0x0100, 0x80);
+        cpu.registers.set_flag(CPUFlag::Zero);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 4);
+        assert_eq!(cpu.registers.a, 0x80);
+        assert_eq!(cpu.registers.stack_ptr(), 0x00);
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Zero));
+    }
+
+    #[test]
+    fn test_cpu_pla_sets_zero_flag() {
+        let mut cpu = setup_stack_cpu(Operation::PullAcc, 0xFC);
+        cpu.registers.a = 0x12;
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.a, 0x00);
+        assert!(cpu.registers.is_flag_set(CPUFlag::Zero));
+    }
+
+    #[test]
+    fn test_cpu_php_pushes_break_and_unused() {
+        let mut cpu = setup_stack_cpu(Operation::PushStatus, 0xFD);
+        cpu.registers.set_flag(CPUFlag::CarryBit);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 3);
+        assert_eq!(
+            cpu.bus.read(0x01FD),
+            CPUFlag::CarryBit.value() | CPUFlag::Break.value() | CPUFlag::Unused.value()
+        );
+        assert_eq!(cpu.registers.stack_ptr(), 0xFC);
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Break));
+    }
+
+    #[test]
+    fn test_cpu_plp_ignores_break_and_unused() {
+        let mut cpu = setup_stack_cpu(Operation::PullStatus, 0xFC);
+        cpu.bus.write(0x01FD, 0xFF);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 4);
+        assert_eq!(
+            cpu.registers.status(),
+            !(CPUFlag::Break.value() | CPUFlag::Unused.value())
+        );
+        assert_eq!(cpu.registers.stack_ptr(), 0xFD);
+    }
+
+    #[test]
+    fn test_cpu_adc_imm_binary_overflow() {
+        let mut cpu = setup_arithmetic_cpu(Operation::AdcImm, 0x7F, 0x01, false);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.registers.a, 0x80);
+        assert!(cpu.registers.is_flag_set(CPUFlag::Overflow));
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::CarryBit));
+    }
+
+    #[test]
+    fn test_cpu_sbc_imm_binary_borrow() {
+        let mut cpu = setup_arithmetic_cpu(Operation::SbcImm, 0x00, 0x01, true);
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.a, 0xFF);
+        assert!(!cpu.registers.is_flag_set(CPUFlag::CarryBit));
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+    }
+
+    #[test]
+    fn test_cpu_2a03_ignores_decimal_mode() {
+        let mut cpu = setup_arithmetic_cpu(Operation::AdcImm, 0x09, 0x01, false);
+        cpu.registers.set_flag(CPUFlag::DecimalMode);
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.variant(), CpuVariant::Ricoh2A03);
+        assert_eq!(cpu.registers.a, 0x0A);
+    }
+
+    #[test]
+    fn test_cpu_adc_imm_decimal_mode() {
+        let mut cpu = setup_arithmetic_cpu(Operation::AdcImm, 0x09, 0x01, false);
+        cpu.set_variant(CpuVariant::Nmos6502);
+        cpu.registers.set_flag(CPUFlag::DecimalMode);
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.a, 0x10);
+        assert!(!cpu.registers.is_flag_set(CPUFlag::CarryBit));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Zero));
+    }
+
+    #[test]
+    fn test_cpu_adc_imm_decimal_mode_carries_out() {
+        let mut cpu = setup_arithmetic_cpu(Operation::AdcImm, 0x99, 0x01, false);
+        cpu.set_variant(CpuVariant::Nmos6502);
+        cpu.registers.set_flag(CPUFlag::DecimalMode);
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.a, 0x00);
+        assert!(cpu.registers.is_flag_set(CPUFlag::CarryBit));
+        // Zero follows the binary sum 0x9A on the NMOS 6502
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Zero));
+    }
+
+    #[test]
+    fn test_cpu_sbc_imm_decimal_mode_with_borrow() {
+        let mut cpu = setup_arithmetic_cpu(Operation::SbcImm, 0x12, 0x21, true);
+        cpu.set_variant(CpuVariant::Nmos6502);
+        cpu.registers.set_flag(CPUFlag::DecimalMode);
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.registers.a, 0x91);
+        assert!(!cpu.registers.is_flag_set(CPUFlag::CarryBit));
+
+        // The borrow left by the first subtraction is taken from the next one, 40 - 15 - 1
+        cpu.registers.a = 0x40;
+        cpu.registers.memory_buffer = 0x15;
+        cpu.registers.sbc(true);
+
+        assert_eq!(cpu.registers.a, 0x24);
+        assert!(cpu.registers.is_flag_set(CPUFlag::CarryBit));
+    }
+
+    #[test]
+    fn test_cpu_store_acc_zero_page() {
+        let opcode = Operation::StoreAccZeroPage.get_opcode();
+        let address: u8 = 0x42;
+        let a_value: u8 = 0x37;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, address);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadAdl)
+        );
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Execution);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::StoreAccumulator)
+        );
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPage)
+        );
+
+        assert_eq!(cpu.bus.read(address as u16), a_value);
+    }
+
+    #[test]
+    fn test_cpu_store_acc_absolute_x() {
+        let opcode = Operation::StoreAccAbsoluteX.get_opcode();
+        let adl: u8 = 0xF0;
+        let adh: u8 = 0x12;
+        let x_value: u8 = 0x20;
+        let a_value: u8 = 0x99;
+        let expected_address: u16 = 0x1310;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, adh);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        for _ in 0..5 {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
+
+        assert_eq!(cpu.bus.read(expected_address), a_value);
+        assert_eq!(cpu.bus.read(0x12F0), 0);
+    }
+
+    #[test]
+    fn test_cpu_store_acc_indirect_x() {
+        let opcode = Operation::StoreAccIndirectX.get_opcode();
+        let a_value: u8 = 0x5A;
+        let x_value: u8 = 0x10;
+        let bal: u8 = 0xF8;
+        let pointer_address: u16 = 0x0008;
+        let expected_address: u16 = 0x3412;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, bal);
+        bus.write(pointer_address, 0x12);
+        bus.write(pointer_address + 1, 0x34);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        for _ in 0..6 {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.bus.read(expected_address), a_value);
+    }
+
+    #[test]
+    fn test_cpu_store_acc_indirect_y_page_crossing() {
+        let opcode = Operation::StoreAccIndirectY.get_opcode();
+        let a_value: u8 = 0xC3;
+        let y_value: u8 = 0x30;
+        let ial: u8 = 0x40;
+        let base_address: u16 = 0x20E0;
+        let expected_address: u16 = 0x2110;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, ial);
+        bus.write(ial as u16, (base_address & 0xFF) as u8);
+        bus.write(ial as u16 + 1, (base_address >> 8) as u8);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = a_value;
+        cpu.registers.y = y_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadIal)
+        );
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadBalIndirectIal)
+        );
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::ReadBahIndirectIal)
+        );
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::CalculateAdlAdhAbsoluteY)
+        );
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::StoreAccumulator)
+        );
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
+
+        assert_eq!(cpu.bus.read(expected_address), a_value);
+        // Without the carry into the high byte the write would land here
+        assert_eq!(cpu.bus.read(0x2010), 0);
+    }
+
+    #[test]
+    fn test_cpu_store_x_zero_page_y() {
+        let opcode = Operation::StoreXZeroPageY.get_opcode();
+        let bal: u8 = 0xF0;
+        let y_value: u8 = 0x20;
+        let x_value: u8 = 0x77;
+        let expected_address: u16 = 0x0010;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, bal);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.x = x_value;
+        cpu.registers.y = y_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        for _ in 0..4 {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteZeroPageBalY)
+        );
+
+        assert_eq!(cpu.bus.read(expected_address), x_value);
+    }
+
+    #[test]
+    fn test_cpu_store_y_absolute() {
+        let opcode = Operation::StoreYAbsolute.get_opcode();
+        let address: u16 = 0x0634;
+        let y_value: u8 = 0x21;
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, 0x34);
+        bus.write(0x0002, 0x06);
+
+        let mut cpu = CPU::new(bus);
+        cpu.registers.y = y_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        for _ in 0..4 {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(MicroInstruction::WriteAbsolute)
+        );
+
+        assert_eq!(cpu.bus.read(address), y_value);
+    }
+
+    fn _test_flag_operation(operation: Operation, flag: CPUFlag, expected_value: bool) {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, operation.get_opcode());
+        let mut cpu = CPU::new(bus);
+        cpu.registers.set_flag_value(flag.clone(), !expected_value);
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(
+            cpu.current_micro_instruction,
+            Some(flag_micro_instruction(flag.clone(), expected_value))
+        );
+        assert_eq!(cpu.registers.is_flag_set(flag), expected_value);
+    }
+
+    #[test]
+    fn test_cpu_flag_operations() {
+        _test_flag_operation(Operation::ClearCarry, CPUFlag::CarryBit, false);
+        _test_flag_operation(Operation::SetCarry, CPUFlag::CarryBit, true);
+        _test_flag_operation(
+            Operation::ClearInterruptDisable,
+            CPUFlag::InterruptDisable,
+            false,
+        );
+        _test_flag_operation(
+            Operation::SetInterruptDisable,
+            CPUFlag::InterruptDisable,
+            true,
+        );
+        _test_flag_operation(Operation::ClearOverflow, CPUFlag::Overflow, false);
+        _test_flag_operation(Operation::ClearDecimal, CPUFlag::DecimalMode, false);
+        _test_flag_operation(Operation::SetDecimal, CPUFlag::DecimalMode, true);
+    }
+
+    #[test]
+    fn test_cpu_clv_leaves_other_flags_untouched() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::ClearOverflow.get_opcode());
+        let mut cpu = CPU::new(bus);
+        cpu.registers.set_flag(CPUFlag::Overflow);
+        cpu.registers.set_flag(CPUFlag::CarryBit);
+        cpu.registers.set_flag(CPUFlag::Negative);
+
+        _test_read_and_decode_operation(&mut cpu);
+
+        cpu.step().unwrap();
+
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Overflow));
+        assert!(cpu.registers.is_flag_set(CPUFlag::CarryBit));
+        assert!(cpu.registers.is_flag_set(CPUFlag::Negative));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::Zero));
+        assert!(!cpu.registers.is_flag_set(CPUFlag::DecimalMode));
+    }
+
+    fn run_load_acc_absolute_x(adl: u8, x_value: u8) -> CPU<TestBus> {
+        let opcode = Operation::LoadAccAbsoluteX.get_opcode();
+
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, adl);
+        bus.write(0x0002, 0x12);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.x = x_value;
+
+        _test_read_and_decode_operation(&mut cpu);
+        _test_absolute_x_read(&mut cpu);
+        cpu.step().unwrap();
+
+        cpu
+    }
+
+    #[test]
+    fn test_cpu_load_acc_absolute_x_page_crossed() {
+        let cpu = run_load_acc_absolute_x(0xFF, 0x01);
+
+        assert!(cpu.last_page_crossed());
+        assert_eq!(cpu.cycle_count(), 5);
+    }
+
+    #[test]
+    fn test_cpu_load_acc_absolute_x_page_not_crossed() {
+        let cpu = run_load_acc_absolute_x(0x10, 0x01);
+
+        assert!(!cpu.last_page_crossed());
+        assert_eq!(cpu.cycle_count(), 4);
+    }
+
+    #[test]
+    fn test_cpu_store_acc_absolute_x_never_reports_page_crossed() {
+        let mut cpu = run_load_acc_absolute_x(0xFF, 0x01);
+        let opcode = Operation::StoreAccAbsoluteX.get_opcode();
+        cpu.bus.write(0x0003, opcode);
+        cpu.bus.write(0x0004, 0xFF);
+        cpu.bus.write(0x0005, 0x12);
+
+        _test_read_and_decode_operation(&mut cpu);
+        while cpu.state == CPUState::Execution {
+            cpu.step().unwrap();
+        }
+
+        assert!(!cpu.last_page_crossed());
+    }
+
+    #[test]
+    fn test_cpu_reset() {
+        let mut bus = TestBus::new();
+        bus.write(RESET_VECTOR_ADDRESS, 0x34);
+        bus.write(RESET_VECTOR_ADDRESS + 1, 0x12);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.a = 0x56;
+
+        cpu.reset();
+
+        assert_eq!(cpu.program_counter(), 0x1234);
+        assert_eq!(cpu.registers.stack_ptr(), 0xFD);
+        assert!(cpu.registers.is_flag_set(CPUFlag::InterruptDisable));
+        assert_eq!(cpu.registers.a, 0x56);
+        assert_eq!(cpu.state, CPUState::Fetching);
+        assert_eq!(cpu.cycle_count(), RESET_CYCLES);
+    }
+
+    #[test]
+    fn test_cpu_nmi_waits_for_instruction_end() {
+        let mut bus = TestBus::new();
+        bus.write(0x0200, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0201, 0x42);
+        bus.write(NMI_VECTOR_ADDRESS, 0x00);
+        bus.write(NMI_VECTOR_ADDRESS + 1, 0x90);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0x0200);
+        cpu.registers.clear_flag(CPUFlag::InterruptDisable);
+        cpu.registers.set_flag(CPUFlag::CarryBit);
+
+        _test_read_and_decode_operation(&mut cpu);
+        cpu.request_nmi();
+        while cpu.state == CPUState::Execution {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.registers.a, 0x42);
+        let cycles = cpu.cycle_count();
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.program_counter(), 0x9000);
+        assert_eq!(cpu.cycle_count(), cycles + INTERRUPT_CYCLES);
+        assert!(cpu.registers.is_flag_set(CPUFlag::InterruptDisable));
+        // Return address and status with B clear below the stack pointer
+        assert_eq!(cpu.registers.stack_ptr(), 0xFA);
+        assert_eq!(cpu.bus.read(0x01FD), 0x02);
+        assert_eq!(cpu.bus.read(0x01FC), 0x02);
+        assert_eq!(cpu.bus.read(0x01FB), 0b0010_0001);
+    }
+
+    #[test]
+    fn test_cpu_takes_nmi_signalled_by_bus() {
+        let mut bus = TestBus::new();
+        bus.write(NMI_VECTOR_ADDRESS, 0x34);
+        bus.write(NMI_VECTOR_ADDRESS + 1, 0x12);
+        bus.nmi = true;
+        let mut cpu = CPU::new(bus);
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.program_counter(), 0x1234);
+        assert!(!cpu.bus.nmi);
+    }
+
+    #[test]
+    fn test_cpu_unknown_opcode_is_reported() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, 0x02);
+        let mut cpu = CPU::new(bus);
+
+        let error = CpuError::UnknownOpcode {
+            opcode: 0x02,
+            address: 0x0000,
+        };
+        assert_eq!(cpu.step_instruction(), Err(error));
+        assert_eq!(cpu.program_counter(), 0x0000);
+        // The CPU stays on the opcode instead of running off into garbage
+        assert_eq!(cpu.step_instruction(), Err(error));
+        assert_eq!(error.to_string(), "unknown opcode 0x02 at 0x0000");
+    }
+
+    #[test]
+    fn test_cpu_step_instruction_runs_lda_immediate() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0001, 0x42);
+        let mut cpu = CPU::new(bus);
+
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.cycle_count(), 2);
+        assert_eq!(cpu.registers.a, 0x42);
+        assert_eq!(cpu.program_counter(), 0x0002);
+        assert_eq!(cpu.state, CPUState::Fetching);
+    }
+
+    #[test]
+    fn test_cpu_snapshot_after_instructions() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0001, 0x42);
+        bus.write(0x0002, Operation::LoadXImm.get_opcode());
+        bus.write(0x0003, 0x80);
+        bus.write(0x0004, Operation::SetCarry.get_opcode());
+        let mut cpu = CPU::new(bus);
+        let status = cpu.status();
+
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+
+        let snapshot = cpu.snapshot();
+        assert_eq!(
+            snapshot,
+            RegisterSnapshot {
+                pc: 0x0005,
+                sp: cpu.sp(),
+                status: status | CPUFlag::Negative.value() | CPUFlag::CarryBit.value(),
+                a: 0x42,
+                x: 0x80,
+                y: 0x00,
+            }
+        );
+        assert_eq!(snapshot.pc, cpu.pc());
+        assert_eq!(snapshot.a, cpu.a());
+        assert_eq!(snapshot.x, cpu.x());
+        assert_eq!(snapshot.y, cpu.y());
+    }
+
+    #[test]
+    fn test_cpu_save_state_round_trips() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0001, 0x42);
+        bus.write(0x0002, Operation::LoadXImm.get_opcode());
+        bus.write(0x0003, 0x80);
+        bus.write(0x0004, Operation::SetCarry.get_opcode());
+        bus.write(0x0005, Operation::IncY.get_opcode());
+        let mut cpu = CPU::new(bus);
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+        // Stop in the middle of SEC to check the micro-instruction state is kept as well
+        cpu.step().unwrap();
+        let snapshot = cpu.snapshot();
+        let cycles = cpu.cycle_count();
+
+        let state = cpu.save_state();
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+        cpu.registers.a = 0x00;
+        cpu.load_state(&state).unwrap();
+
+        assert_eq!(cpu.snapshot(), snapshot);
+        assert_eq!(cpu.cycle_count(), cycles);
+        cpu.step_instruction().unwrap();
+        assert!(cpu.flag(CPUFlag::CarryBit));
+        assert_eq!(cpu.pc(), 0x0005);
+    }
+
+    #[test]
+    fn test_cpu_load_state_rejects_malformed_blob() {
+        let mut cpu = CPU::new(TestBus::new());
+        let snapshot = cpu.snapshot();
+
+        assert!(cpu.load_state(&[0x01, 0x02]).is_err());
+        assert_eq!(cpu.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_cpu_trace_formats_lda_immediate_like_nestest() {
+        let mut bus = TestBus::new();
+        bus.write(0xC000, Operation::LoadAccImm.get_opcode());
+        bus.write(0xC001, 0x42);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC000);
+
+        assert_eq!(
+            cpu.trace(),
+            "C000  A9 42     LDA #$42                        A:00 X:00 Y:00 P:24 SP:FD"
+        );
+    }
+
+    #[test]
+    fn test_cpu_trace_annotates_indexed_operands() {
+        let mut bus = TestBus::new();
+        bus.write(0x0300, Operation::LoadAccAbsoluteX.get_opcode());
+        bus.write(0x0301, 0x00);
+        bus.write(0x0302, 0x03);
+        bus.write(0x0305, 0x89);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0x0300);
+        cpu.registers.x = 0x05;
+
+        assert_eq!(
+            cpu.trace(),
+            "0300  BD 00 03  LDA $0300,X @ 0305 = 89         A:00 X:05 Y:00 P:24 SP:FD"
+        );
+    }
+
+    #[test]
+    fn test_cpu_trace_entry_status_matches_trace() {
+        let mut cpu = CPU::new(TestBus::new());
+        cpu.reset();
+
+        assert_eq!(cpu.trace_entry().status, 0x24);
+        assert!(cpu.trace().ends_with("P:24 SP:FD"));
+    }
+
+    #[test]
+    fn test_cpu_trace_shows_branch_target() {
+        let mut bus = TestBus::new();
+        bus.write(0xC72A, Operation::BranchIfNotEqual.get_opcode());
+        bus.write(0xC72B, 0x01);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC72A);
+
+        assert_eq!(
+            cpu.trace(),
+            "C72A  D0 01     BNE $C72D                       A:00 X:00 Y:00 P:24 SP:FD"
+        );
+    }
+
+    #[test]
+    fn test_cpu_vector_accessors() {
+        let mut bus = TestBus::new();
+        bus.write(NMI_VECTOR_ADDRESS, 0x11);
+        bus.write(NMI_VECTOR_ADDRESS + 1, 0x22);
+        bus.write(RESET_VECTOR_ADDRESS, 0x33);
+        bus.write(RESET_VECTOR_ADDRESS + 1, 0x44);
+        bus.write(IRQ_VECTOR_ADDRESS, 0x55);
+        bus.write(IRQ_VECTOR_ADDRESS + 1, 0x66);
+        let mut cpu = CPU::new(bus);
+
+        assert_eq!(cpu.nmi_vector(), 0x2211);
+        assert_eq!(cpu.reset_vector(), 0x4433);
+        assert_eq!(cpu.irq_vector(), 0x6655);
+    }
+
+    #[test]
+    fn test_cpu_run_until_stops_when_predicate_holds() {
+        let mut bus = TestBus::new();
+        for address in 0xC000..0xC010 {
+            bus.write(address, Operation::IncX.get_opcode());
+        }
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC000);
+
+        let cycles = cpu.run_until(|cpu| cpu.x() == 5, 1000);
+
+        assert_eq!(cycles, Ok(10));
+        assert_eq!(cpu.x(), 5);
+    }
+
+    #[test]
+    fn test_cpu_run_until_gives_up_on_endless_loop() {
+        // BVC to itself, overflow is clear so it spins forever
+        let mut bus = TestBus::new();
+        bus.write(0xC000, Operation::BranchIfOverflowClear.get_opcode());
+        bus.write(0xC001, 0xFE);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC000);
+
+        let result = cpu.run_until(|cpu| cpu.pc() != 0xC000, 100);
+
+        // Each pass takes three cycles, the budget is checked before every instruction
+        assert_eq!(result, Err(CpuError::CycleBudgetExhausted { cycles: 102 }));
+        assert_eq!(cpu.pc(), 0xC000);
+    }
+
+    #[test]
+    fn test_cpu_run_traced_collects_one_line_per_instruction() {
+        let mut bus = TestBus::new();
+        bus.write(0xC000, Operation::LoadAccImm.get_opcode());
+        bus.write(0xC001, 0x42);
+        bus.write(0xC002, Operation::TransferAccToX.get_opcode());
+        bus.write(0xC003, Operation::IncX.get_opcode());
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC000);
+
+        let lines = cpu.run_traced(3);
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("C000  A9 42     LDA #$42"));
+        assert!(lines[2].contains("INX"));
+        assert_eq!(cpu.x(), 0x43);
+    }
+
+    #[test]
+    fn test_cpu_run_traced_stops_at_unknown_opcode() {
+        let mut bus = TestBus::new();
+        bus.write(0xC000, Operation::IncX.get_opcode());
+        bus.write(0xC001, 0x02);
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0xC000);
+
+        let lines = cpu.run_traced(5);
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("???"));
+    }
+
+    #[test]
+    fn test_cpu_ror_threads_in_carry_set_through_public_api() {
+        let mut bus = TestBus::new();
+        bus.write(0x0000, Operation::LoadAccImm.get_opcode());
+        bus.write(0x0001, 0b0000_0010);
+        bus.write(0x0002, Operation::RorA.get_opcode());
+        let mut cpu = CPU::new(bus);
+
+        cpu.step_instruction().unwrap();
+        cpu.set_flag(CPUFlag::CarryBit, true);
+        assert!(cpu.flag(CPUFlag::CarryBit));
+        let cycles = cpu.step_instruction().unwrap();
+
+        assert_eq!(cycles, 2);
+        assert_eq!(cpu.a(), 0b1000_0001);
+        assert!(!cpu.flag(CPUFlag::CarryBit));
+        assert!(cpu.flag(CPUFlag::Negative));
+        assert!(!cpu.flag(CPUFlag::Zero));
+    }
+
+    // IRQ handler at 0x9000, program at 0x0200, IRQs masked and the IRQ line held low
+    fn setup_irq_cpu(program: &[Operation]) -> CPU<TestBus> {
+        let mut bus = TestBus::new();
+        for (offset, operation) in program.iter().enumerate() {
+            bus.write(0x0200 + offset as u16, operation.get_opcode());
+        }
+        bus.write(IRQ_VECTOR_ADDRESS, 0x00);
+        bus.write(IRQ_VECTOR_ADDRESS + 1, 0x90);
+        bus.irq = true;
+        let mut cpu = CPU::new(bus);
+        cpu.registers.reset(0x0200);
+        cpu
+    }
+
+    #[test]
+    fn test_cpu_irq_is_delayed_one_instruction_after_cli() {
+        let mut cpu = setup_irq_cpu(&[
+            Operation::ClearInterruptDisable,
+            Operation::ClearCarry,
+            Operation::ClearCarry,
+        ]);
+
+        cpu.step_instruction().unwrap();
+        assert!(!cpu.registers.is_flag_set(CPUFlag::InterruptDisable));
+        // CLI cleared I after the poll, the next instruction still runs
+        cpu.step_instruction().unwrap();
+        assert_eq!(cpu.program_counter(), 0x0202);
+        let cycles = cpu.cycle_count();
+
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.program_counter(), 0x9000);
+        assert_eq!(cpu.cycle_count(), cycles + INTERRUPT_CYCLES);
+        assert!(cpu.registers.is_flag_set(CPUFlag::InterruptDisable));
+        // Return address points at the instruction skipped by the IRQ
+        assert_eq!(cpu.bus.read(0x01FD), 0x02);
+        assert_eq!(cpu.bus.read(0x01FC), 0x02);
+    }
+
+    #[test]
+    fn test_cpu_irq_is_taken_right_after_sei() {
+        let mut cpu = setup_irq_cpu(&[Operation::SetInterruptDisable, Operation::ClearCarry]);
+        cpu.registers.clear_flag(CPUFlag::InterruptDisable);
+
+        cpu.step_instruction().unwrap();
+        assert!(cpu.registers.is_flag_set(CPUFlag::InterruptDisable));
+        assert!(!cpu.take_irq_serviced());
+        // SEI set I after the poll, so the IRQ still gets in
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.program_counter(), 0x9000);
+        assert_eq!(cpu.bus.read(0x01FC), 0x01);
+        assert!(cpu.take_irq_serviced());
+        assert!(!cpu.take_irq_serviced());
+    }
+
+    #[test]
+    fn test_cpu_ignores_masked_irq() {
+        let mut cpu = setup_irq_cpu(&[Operation::ClearCarry, Operation::ClearCarry]);
+
+        cpu.step_instruction().unwrap();
+        cpu.step_instruction().unwrap();
+
+        assert_eq!(cpu.program_counter(), 0x0202);
+        assert!(!cpu.take_irq_serviced());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "LoadAccumulator executed before the addressing sequence read its operand"
+    )]
+    fn test_cpu_operation_without_operand_read_trips_assertion() {
+        let opcode = Operation::LoadAccImm.get_opcode();
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        let mut cpu = CPU::new(bus);
+        _test_read_and_decode_operation(&mut cpu);
+
+        // Addressing sequence that resolves the address but never reads from it
+        cpu.registers.set_decoded_operation(
+            Some(MicroInstructionSequence::new(vec![
+                MicroInstruction::ReadAdl,
+            ])),
+            MicroInstructionSequence::new(vec![MicroInstruction::LoadAccumulator]),
+        );
+
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+    }
+
+    #[test]
+    fn test_cpu_rmw_operation_after_operand_read() {
+        let opcode = Operation::IncMemZeroPage.get_opcode();
+        let mut bus = TestBus::new();
+        bus.write(0x0000, opcode);
+        bus.write(0x0001, 0x10);
+        bus.write(0x0010, 0x41);
+        let mut cpu = CPU::new(bus);
+
+        _test_read_and_decode_operation(&mut cpu);
+        while cpu.state == CPUState::Execution {
+            cpu.step().unwrap();
+        }
+
+        assert_eq!(cpu.bus.read(0x0010), 0x42);
+    }
 }
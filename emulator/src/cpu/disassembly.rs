@@ -0,0 +1,122 @@
+use crate::bus::BusLike;
+use crate::cpu::operations::{AddressingMode, Operation};
+
+// One decoded instruction: its raw bytes and the nestest-style text, e.g. "LDA $0300,X @ 0301 = 89"
+pub struct Disassembly {
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+// Decodes the instruction at pc, memory operands are annotated with the values they point at
+// the way nestest.log does, see https://www.qmtpro.com/~nes/misc/nestest.log
+// Memory is only peeked, so operands the bus can't peek (e.g. $2002) are left without a value
+pub fn disassemble<T: BusLike>(bus: &T, pc: u16, x: u8, y: u8) -> Disassembly {
+    let unknown = |bytes| Disassembly {
+        bytes,
+        text: "???".to_string(),
+    };
+    let Some(opcode) = bus.peek(pc) else {
+        return unknown(Vec::new());
+    };
+    let Some(operation) = Operation::get_operation(opcode) else {
+        return unknown(vec![opcode]);
+    };
+
+    let Some(bytes) = (0..operation.length())
+        .map(|offset| bus.peek(pc.wrapping_add(offset)))
+        .collect::<Option<Vec<u8>>>()
+    else {
+        return unknown(vec![opcode]);
+    };
+    let low = bytes.get(1).copied().unwrap_or(0);
+    let absolute = ((bytes.get(2).copied().unwrap_or(0) as u16) << 8) | low as u16;
+
+    let mode = operation.addressing_mode();
+    let operand = match mode {
+        AddressingMode::Implied => String::new(),
+        AddressingMode::Accumulator => " A".to_string(),
+        AddressingMode::Immediate => format!(" #${:02X}", low),
+        AddressingMode::ZeroPage => format!(" ${:02X}{}", low, value(bus, low as u16)),
+        AddressingMode::ZeroPageX | AddressingMode::ZeroPageY => {
+            let (register, index) = if mode == AddressingMode::ZeroPageX {
+                ('X', x)
+            } else {
+                ('Y', y)
+            };
+            let address = low.wrapping_add(index);
+            format!(
+                " ${:02X},{} @ {:02X}{}",
+                low,
+                register,
+                address,
+                value(bus, address as u16)
+            )
+        }
+        AddressingMode::Absolute => format!(" ${:04X}{}", absolute, value(bus, absolute)),
+        AddressingMode::AbsoluteX | AddressingMode::AbsoluteY => {
+            let (register, index) = if mode == AddressingMode::AbsoluteX {
+                ('X', x)
+            } else {
+                ('Y', y)
+            };
+            let address = absolute.wrapping_add(index as u16);
+            format!(
+                " ${:04X},{} @ {:04X}{}",
+                absolute,
+                register,
+                address,
+                value(bus, address)
+            )
+        }
+        AddressingMode::Relative => {
+            let target = pc
+                .wrapping_add(operation.length())
+                .wrapping_add(low as i8 as u16);
+            format!(" ${:04X}", target)
+        }
+        AddressingMode::IndirectX => {
+            let pointer = low.wrapping_add(x);
+            match peek_u16_zp_wrap(bus, pointer) {
+                Some(address) => format!(
+                    " (${:02X},X) @ {:02X} = {:04X}{}",
+                    low,
+                    pointer,
+                    address,
+                    value(bus, address)
+                ),
+                None => format!(" (${:02X},X) @ {:02X}", low, pointer),
+            }
+        }
+        AddressingMode::IndirectY => match peek_u16_zp_wrap(bus, low) {
+            Some(base) => {
+                let address = base.wrapping_add(y as u16);
+                format!(
+                    " (${:02X}),Y = {:04X} @ {:04X}{}",
+                    low,
+                    base,
+                    address,
+                    value(bus, address)
+                )
+            }
+            None => format!(" (${:02X}),Y", low),
+        },
+    };
+
+    Disassembly {
+        bytes,
+        text: format!("{}{}", operation.mnemonic(), operand),
+    }
+}
+
+// The " = XX" annotation, empty when the bus can't peek the address
+fn value<T: BusLike>(bus: &T, address: u16) -> String {
+    bus.peek(address)
+        .map(|value| format!(" = {:02X}", value))
+        .unwrap_or_default()
+}
+
+fn peek_u16_zp_wrap<T: BusLike>(bus: &T, address: u8) -> Option<u16> {
+    let low = bus.peek(address as u16)? as u16;
+    let high = bus.peek(address.wrapping_add(1) as u16)? as u16;
+    Some((high << 8) | low)
+}
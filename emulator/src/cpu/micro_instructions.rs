@@ -1,4 +1,7 @@
-#[derive(Clone, PartialEq, Debug)]
+use crate::cpu::cpu::CPUFlag;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum MicroInstruction {
     Empty,
     ReadOperationCode,
@@ -20,13 +23,17 @@ pub enum MicroInstruction {
     ReadIal,
     ReadBalIndirectIal,
     ReadBahIndirectIal,
+    CalculateAdlAdhAbsoluteX,
+    CalculateAdlAdhAbsoluteY,
 
     WriteZeroPage,
     WriteAbsolute,
     WriteZeroPageBalX,
+    WriteZeroPageBalY,
 
     ShiftLeftAccumulator,
     ShiftLeftMemoryBuffer,
+    RotateRightAccumulator,
 
     IncrementMemoryBuffer,
     IncrementX,
@@ -38,10 +45,127 @@ pub enum MicroInstruction {
     LoadAccumulator,
     LoadX,
     LoadY,
+    StoreAccumulator,
+    StoreX,
+    StoreY,
+
+    TransferAccumulatorToX,
+    TransferAccumulatorToY,
+    TransferXToAccumulator,
+    TransferYToAccumulator,
+    TransferStackptrToX,
+    TransferXToStackptr,
+
+    PushAccumulator,
+    PushStatus,
+    PullAccumulator,
+    PullStatus,
 
     And,
+    Or,
+    AddWithCarry,
+    SubtractWithCarry,
+
+    // Jumps by the signed offset in the memory buffer when the flag has the given value
+    Branch(CPUFlag, bool),
+
+    SetFlag(CPUFlag),
+    ClearFlag(CPUFlag),
+}
+
+impl MicroInstruction {
+    // Decoding, register loads, stores and read-modify-write updates overlap a bus cycle
+    pub fn consumes_cycle(&self) -> bool {
+        !matches!(
+            self,
+            Self::DecodeOperation
+                | Self::LoadAccumulator
+                | Self::LoadX
+                | Self::LoadY
+                | Self::And
+                | Self::Or
+                | Self::AddWithCarry
+                | Self::SubtractWithCarry
+                | Self::Branch(..)
+                | Self::StoreAccumulator
+                | Self::StoreX
+                | Self::StoreY
+                | Self::ShiftLeftMemoryBuffer
+                | Self::IncrementMemoryBuffer
+                | Self::DecrementMemoryBuffer
+        )
+    }
+
+    // Loads the operand into the memory buffer
+    pub fn reads_operand(&self) -> bool {
+        matches!(
+            self,
+            Self::ImmediateRead
+                | Self::ReadZeroPage
+                | Self::ReadAbsolute
+                | Self::ReadZeroPageBalX
+                | Self::ReadZeroPageBalY
+                | Self::ReadAdlAdhAbsoluteX
+                | Self::ReadAdlAdhAbsoluteY
+        )
+    }
+
+    // Works on the operand the addressing sequence read into the memory buffer
+    pub fn requires_operand(&self) -> bool {
+        matches!(
+            self,
+            Self::ShiftLeftMemoryBuffer
+                | Self::IncrementMemoryBuffer
+                | Self::DecrementMemoryBuffer
+                | Self::LoadAccumulator
+                | Self::LoadX
+                | Self::LoadY
+                | Self::And
+                | Self::Or
+                | Self::AddWithCarry
+                | Self::SubtractWithCarry
+                | Self::Branch(..)
+        )
+    }
+
+    // Writes to the bus, the memory buffer or a push
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Self::WriteZeroPage
+                | Self::WriteAbsolute
+                | Self::WriteZeroPageBalX
+                | Self::WriteZeroPageBalY
+                | Self::PushAccumulator
+                | Self::PushStatus
+        )
+    }
+
+    // Fetches an operand byte of the instruction itself
+    pub fn fetches_instruction_byte(&self) -> bool {
+        matches!(
+            self,
+            Self::ImmediateRead
+                | Self::ReadAdl
+                | Self::ReadAdh
+                | Self::ReadBal
+                | Self::ReadBah
+                | Self::ReadIal
+        )
+    }
+
+    // Indexed absolute reads take an extra cycle when the index crosses a page
+    pub fn is_indexed_read(&self) -> bool {
+        matches!(self, Self::ReadAdlAdhAbsoluteX | Self::ReadAdlAdhAbsoluteY)
+    }
+
+    // Branches take an extra cycle when taken and another when crossing a page
+    pub fn is_branch(&self) -> bool {
+        matches!(self, Self::Branch(..))
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MicroInstructionSequence {
     sequence: Vec<MicroInstruction>,
     idx: usize,
@@ -52,6 +176,10 @@ impl MicroInstructionSequence {
         Self { sequence, idx: 0 }
     }
 
+    pub fn micro_instructions(&self) -> &[MicroInstruction] {
+        &self.sequence
+    }
+
     pub fn get_micro_instruction(&self) -> &MicroInstruction {
         &self.sequence[self.idx]
     }
@@ -60,6 +188,10 @@ impl MicroInstructionSequence {
         self.idx += 1;
     }
 
+    pub fn is_at_start(&self) -> bool {
+        self.idx == 0
+    }
+
     pub fn is_completed(&self) -> bool {
         self.idx >= self.sequence.len()
     }
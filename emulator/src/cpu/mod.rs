@@ -1,4 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod cpu;
+pub mod disassembly;
 pub mod micro_instructions;
 pub mod operations;
 pub mod registers;
+pub mod trace;
@@ -1,3 +1,4 @@
+use crate::cpu::cpu::CPUFlag;
 use crate::cpu::micro_instructions::{MicroInstruction, MicroInstructionSequence};
 
 #[derive(PartialEq, Debug)]
@@ -6,6 +7,7 @@ pub enum Operation {
     AslZeroPage,
     AslZeroPageX,
     AslAbsolute,
+    RorA,
     IncMemZeroPage,
     IncMemZeroPageX,
     IncMemAbsolute,
@@ -44,13 +46,129 @@ pub enum Operation {
     AndAbsoluteY,
     AndIndirectX,
     AndIndirectY,
+    OraImm,
+    OraZeroPage,
+    OraZeroPageX,
+    OraAbsolute,
+    OraAbsoluteX,
+    OraAbsoluteY,
+    OraIndirectX,
+    OraIndirectY,
+    AdcImm,
+    SbcImm,
+    StoreAccZeroPage,
+    StoreAccZeroPageX,
+    StoreAccAbsolute,
+    StoreAccAbsoluteX,
+    StoreAccAbsoluteY,
+    StoreAccIndirectX,
+    StoreAccIndirectY,
+    StoreXZeroPage,
+    StoreXZeroPageY,
+    StoreXAbsolute,
+    StoreYZeroPage,
+    StoreYZeroPageX,
+    StoreYAbsolute,
+    TransferAccToX,
+    TransferAccToY,
+    TransferXToAcc,
+    TransferYToAcc,
+    TransferStackptrToX,
+    TransferXToStackptr,
+    PushAcc,
+    PushStatus,
+    PullAcc,
+    PullStatus,
+    BranchIfPositive,
+    BranchIfMinus,
+    BranchIfOverflowClear,
+    BranchIfOverflowSet,
+    BranchIfCarryClear,
+    BranchIfCarrySet,
+    BranchIfNotEqual,
+    BranchIfEqual,
+    ClearCarry,
+    SetCarry,
+    ClearInterruptDisable,
+    SetInterruptDisable,
+    ClearOverflow,
+    ClearDecimal,
+    SetDecimal,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AddressingMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,
+    IndirectY,
+    Relative,
+}
+
+impl AddressingMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Implied => "Implied",
+            Self::Accumulator => "Accumulator",
+            Self::Immediate => "Immediate",
+            Self::ZeroPage => "ZeroPage",
+            Self::ZeroPageX => "ZeroPage,X",
+            Self::ZeroPageY => "ZeroPage,Y",
+            Self::Absolute => "Absolute",
+            Self::AbsoluteX => "Absolute,X",
+            Self::AbsoluteY => "Absolute,Y",
+            Self::IndirectX => "(Indirect,X)",
+            Self::IndirectY => "(Indirect),Y",
+            Self::Relative => "Relative",
+        }
+    }
+
+    // Number of bytes an instruction with this mode takes, opcode included
+    pub fn length(&self) -> u16 {
+        match self {
+            Self::Implied | Self::Accumulator => 1,
+            Self::Absolute | Self::AbsoluteX | Self::AbsoluteY => 3,
+            _ => 2,
+        }
+    }
+}
+
+// Invariant: when the operation sequence works on the memory buffer, the addressing
+// sequence ends with a read of the operand. The CPU checks it with a debug assertion.
 pub struct OperationMicroInstructions {
     pub addressing_sequence: Option<MicroInstructionSequence>,
     pub operation_sequence: MicroInstructionSequence,
 }
 
+// Branches fetch the offset like an immediate operand, the cycles a taken branch spends
+// on top of that are added by the CPU, see MicroInstruction::Branch
+pub fn branch_micro_instructions(flag: CPUFlag, set: bool) -> OperationMicroInstructions {
+    OperationMicroInstructions {
+        addressing_sequence: Some(MicroInstructionSequence::new(vec![
+            MicroInstruction::ImmediateRead,
+        ])),
+        operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Branch(
+            flag, set,
+        )]),
+    }
+}
+
+// All the flag instructions (CLC, SEC, CLV, ...) share the same two micro-instructions
+pub fn flag_micro_instruction(flag: CPUFlag, set: bool) -> MicroInstruction {
+    if set {
+        MicroInstruction::SetFlag(flag)
+    } else {
+        MicroInstruction::ClearFlag(flag)
+    }
+}
+
 impl Operation {
     pub fn get_micro_instructions(&self) -> OperationMicroInstructions {
         let zero_page_addressing = MicroInstructionSequence::new(vec![
@@ -83,7 +201,6 @@ impl Operation {
             MicroInstruction::ReadBal,
             MicroInstruction::ReadBah,
             MicroInstruction::ReadAdlAdhAbsoluteX,
-            // TODO: Check if this is correct (T4 is optional if page boundary is not crossed)
         ]);
         let absolute_y_addressing = MicroInstructionSequence::new(vec![
             MicroInstruction::ReadBal,
@@ -95,11 +212,50 @@ impl Operation {
             MicroInstruction::ReadBalIndirectIal,
             MicroInstruction::ReadBahIndirectIal,
             MicroInstruction::ReadAdlAdhAbsoluteY,
-            // TODO: Same as absolute_x_addressing
         ]);
         let immediate_addressing =
             MicroInstructionSequence::new(vec![MicroInstruction::ImmediateRead]);
 
+        // Stores only resolve the effective address, reading the target first
+        // would trigger side effects on memory mapped registers
+        let zero_page_store_addressing =
+            MicroInstructionSequence::new(vec![MicroInstruction::ReadAdl]);
+        let zero_page_indexed_store_addressing =
+            MicroInstructionSequence::new(vec![MicroInstruction::ReadBal, MicroInstruction::Empty]);
+        let absolute_store_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadAdl,
+            MicroInstruction::ReadAdh,
+        ]);
+        let absolute_x_store_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadBal,
+            MicroInstruction::ReadBah,
+            MicroInstruction::CalculateAdlAdhAbsoluteX,
+        ]);
+        // Read-modify-write always spends the fix-up cycle before reading the operand
+        let absolute_x_rmw_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadBal,
+            MicroInstruction::ReadBah,
+            MicroInstruction::CalculateAdlAdhAbsoluteX,
+            MicroInstruction::ReadAbsolute,
+        ]);
+        let absolute_y_store_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadBal,
+            MicroInstruction::ReadBah,
+            MicroInstruction::CalculateAdlAdhAbsoluteY,
+        ]);
+        let indirect_x_store_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadBal,
+            MicroInstruction::Empty,
+            MicroInstruction::ReadAdlIndirectBal,
+            MicroInstruction::ReadAdhIndirectBal,
+        ]);
+        let indirect_y_store_addressing = MicroInstructionSequence::new(vec![
+            MicroInstruction::ReadIal,
+            MicroInstruction::ReadBalIndirectIal,
+            MicroInstruction::ReadBahIndirectIal,
+            MicroInstruction::CalculateAdlAdhAbsoluteY,
+        ]);
+
         match self {
             Self::AslA => OperationMicroInstructions {
                 addressing_sequence: None,
@@ -107,9 +263,18 @@ impl Operation {
                     MicroInstruction::ShiftLeftAccumulator,
                 ]),
             },
+            Self::RorA => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::RotateRightAccumulator,
+                ]),
+            },
             Self::AslZeroPage => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_addressing),
+                // Read-modify-write puts the unmodified value back on the bus while the ALU
+                // works, mappers watching writes see both values
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPage,
                     MicroInstruction::ShiftLeftMemoryBuffer,
                     MicroInstruction::WriteZeroPage,
                 ]),
@@ -117,6 +282,7 @@ impl Operation {
             Self::AslZeroPageX => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_x_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPageBalX,
                     MicroInstruction::ShiftLeftMemoryBuffer,
                     MicroInstruction::WriteZeroPageBalX,
                 ]),
@@ -124,6 +290,7 @@ impl Operation {
             Self::AslAbsolute => OperationMicroInstructions {
                 addressing_sequence: Some(absolute_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteAbsolute,
                     MicroInstruction::ShiftLeftMemoryBuffer,
                     MicroInstruction::WriteAbsolute,
                 ]),
@@ -131,6 +298,7 @@ impl Operation {
             Self::IncMemZeroPage => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPage,
                     MicroInstruction::IncrementMemoryBuffer,
                     MicroInstruction::WriteZeroPage,
                 ]),
@@ -138,6 +306,7 @@ impl Operation {
             Self::IncMemZeroPageX => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_x_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPageBalX,
                     MicroInstruction::IncrementMemoryBuffer,
                     MicroInstruction::WriteZeroPageBalX,
                 ]),
@@ -145,13 +314,15 @@ impl Operation {
             Self::IncMemAbsolute => OperationMicroInstructions {
                 addressing_sequence: Some(absolute_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteAbsolute,
                     MicroInstruction::IncrementMemoryBuffer,
                     MicroInstruction::WriteAbsolute,
                 ]),
             },
             Self::IncMemAbsoluteX => OperationMicroInstructions {
-                addressing_sequence: Some(absolute_x_addressing),
+                addressing_sequence: Some(absolute_x_rmw_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteAbsolute,
                     MicroInstruction::IncrementMemoryBuffer,
                     MicroInstruction::WriteAbsolute,
                 ]),
@@ -171,6 +342,7 @@ impl Operation {
             Self::DecMemZeroPage => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPage,
                     MicroInstruction::DecrementMemoryBuffer,
                     MicroInstruction::WriteZeroPage,
                 ]),
@@ -178,6 +350,7 @@ impl Operation {
             Self::DecMemZeroPageX => OperationMicroInstructions {
                 addressing_sequence: Some(zero_page_x_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteZeroPageBalX,
                     MicroInstruction::DecrementMemoryBuffer,
                     MicroInstruction::WriteZeroPageBalX,
                 ]),
@@ -185,13 +358,15 @@ impl Operation {
             Self::DecMemAbsolute => OperationMicroInstructions {
                 addressing_sequence: Some(absolute_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteAbsolute,
                     MicroInstruction::DecrementMemoryBuffer,
                     MicroInstruction::WriteAbsolute,
                 ]),
             },
             Self::DecMemAbsoluteX => OperationMicroInstructions {
-                addressing_sequence: Some(absolute_x_addressing),
+                addressing_sequence: Some(absolute_x_rmw_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::WriteAbsolute,
                     MicroInstruction::DecrementMemoryBuffer,
                     MicroInstruction::WriteAbsolute,
                 ]),
@@ -328,12 +503,500 @@ impl Operation {
                 addressing_sequence: Some(indirect_y_addressing),
                 operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::And]),
             },
+            Self::OraImm => OperationMicroInstructions {
+                addressing_sequence: Some(immediate_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraZeroPage => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraZeroPageX => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_x_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraAbsolute => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraAbsoluteX => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_x_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraAbsoluteY => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_y_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraIndirectX => OperationMicroInstructions {
+                addressing_sequence: Some(indirect_x_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::OraIndirectY => OperationMicroInstructions {
+                addressing_sequence: Some(indirect_y_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![MicroInstruction::Or]),
+            },
+            Self::AdcImm => OperationMicroInstructions {
+                addressing_sequence: Some(immediate_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::AddWithCarry,
+                ]),
+            },
+            Self::SbcImm => OperationMicroInstructions {
+                addressing_sequence: Some(immediate_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::SubtractWithCarry,
+                ]),
+            },
+            Self::StoreAccZeroPage => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteZeroPage,
+                ]),
+            },
+            Self::StoreAccZeroPageX => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_indexed_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteZeroPageBalX,
+                ]),
+            },
+            Self::StoreAccAbsolute => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreAccAbsoluteX => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_x_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreAccAbsoluteY => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_y_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreAccIndirectX => OperationMicroInstructions {
+                addressing_sequence: Some(indirect_x_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreAccIndirectY => OperationMicroInstructions {
+                addressing_sequence: Some(indirect_y_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreAccumulator,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreXZeroPage => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreX,
+                    MicroInstruction::WriteZeroPage,
+                ]),
+            },
+            Self::StoreXZeroPageY => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_indexed_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreX,
+                    MicroInstruction::WriteZeroPageBalY,
+                ]),
+            },
+            Self::StoreXAbsolute => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreX,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::StoreYZeroPage => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreY,
+                    MicroInstruction::WriteZeroPage,
+                ]),
+            },
+            Self::StoreYZeroPageX => OperationMicroInstructions {
+                addressing_sequence: Some(zero_page_indexed_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreY,
+                    MicroInstruction::WriteZeroPageBalX,
+                ]),
+            },
+            Self::StoreYAbsolute => OperationMicroInstructions {
+                addressing_sequence: Some(absolute_store_addressing),
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::StoreY,
+                    MicroInstruction::WriteAbsolute,
+                ]),
+            },
+            Self::TransferAccToX => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferAccumulatorToX,
+                ]),
+            },
+            Self::TransferAccToY => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferAccumulatorToY,
+                ]),
+            },
+            Self::TransferXToAcc => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferXToAccumulator,
+                ]),
+            },
+            Self::TransferYToAcc => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferYToAccumulator,
+                ]),
+            },
+            Self::TransferStackptrToX => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferStackptrToX,
+                ]),
+            },
+            Self::TransferXToStackptr => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::TransferXToStackptr,
+                ]),
+            },
+            // The second cycle reads the next byte and drops it, pulls spend a third moving S
+            Self::PushAcc => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::Empty,
+                    MicroInstruction::PushAccumulator,
+                ]),
+            },
+            Self::PushStatus => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::Empty,
+                    MicroInstruction::PushStatus,
+                ]),
+            },
+            Self::PullAcc => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::Empty,
+                    MicroInstruction::Empty,
+                    MicroInstruction::PullAccumulator,
+                ]),
+            },
+            Self::PullStatus => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![
+                    MicroInstruction::Empty,
+                    MicroInstruction::Empty,
+                    MicroInstruction::PullStatus,
+                ]),
+            },
+            Self::BranchIfPositive => branch_micro_instructions(CPUFlag::Negative, false),
+            Self::BranchIfMinus => branch_micro_instructions(CPUFlag::Negative, true),
+            Self::BranchIfOverflowClear => branch_micro_instructions(CPUFlag::Overflow, false),
+            Self::BranchIfOverflowSet => branch_micro_instructions(CPUFlag::Overflow, true),
+            Self::BranchIfCarryClear => branch_micro_instructions(CPUFlag::CarryBit, false),
+            Self::BranchIfCarrySet => branch_micro_instructions(CPUFlag::CarryBit, true),
+            Self::BranchIfNotEqual => branch_micro_instructions(CPUFlag::Zero, false),
+            Self::BranchIfEqual => branch_micro_instructions(CPUFlag::Zero, true),
+            Self::ClearCarry => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::CarryBit,
+                    false,
+                )]),
+            },
+            Self::SetCarry => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::CarryBit,
+                    true,
+                )]),
+            },
+            Self::ClearInterruptDisable => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::InterruptDisable,
+                    false,
+                )]),
+            },
+            Self::SetInterruptDisable => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::InterruptDisable,
+                    true,
+                )]),
+            },
+            Self::ClearOverflow => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::Overflow,
+                    false,
+                )]),
+            },
+            Self::ClearDecimal => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::DecimalMode,
+                    false,
+                )]),
+            },
+            Self::SetDecimal => OperationMicroInstructions {
+                addressing_sequence: None,
+                operation_sequence: MicroInstructionSequence::new(vec![flag_micro_instruction(
+                    CPUFlag::DecimalMode,
+                    true,
+                )]),
+            },
+        }
+    }
+
+    // Assembler mnemonic, shared by every addressing mode of an instruction
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::AslA | Self::AslZeroPage | Self::AslZeroPageX | Self::AslAbsolute => "ASL",
+            Self::RorA => "ROR",
+            Self::IncMemZeroPage
+            | Self::IncMemZeroPageX
+            | Self::IncMemAbsolute
+            | Self::IncMemAbsoluteX => "INC",
+            Self::IncX => "INX",
+            Self::IncY => "INY",
+            Self::DecMemZeroPage
+            | Self::DecMemZeroPageX
+            | Self::DecMemAbsolute
+            | Self::DecMemAbsoluteX => "DEC",
+            Self::DecX => "DEX",
+            Self::DecY => "DEY",
+            Self::LoadAccImm
+            | Self::LoadAccZeroPage
+            | Self::LoadAccZeroPageX
+            | Self::LoadAccAbsolute
+            | Self::LoadAccAbsoluteX
+            | Self::LoadAccAbsoluteY
+            | Self::LoadAccIndirectX
+            | Self::LoadAccIndirectY => "LDA",
+            Self::LoadXImm
+            | Self::LoadXZeroPage
+            | Self::LoadXZeroPageY
+            | Self::LoadXAbsolute
+            | Self::LoadXAbsoluteY => "LDX",
+            Self::LoadYImm
+            | Self::LoadYZeroPage
+            | Self::LoadYZeroPageX
+            | Self::LoadYAbsolute
+            | Self::LoadYAbsoluteX => "LDY",
+            Self::AndImm
+            | Self::AndZeroPage
+            | Self::AndZeroPageX
+            | Self::AndAbsolute
+            | Self::AndAbsoluteX
+            | Self::AndAbsoluteY
+            | Self::AndIndirectX
+            | Self::AndIndirectY => "AND",
+            Self::OraImm
+            | Self::OraZeroPage
+            | Self::OraZeroPageX
+            | Self::OraAbsolute
+            | Self::OraAbsoluteX
+            | Self::OraAbsoluteY
+            | Self::OraIndirectX
+            | Self::OraIndirectY => "ORA",
+            Self::AdcImm => "ADC",
+            Self::SbcImm => "SBC",
+            Self::StoreAccZeroPage
+            | Self::StoreAccZeroPageX
+            | Self::StoreAccAbsolute
+            | Self::StoreAccAbsoluteX
+            | Self::StoreAccAbsoluteY
+            | Self::StoreAccIndirectX
+            | Self::StoreAccIndirectY => "STA",
+            Self::StoreXZeroPage | Self::StoreXZeroPageY | Self::StoreXAbsolute => "STX",
+            Self::StoreYZeroPage | Self::StoreYZeroPageX | Self::StoreYAbsolute => "STY",
+            Self::TransferAccToX => "TAX",
+            Self::TransferAccToY => "TAY",
+            Self::TransferXToAcc => "TXA",
+            Self::TransferYToAcc => "TYA",
+            Self::TransferStackptrToX => "TSX",
+            Self::TransferXToStackptr => "TXS",
+            Self::PushAcc => "PHA",
+            Self::PushStatus => "PHP",
+            Self::PullAcc => "PLA",
+            Self::PullStatus => "PLP",
+            Self::BranchIfPositive => "BPL",
+            Self::BranchIfMinus => "BMI",
+            Self::BranchIfOverflowClear => "BVC",
+            Self::BranchIfOverflowSet => "BVS",
+            Self::BranchIfCarryClear => "BCC",
+            Self::BranchIfCarrySet => "BCS",
+            Self::BranchIfNotEqual => "BNE",
+            Self::BranchIfEqual => "BEQ",
+            Self::ClearCarry => "CLC",
+            Self::SetCarry => "SEC",
+            Self::ClearInterruptDisable => "CLI",
+            Self::SetInterruptDisable => "SEI",
+            Self::ClearOverflow => "CLV",
+            Self::ClearDecimal => "CLD",
+            Self::SetDecimal => "SED",
+        }
+    }
+
+    pub fn addressing_mode(&self) -> AddressingMode {
+        match self {
+            Self::AslA | Self::RorA => AddressingMode::Accumulator,
+            Self::LoadAccImm
+            | Self::LoadXImm
+            | Self::LoadYImm
+            | Self::AndImm
+            | Self::OraImm
+            | Self::AdcImm
+            | Self::SbcImm => AddressingMode::Immediate,
+            Self::AslZeroPage
+            | Self::IncMemZeroPage
+            | Self::DecMemZeroPage
+            | Self::LoadAccZeroPage
+            | Self::LoadXZeroPage
+            | Self::LoadYZeroPage
+            | Self::AndZeroPage
+            | Self::OraZeroPage
+            | Self::StoreAccZeroPage
+            | Self::StoreXZeroPage
+            | Self::StoreYZeroPage => AddressingMode::ZeroPage,
+            Self::AslZeroPageX
+            | Self::IncMemZeroPageX
+            | Self::DecMemZeroPageX
+            | Self::LoadAccZeroPageX
+            | Self::LoadYZeroPageX
+            | Self::AndZeroPageX
+            | Self::OraZeroPageX
+            | Self::StoreAccZeroPageX
+            | Self::StoreYZeroPageX => AddressingMode::ZeroPageX,
+            Self::LoadXZeroPageY | Self::StoreXZeroPageY => AddressingMode::ZeroPageY,
+            Self::AslAbsolute
+            | Self::IncMemAbsolute
+            | Self::DecMemAbsolute
+            | Self::LoadAccAbsolute
+            | Self::LoadXAbsolute
+            | Self::LoadYAbsolute
+            | Self::AndAbsolute
+            | Self::OraAbsolute
+            | Self::StoreAccAbsolute
+            | Self::StoreXAbsolute
+            | Self::StoreYAbsolute => AddressingMode::Absolute,
+            Self::IncMemAbsoluteX
+            | Self::DecMemAbsoluteX
+            | Self::LoadAccAbsoluteX
+            | Self::LoadYAbsoluteX
+            | Self::AndAbsoluteX
+            | Self::OraAbsoluteX
+            | Self::StoreAccAbsoluteX => AddressingMode::AbsoluteX,
+            Self::LoadAccAbsoluteY
+            | Self::LoadXAbsoluteY
+            | Self::AndAbsoluteY
+            | Self::OraAbsoluteY
+            | Self::StoreAccAbsoluteY => AddressingMode::AbsoluteY,
+            Self::LoadAccIndirectX
+            | Self::AndIndirectX
+            | Self::OraIndirectX
+            | Self::StoreAccIndirectX => AddressingMode::IndirectX,
+            Self::LoadAccIndirectY
+            | Self::AndIndirectY
+            | Self::OraIndirectY
+            | Self::StoreAccIndirectY => AddressingMode::IndirectY,
+            Self::IncX
+            | Self::IncY
+            | Self::DecX
+            | Self::DecY
+            | Self::ClearCarry
+            | Self::SetCarry
+            | Self::ClearInterruptDisable
+            | Self::SetInterruptDisable
+            | Self::ClearOverflow
+            | Self::ClearDecimal
+            | Self::TransferAccToX
+            | Self::TransferAccToY
+            | Self::TransferXToAcc
+            | Self::TransferYToAcc
+            | Self::TransferStackptrToX
+            | Self::TransferXToStackptr
+            | Self::PushAcc
+            | Self::PushStatus
+            | Self::PullAcc
+            | Self::PullStatus
+            | Self::SetDecimal => AddressingMode::Implied,
+            Self::BranchIfPositive
+            | Self::BranchIfMinus
+            | Self::BranchIfOverflowClear
+            | Self::BranchIfOverflowSet
+            | Self::BranchIfCarryClear
+            | Self::BranchIfCarrySet
+            | Self::BranchIfNotEqual
+            | Self::BranchIfEqual => AddressingMode::Relative,
+        }
+    }
+
+    // Human readable addressing mode for trace and debug output
+    pub fn addressing_mode_name(&self) -> &'static str {
+        self.addressing_mode().name()
+    }
+
+    // Opcode byte included
+    pub fn length(&self) -> u16 {
+        self.addressing_mode().length()
+    }
+
+    // Cycles the instruction takes without the page crossing and taken branch penalties
+    // See https://www.nesdev.org/obelisk-6502-guide/reference.html
+    pub fn base_cycles(&self) -> u8 {
+        match self {
+            Self::PushAcc | Self::PushStatus => 3,
+            Self::PullAcc | Self::PullStatus => 4,
+            Self::AslZeroPage | Self::IncMemZeroPage | Self::DecMemZeroPage => 5,
+            Self::AslZeroPageX
+            | Self::AslAbsolute
+            | Self::IncMemZeroPageX
+            | Self::IncMemAbsolute
+            | Self::DecMemZeroPageX
+            | Self::DecMemAbsolute => 6,
+            Self::IncMemAbsoluteX | Self::DecMemAbsoluteX => 7,
+            // Stores always spend the index fix-up cycle
+            Self::StoreAccAbsoluteX | Self::StoreAccAbsoluteY => 5,
+            Self::StoreAccIndirectY => 6,
+            _ => match self.addressing_mode() {
+                AddressingMode::Implied
+                | AddressingMode::Accumulator
+                | AddressingMode::Immediate
+                | AddressingMode::Relative => 2,
+                AddressingMode::ZeroPage => 3,
+                AddressingMode::ZeroPageX
+                | AddressingMode::ZeroPageY
+                | AddressingMode::Absolute
+                | AddressingMode::AbsoluteX
+                | AddressingMode::AbsoluteY => 4,
+                AddressingMode::IndirectY => 5,
+                AddressingMode::IndirectX => 6,
+            },
         }
     }
 
     pub fn get_opcode(&self) -> u8 {
         match self {
             Self::AslA => 0x0A,
+            Self::RorA => 0x6A,
             Self::AslZeroPage => 0x06,
             Self::AslZeroPageX => 0x16,
             Self::AslAbsolute => 0x0E,
@@ -375,12 +1038,66 @@ impl Operation {
             Self::AndAbsoluteY => 0x39,
             Self::AndIndirectX => 0x21,
             Self::AndIndirectY => 0x31,
+            Self::OraImm => 0x09,
+            Self::OraZeroPage => 0x05,
+            Self::OraZeroPageX => 0x15,
+            Self::OraAbsolute => 0x0D,
+            Self::OraAbsoluteX => 0x1D,
+            Self::OraAbsoluteY => 0x19,
+            Self::OraIndirectX => 0x01,
+            Self::OraIndirectY => 0x11,
+            Self::AdcImm => 0x69,
+            Self::SbcImm => 0xE9,
+            Self::StoreAccZeroPage => 0x85,
+            Self::StoreAccZeroPageX => 0x95,
+            Self::StoreAccAbsolute => 0x8D,
+            Self::StoreAccAbsoluteX => 0x9D,
+            Self::StoreAccAbsoluteY => 0x99,
+            Self::StoreAccIndirectX => 0x81,
+            Self::StoreAccIndirectY => 0x91,
+            Self::StoreXZeroPage => 0x86,
+            Self::StoreXZeroPageY => 0x96,
+            Self::StoreXAbsolute => 0x8E,
+            Self::StoreYZeroPage => 0x84,
+            Self::StoreYZeroPageX => 0x94,
+            Self::StoreYAbsolute => 0x8C,
+            Self::TransferAccToX => 0xAA,
+            Self::TransferAccToY => 0xA8,
+            Self::TransferXToAcc => 0x8A,
+            Self::TransferYToAcc => 0x98,
+            Self::TransferStackptrToX => 0xBA,
+            Self::TransferXToStackptr => 0x9A,
+            Self::PushAcc => 0x48,
+            Self::PushStatus => 0x08,
+            Self::PullAcc => 0x68,
+            Self::PullStatus => 0x28,
+            Self::BranchIfPositive => 0x10,
+            Self::BranchIfMinus => 0x30,
+            Self::BranchIfOverflowClear => 0x50,
+            Self::BranchIfOverflowSet => 0x70,
+            Self::BranchIfCarryClear => 0x90,
+            Self::BranchIfCarrySet => 0xB0,
+            Self::BranchIfNotEqual => 0xD0,
+            Self::BranchIfEqual => 0xF0,
+            Self::ClearCarry => 0x18,
+            Self::SetCarry => 0x38,
+            Self::ClearInterruptDisable => 0x58,
+            Self::SetInterruptDisable => 0x78,
+            Self::ClearOverflow => 0xB8,
+            Self::ClearDecimal => 0xD8,
+            Self::SetDecimal => 0xF8,
         }
     }
 
+    // Every implemented operation, in opcode order
+    pub fn all() -> Vec<Self> {
+        (0..=255u8).filter_map(Self::get_operation).collect()
+    }
+
     pub fn get_operation(opcode: u8) -> Option<Self> {
         match opcode {
             0x0A => Some(Self::AslA),
+            0x6A => Some(Self::RorA),
             0x06 => Some(Self::AslZeroPage),
             0x16 => Some(Self::AslZeroPageX),
             0x0E => Some(Self::AslAbsolute),
@@ -422,7 +1139,245 @@ impl Operation {
             0x39 => Some(Self::AndAbsoluteY),
             0x21 => Some(Self::AndIndirectX),
             0x31 => Some(Self::AndIndirectY),
+            0x09 => Some(Self::OraImm),
+            0x05 => Some(Self::OraZeroPage),
+            0x15 => Some(Self::OraZeroPageX),
+            0x0D => Some(Self::OraAbsolute),
+            0x1D => Some(Self::OraAbsoluteX),
+            0x19 => Some(Self::OraAbsoluteY),
+            0x01 => Some(Self::OraIndirectX),
+            0x11 => Some(Self::OraIndirectY),
+            0x69 => Some(Self::AdcImm),
+            0xE9 => Some(Self::SbcImm),
+            0x85 => Some(Self::StoreAccZeroPage),
+            0x95 => Some(Self::StoreAccZeroPageX),
+            0x8D => Some(Self::StoreAccAbsolute),
+            0x9D => Some(Self::StoreAccAbsoluteX),
+            0x99 => Some(Self::StoreAccAbsoluteY),
+            0x81 => Some(Self::StoreAccIndirectX),
+            0x91 => Some(Self::StoreAccIndirectY),
+            0x86 => Some(Self::StoreXZeroPage),
+            0x96 => Some(Self::StoreXZeroPageY),
+            0x8E => Some(Self::StoreXAbsolute),
+            0x84 => Some(Self::StoreYZeroPage),
+            0x94 => Some(Self::StoreYZeroPageX),
+            0x8C => Some(Self::StoreYAbsolute),
+            0xAA => Some(Self::TransferAccToX),
+            0xA8 => Some(Self::TransferAccToY),
+            0x8A => Some(Self::TransferXToAcc),
+            0x98 => Some(Self::TransferYToAcc),
+            0xBA => Some(Self::TransferStackptrToX),
+            0x9A => Some(Self::TransferXToStackptr),
+            0x48 => Some(Self::PushAcc),
+            0x08 => Some(Self::PushStatus),
+            0x68 => Some(Self::PullAcc),
+            0x28 => Some(Self::PullStatus),
+            0x10 => Some(Self::BranchIfPositive),
+            0x30 => Some(Self::BranchIfMinus),
+            0x50 => Some(Self::BranchIfOverflowClear),
+            0x70 => Some(Self::BranchIfOverflowSet),
+            0x90 => Some(Self::BranchIfCarryClear),
+            0xB0 => Some(Self::BranchIfCarrySet),
+            0xD0 => Some(Self::BranchIfNotEqual),
+            0xF0 => Some(Self::BranchIfEqual),
+            0x18 => Some(Self::ClearCarry),
+            0x38 => Some(Self::SetCarry),
+            0x58 => Some(Self::ClearInterruptDisable),
+            0x78 => Some(Self::SetInterruptDisable),
+            0xB8 => Some(Self::ClearOverflow),
+            0xD8 => Some(Self::ClearDecimal),
+            0xF8 => Some(Self::SetDecimal),
             _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Raise this whenever new opcodes are implemented, so none get dropped by accident
+    const MINIMUM_IMPLEMENTED_OPCODES: usize = 91;
+
+    fn opcode_matrix() -> String {
+        let mut matrix = String::from("   ");
+        for low_nibble in 0..16 {
+            matrix.push_str(&format!(" {:X} ", low_nibble));
+        }
+        matrix.push('\n');
+
+        for high_nibble in 0..16u8 {
+            matrix.push_str(&format!("{:X}0 ", high_nibble));
+            for low_nibble in 0..16u8 {
+                let opcode = high_nibble << 4 | low_nibble;
+                match Operation::get_operation(opcode) {
+                    Some(_) => matrix.push_str(" # "),
+                    None => matrix.push_str(" . "),
+                }
+            }
+            matrix.push('\n');
+        }
+
+        matrix
+    }
+
+    #[test]
+    fn test_opcode_matrix_coverage() {
+        let implemented = (0..=255u8)
+            .filter(|opcode| Operation::get_operation(*opcode).is_some())
+            .count();
+
+        println!("{}", opcode_matrix());
+        println!("Implemented opcodes: {}/256", implemented);
+
+        assert!(
+            implemented >= MINIMUM_IMPLEMENTED_OPCODES,
+            "Only {} opcodes are implemented, expected at least {}",
+            implemented,
+            MINIMUM_IMPLEMENTED_OPCODES
+        );
+    }
+
+    #[test]
+    fn test_opcode_table_round_trips() {
+        for opcode in 0..=255u8 {
+            if let Some(operation) = Operation::get_operation(opcode) {
+                assert_eq!(operation.get_opcode(), opcode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_addressing_mode_names() {
+        let expected = [
+            (Operation::AslA, "Accumulator"),
+            (Operation::LoadAccImm, "Immediate"),
+            (Operation::StoreXZeroPage, "ZeroPage"),
+            (Operation::LoadYZeroPageX, "ZeroPage,X"),
+            (Operation::LoadXZeroPageY, "ZeroPage,Y"),
+            (Operation::DecMemAbsolute, "Absolute"),
+            (Operation::IncMemAbsoluteX, "Absolute,X"),
+            (Operation::AndAbsoluteY, "Absolute,Y"),
+            (Operation::LoadAccIndirectX, "(Indirect,X)"),
+            (Operation::StoreAccIndirectY, "(Indirect),Y"),
+            (Operation::ClearCarry, "Implied"),
+        ];
+
+        for (operation, name) in expected {
+            assert_eq!(operation.addressing_mode_name(), name, "{:?}", operation);
+        }
+    }
+
+    #[test]
+    fn test_addressing_modes_and_lengths() {
+        let expected = [
+            (Operation::IncX, AddressingMode::Implied, 1),
+            (Operation::RorA, AddressingMode::Accumulator, 1),
+            (Operation::AndImm, AddressingMode::Immediate, 2),
+            (Operation::LoadAccZeroPage, AddressingMode::ZeroPage, 2),
+            (Operation::StoreYZeroPageX, AddressingMode::ZeroPageX, 2),
+            (Operation::StoreXZeroPageY, AddressingMode::ZeroPageY, 2),
+            (Operation::AslAbsolute, AddressingMode::Absolute, 3),
+            (Operation::LoadYAbsoluteX, AddressingMode::AbsoluteX, 3),
+            (Operation::LoadXAbsoluteY, AddressingMode::AbsoluteY, 3),
+            (Operation::AndIndirectX, AddressingMode::IndirectX, 2),
+            (Operation::LoadAccIndirectY, AddressingMode::IndirectY, 2),
+            (Operation::BranchIfEqual, AddressingMode::Relative, 2),
+        ];
+
+        for (operation, mode, length) in expected {
+            assert_eq!(operation.addressing_mode(), mode, "{:?}", operation);
+            assert_eq!(operation.length(), length, "{:?}", operation);
+        }
+    }
+
+    #[test]
+    fn test_base_cycles() {
+        let expected = [
+            (Operation::LoadAccImm, 2),
+            (Operation::LoadAccZeroPage, 3),
+            (Operation::LoadAccAbsoluteX, 4),
+            (Operation::LoadAccIndirectX, 6),
+            (Operation::LoadAccIndirectY, 5),
+            (Operation::StoreAccAbsoluteX, 5),
+            (Operation::StoreAccIndirectY, 6),
+            (Operation::IncMemAbsoluteX, 7),
+            (Operation::AslA, 2),
+            (Operation::PushAcc, 3),
+            (Operation::PullStatus, 4),
+            (Operation::BranchIfEqual, 2),
+        ];
+
+        for (operation, cycles) in expected {
+            assert_eq!(operation.base_cycles(), cycles, "{:?}", operation);
+        }
+    }
+
+    #[test]
+    fn test_operation_sequences_match_opcode_table() {
+        for operation in Operation::all() {
+            let micro_instructions = operation.get_micro_instructions();
+            let addressing: &[MicroInstruction] = micro_instructions
+                .addressing_sequence
+                .as_ref()
+                .map_or(&[], |sequence| sequence.micro_instructions());
+            let operation_sequence = micro_instructions.operation_sequence.micro_instructions();
+
+            // The addressing sequence fetches exactly the operand bytes the opcode table declares
+            let fetched_bytes = addressing
+                .iter()
+                .filter(|micro_instruction| micro_instruction.fetches_instruction_byte())
+                .count() as u16;
+            assert_eq!(fetched_bytes, operation.length() - 1, "{:?}", operation);
+
+            let writes = operation_sequence
+                .iter()
+                .filter(|micro_instruction| micro_instruction.is_write())
+                .count();
+            assert!(
+                !addressing.iter().any(MicroInstruction::is_write),
+                "{:?} writes while addressing",
+                operation
+            );
+
+            let expected_writes = match operation.mnemonic() {
+                "STA" | "STX" | "STY" | "PHA" | "PHP" => 1,
+                "ASL" | "INC" | "DEC"
+                    if operation.addressing_mode() != AddressingMode::Accumulator =>
+                {
+                    // The dummy write of the unmodified value comes first
+                    assert!(
+                        operation_sequence
+                            .first()
+                            .is_some_and(MicroInstruction::is_write),
+                        "{:?} should start with a dummy write",
+                        operation
+                    );
+                    2
+                }
+                _ => 0,
+            };
+            if expected_writes > 0 {
+                assert_eq!(writes, expected_writes, "{:?} write count", operation);
+                assert!(
+                    operation_sequence
+                        .last()
+                        .is_some_and(MicroInstruction::is_write),
+                    "{:?} should end with a write",
+                    operation
+                );
+            } else {
+                assert_eq!(writes, 0, "{:?} should not write", operation);
+            }
+
+            // Stores must not read the target, memory mapped registers have read side effects
+            if matches!(operation.mnemonic(), "STA" | "STX" | "STY") {
+                assert!(
+                    !addressing.iter().any(MicroInstruction::reads_operand),
+                    "{:?} reads its target",
+                    operation
+                );
+            }
+        }
+    }
+}
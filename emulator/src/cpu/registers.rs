@@ -1,9 +1,15 @@
 use crate::bus::BusLike;
-use crate::cpu::cpu::CPUFlag;
+use crate::cpu::cpu::{CPUFlag, CpuError};
 use crate::cpu::micro_instructions::MicroInstructionSequence;
 use crate::cpu::operations::Operation;
+use crate::logging::nes_logging::CPU_LOG_TARGET;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+const STACK_PAGE: u16 = 0x0100;
 
 #[allow(dead_code)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Registers {
     pub x: u8,
     pub y: u8,
@@ -20,6 +26,14 @@ pub struct Registers {
     decoded_addressing_mode: Option<MicroInstructionSequence>,
     decoded_operation: Option<MicroInstructionSequence>,
     pub memory_buffer: u8,
+    page_crossed: bool,
+    branch_taken: bool,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Registers {
@@ -40,6 +54,8 @@ impl Registers {
             decoded_addressing_mode: None,
             decoded_operation: None,
             memory_buffer: 0x00,
+            page_crossed: false,
+            branch_taken: false,
         }
     }
 
@@ -56,6 +72,16 @@ impl Registers {
         }
     }
 
+    #[cfg(test)]
+    pub fn set_decoded_operation(
+        &mut self,
+        addressing_mode: Option<MicroInstructionSequence>,
+        operation: MicroInstructionSequence,
+    ) {
+        self.decoded_addressing_mode = addressing_mode;
+        self.decoded_operation = Some(operation);
+    }
+
     pub fn is_operation_completed(&self) -> bool {
         match &self.decoded_operation {
             Some(operation) => operation.is_completed(),
@@ -87,27 +113,99 @@ impl Registers {
         self.status = 0x00;
     }
 
+    // Reset runs a forced BRK with writes suppressed: the stack pointer still
+    // moves down by three and A, X and Y keep their values
+    pub fn reset(&mut self, reset_vector: u16) {
+        self.program_counter = reset_vector;
+        self.stack_ptr = self.stack_ptr.wrapping_sub(3);
+        self.set_flag(CPUFlag::InterruptDisable);
+        self.decoded_addressing_mode = None;
+        self.decoded_operation = None;
+    }
+
+    // Hardware interrupts push the return address and the status with B clear,
+    // then continue at the handler with further IRQs masked
+    pub fn interrupt<T: BusLike>(&mut self, bus: &mut T, vector: u16) {
+        self.push(bus, (self.program_counter >> 8) as u8);
+        self.push(bus, self.program_counter as u8);
+        let status = (self.status | CPUFlag::Unused.value()) & !CPUFlag::Break.value();
+        self.push(bus, status);
+        self.set_flag(CPUFlag::InterruptDisable);
+
+        self.program_counter = bus.read_u16(vector);
+        self.decoded_addressing_mode = None;
+        self.decoded_operation = None;
+    }
+
+    // The stack pointer wraps within page 1, pushing at 0x00 continues at 0xFF
+    fn push<T: BusLike>(&mut self, bus: &mut T, data: u8) {
+        bus.write(STACK_PAGE | self.stack_ptr as u16, data);
+        self.stack_ptr = self.stack_ptr.wrapping_sub(1);
+    }
+
+    fn pull<T: BusLike>(&mut self, bus: &mut T) -> u8 {
+        self.stack_ptr = self.stack_ptr.wrapping_add(1);
+        bus.read(STACK_PAGE | self.stack_ptr as u16)
+    }
+
+    pub fn push_accumulator<T: BusLike>(&mut self, bus: &mut T) {
+        self.push(bus, self.a);
+    }
+
+    // PHP pushes the status with B and the unused bit set, unlike interrupts
+    pub fn push_status<T: BusLike>(&mut self, bus: &mut T) {
+        let status = self.status | CPUFlag::Break.value() | CPUFlag::Unused.value();
+        self.push(bus, status);
+    }
+
+    pub fn pull_accumulator<T: BusLike>(&mut self, bus: &mut T) {
+        self.a = self.pull(bus);
+        self.set_zero_and_negative(self.a);
+    }
+
+    // B and the unused bit do not exist in the status register, so they are not pulled
+    pub fn pull_status<T: BusLike>(&mut self, bus: &mut T) {
+        let ignored = CPUFlag::Break.value() | CPUFlag::Unused.value();
+        let status = self.pull(bus);
+        self.status = (status & !ignored) | (self.status & ignored);
+    }
+
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    pub fn stack_ptr(&self) -> u8 {
+        self.stack_ptr
+    }
+
     pub fn step_program_counter(&mut self) {
         self.program_counter += 1;
     }
 
     pub fn read_operation_code<T: BusLike>(&mut self, bus: &mut T) {
-        self.operation = bus.read(self.program_counter as u16);
+        self.operation = bus.read(self.program_counter);
     }
+    // The program counter is left on an unknown opcode, so the error points at it
     #[allow(unused_variables)]
-    pub fn decode_operation<T: BusLike>(&mut self, bus: &T) {
+    pub fn decode_operation<T: BusLike>(&mut self, bus: &T) -> Result<(), CpuError> {
         let operation_code = self.operation;
-        println!("Operation code: {:#X}", operation_code);
+        debug!(target: CPU_LOG_TARGET, "Operation code: {:#X}", operation_code);
 
-        if let Some(operation) = Operation::get_operation(operation_code) {
-            let micro_instructions = operation.get_micro_instructions();
-            self.decoded_addressing_mode = micro_instructions.addressing_sequence;
-            self.decoded_operation = Some(micro_instructions.operation_sequence);
-        } else {
-            panic!("Operation not found for opcode: {:#X}", operation_code);
-        }
+        let operation =
+            Operation::get_operation(operation_code).ok_or(CpuError::UnknownOpcode {
+                opcode: operation_code,
+                address: self.program_counter,
+            })?;
+        let micro_instructions = operation.get_micro_instructions();
+        self.decoded_addressing_mode = micro_instructions.addressing_sequence;
+        self.decoded_operation = Some(micro_instructions.operation_sequence);
 
         self.step_program_counter();
+        Ok(())
     }
 
     pub fn immediate_read<T: BusLike>(&mut self, bus: &mut T) {
@@ -126,32 +224,33 @@ impl Registers {
     }
 
     pub fn read_zero_page<T: BusLike>(&mut self, bus: &mut T) {
-        println!("Reading zero page address: {:#X}", self.adl);
+        debug!(target: CPU_LOG_TARGET, "Reading zero page address: {:#X}", self.adl);
         self.memory_buffer = bus.read(self.adl as u16);
     }
 
     pub fn read_absolute<T: BusLike>(&mut self, bus: &mut T) {
         let address = (self.adh as u16) << 8 | self.adl as u16;
-        self.memory_buffer = bus.read(address as u16);
+        self.memory_buffer = bus.read(address);
     }
 
     pub fn read_bal<T: BusLike>(&mut self, bus: &mut T) {
-        self.bal = bus.read(self.program_counter as u16);
+        self.bal = bus.read(self.program_counter);
         self.step_program_counter();
     }
 
     pub fn read_bah<T: BusLike>(&mut self, bus: &mut T) {
-        self.bah = bus.read(self.program_counter as u16);
+        self.bah = bus.read(self.program_counter);
         self.step_program_counter();
     }
 
     pub fn read_adl_indirect_bal<T: BusLike>(&mut self, bus: &mut T) {
-        let address = (self.bal + self.x) as usize;
+        // The pointer lives in the zero page, so the index wraps within it
+        let address = self.bal.wrapping_add(self.x);
         self.adl = bus.read(address as u16);
     }
 
     pub fn read_adh_indirect_bal<T: BusLike>(&mut self, bus: &mut T) {
-        let address = (self.bal + self.x + 1) as usize;
+        let address = self.bal.wrapping_add(self.x).wrapping_add(1);
         self.adh = bus.read(address as u16);
     }
 
@@ -161,38 +260,82 @@ impl Registers {
 
     pub fn write_absolute<T: BusLike>(&mut self, bus: &mut T) {
         let address = (self.adh as u16) << 8 | self.adl as u16;
-        bus.write(address as u16, self.memory_buffer);
+        bus.write(address, self.memory_buffer);
     }
 
+    // Zero page indexing never leaves page 0, the sum wraps around
     pub fn read_zero_page_bal_x<T: BusLike>(&mut self, bus: &mut T) {
-        // TODO: Be careful with overflow, check if it's correct
-
-        let address = (self.bal + self.x) as usize;
+        let address = self.bal.wrapping_add(self.x);
         self.memory_buffer = bus.read(address as u16);
     }
 
     pub fn read_zero_page_bal_y<T: BusLike>(&mut self, bus: &mut T) {
-        let address = (self.bal + self.y) as usize;
+        let address = self.bal.wrapping_add(self.y);
         self.memory_buffer = bus.read(address as u16);
     }
 
     pub fn write_zero_page_bal_x<T: BusLike>(&mut self, bus: &mut T) {
-        let address = (self.bal + self.x) as usize;
+        let address = self.bal.wrapping_add(self.x);
         bus.write(address as u16, self.memory_buffer);
     }
 
-    pub fn read_adl_adh_absolute_index_register<T: BusLike>(
-        &mut self,
-        bus: &mut T,
-        index_register: u8,
-    ) {
+    pub fn write_zero_page_bal_y<T: BusLike>(&mut self, bus: &mut T) {
+        let address = self.bal.wrapping_add(self.y);
+        bus.write(address as u16, self.memory_buffer);
+    }
+
+    // Leaves the indexed address in adl/adh, so read-modify-write instructions can write it
+    // back with WriteAbsolute
+    pub fn calculate_adl_adh_absolute_index_register(&mut self, index_register: u8) -> u16 {
         let bal_address = self.bal as usize;
         let bah_address = self.bah as usize;
         let address = ((bah_address << 8) | bal_address) + (index_register as usize);
         self.adh = ((address & 0xFF00) >> 8) as u8;
         self.adl = (address & 0x00FF) as u8;
 
-        self.memory_buffer = bus.read(address as u16);
+        (address & 0xFFFF) as u16
+    }
+
+    // Stores always spend the fix-up cycle, so they never pay the page crossing penalty
+    pub fn calculate_adl_adh_absolute_x(&mut self) {
+        self.calculate_adl_adh_absolute_index_register(self.x);
+        self.page_crossed = false;
+    }
+
+    pub fn calculate_adl_adh_absolute_y(&mut self) {
+        self.calculate_adl_adh_absolute_index_register(self.y);
+        self.page_crossed = false;
+    }
+
+    pub fn read_adl_adh_absolute_index_register<T: BusLike>(
+        &mut self,
+        bus: &mut T,
+        index_register: u8,
+    ) {
+        let address = self.calculate_adl_adh_absolute_index_register(index_register);
+        self.page_crossed = self.adh != self.bah;
+        self.memory_buffer = bus.read(address);
+    }
+
+    pub fn is_page_crossed(&self) -> bool {
+        self.page_crossed
+    }
+
+    // The offset is relative to the instruction after the branch
+    pub fn branch(&mut self, flag: CPUFlag, set: bool) {
+        self.branch_taken = self.is_flag_set(flag) == set;
+        self.page_crossed = false;
+        if self.branch_taken {
+            let target = self
+                .program_counter
+                .wrapping_add(self.memory_buffer as i8 as u16);
+            self.page_crossed = target & 0xFF00 != self.program_counter & 0xFF00;
+            self.program_counter = target;
+        }
+    }
+
+    pub fn is_branch_taken(&self) -> bool {
+        self.branch_taken
     }
 
     pub fn read_adl_adh_absolute_x<T: BusLike>(&mut self, bus: &mut T) {
@@ -204,7 +347,7 @@ impl Registers {
     }
 
     pub fn read_ial<T: BusLike>(&mut self, bus: &mut T) {
-        self.ial = bus.read(self.program_counter as u16);
+        self.ial = bus.read(self.program_counter);
         self.step_program_counter();
     }
 
@@ -213,7 +356,7 @@ impl Registers {
     }
 
     pub fn read_bah_indirect_ial<T: BusLike>(&mut self, bus: &mut T) {
-        self.bah = bus.read(self.ial as u16 + 1);
+        self.bah = bus.read(self.ial.wrapping_add(1) as u16);
     }
 
     pub fn shift_left_accumulator(&mut self) {
@@ -226,6 +369,16 @@ impl Registers {
         self.set_flag_value(CPUFlag::Negative, is_negative);
     }
 
+    // The carry goes into bit 7 and bit 0 comes out as the new carry
+    pub fn rotate_right_accumulator(&mut self) {
+        let is_carry = self.a & 0x01 != 0;
+        self.a = (self.a >> 1) | ((self.is_flag_set(CPUFlag::CarryBit) as u8) << 7);
+
+        self.set_flag_value(CPUFlag::CarryBit, is_carry);
+        self.set_flag_value(CPUFlag::Zero, self.a == 0);
+        self.set_flag_value(CPUFlag::Negative, self.a & 0x80 != 0);
+    }
+
     pub fn shift_left_memory_buffer(&mut self) {
         let is_carry = self.memory_buffer & 0x80 != 0;
         self.memory_buffer <<= 1;
@@ -317,12 +470,144 @@ impl Registers {
         self.set_flag_value(CPUFlag::Negative, is_negative);
     }
 
+    pub fn transfer_accumulator_to_x(&mut self) {
+        self.x = self.a;
+        self.set_zero_and_negative(self.x);
+    }
+
+    pub fn transfer_accumulator_to_y(&mut self) {
+        self.y = self.a;
+        self.set_zero_and_negative(self.y);
+    }
+
+    pub fn transfer_x_to_accumulator(&mut self) {
+        self.a = self.x;
+        self.set_zero_and_negative(self.a);
+    }
+
+    pub fn transfer_y_to_accumulator(&mut self) {
+        self.a = self.y;
+        self.set_zero_and_negative(self.a);
+    }
+
+    pub fn transfer_stack_ptr_to_x(&mut self) {
+        self.x = self.stack_ptr;
+        self.set_zero_and_negative(self.x);
+    }
+
+    // The only transfer which leaves the flags alone
+    pub fn transfer_x_to_stack_ptr(&mut self) {
+        self.stack_ptr = self.x;
+    }
+
+    fn set_zero_and_negative(&mut self, value: u8) {
+        self.set_flag_value(CPUFlag::Zero, value == 0);
+        self.set_flag_value(CPUFlag::Negative, value & 0x80 != 0);
+    }
+
+    pub fn store_accumulator(&mut self) {
+        self.memory_buffer = self.a;
+    }
+
+    pub fn store_x(&mut self) {
+        self.memory_buffer = self.x;
+    }
+
+    pub fn store_y(&mut self) {
+        self.memory_buffer = self.y;
+    }
+
     pub fn and(&mut self) {
-        self.a = self.a & self.memory_buffer;
+        self.a &= self.memory_buffer;
         let is_zero = self.a == 0;
         let is_negative = self.a & 0x80 != 0;
 
         self.set_flag_value(CPUFlag::Zero, is_zero);
         self.set_flag_value(CPUFlag::Negative, is_negative);
     }
+
+    pub fn or(&mut self) {
+        self.a |= self.memory_buffer;
+        let is_zero = self.a == 0;
+        let is_negative = self.a & 0x80 != 0;
+
+        self.set_flag_value(CPUFlag::Zero, is_zero);
+        self.set_flag_value(CPUFlag::Negative, is_negative);
+    }
+
+    // BCD arithmetic only happens on CPUs that have decimal mode, with D set
+    pub fn adc(&mut self, decimal_mode: bool) {
+        if decimal_mode && self.is_flag_set(CPUFlag::DecimalMode) {
+            self.adc_decimal();
+        } else {
+            self.add_binary(self.memory_buffer);
+        }
+    }
+
+    pub fn sbc(&mut self, decimal_mode: bool) {
+        if decimal_mode && self.is_flag_set(CPUFlag::DecimalMode) {
+            self.sbc_decimal();
+        } else {
+            self.add_binary(!self.memory_buffer);
+        }
+    }
+
+    // Subtraction is an addition of the one's complement, the carry acts as an inverted borrow
+    fn add_binary(&mut self, value: u8) {
+        let carry = self.is_flag_set(CPUFlag::CarryBit) as u16;
+        let sum = self.a as u16 + value as u16 + carry;
+        let result = sum as u8;
+        let is_overflow = (self.a ^ result) & (value ^ result) & 0x80 != 0;
+
+        self.a = result;
+        self.set_flag_value(CPUFlag::CarryBit, sum > 0xFF);
+        self.set_flag_value(CPUFlag::Overflow, is_overflow);
+        self.set_flag_value(CPUFlag::Zero, result == 0);
+        self.set_flag_value(CPUFlag::Negative, result & 0x80 != 0);
+    }
+
+    // NMOS 6502 BCD addition: Zero comes from the binary sum, Negative and Overflow from the
+    // sum before the high nibble is adjusted, see http://www.6502.org/tutorials/decimal_mode.html
+    fn adc_decimal(&mut self) {
+        let value = self.memory_buffer;
+        let carry = self.is_flag_set(CPUFlag::CarryBit) as u8;
+        let binary = self.a.wrapping_add(value).wrapping_add(carry);
+
+        let mut low = (self.a & 0x0F) + (value & 0x0F) + carry;
+        if low > 0x09 {
+            low += 0x06;
+        }
+        let mut high = (self.a >> 4) as u16 + (value >> 4) as u16 + (low > 0x0F) as u16;
+        let intermediate = (high << 4) as u8 | (low & 0x0F);
+        let is_overflow = (self.a ^ intermediate) & (value ^ intermediate) & 0x80 != 0;
+        if high > 0x09 {
+            high += 0x06;
+        }
+
+        self.a = ((high << 4) as u8) | (low & 0x0F);
+        self.set_flag_value(CPUFlag::CarryBit, high > 0x0F);
+        self.set_flag_value(CPUFlag::Overflow, is_overflow);
+        self.set_flag_value(CPUFlag::Zero, binary == 0);
+        self.set_flag_value(CPUFlag::Negative, intermediate & 0x80 != 0);
+    }
+
+    // NMOS 6502 BCD subtraction: every flag comes from the binary difference
+    fn sbc_decimal(&mut self) {
+        let value = self.memory_buffer;
+        let borrow = !self.is_flag_set(CPUFlag::CarryBit) as i16;
+
+        let mut low = (self.a & 0x0F) as i16 - (value & 0x0F) as i16 - borrow;
+        let mut high = (self.a >> 4) as i16 - (value >> 4) as i16;
+        if low < 0 {
+            low -= 0x06;
+            high -= 1;
+        }
+        if high < 0 {
+            high -= 0x06;
+        }
+        let result = ((high << 4) as u8 & 0xF0) | (low as u8 & 0x0F);
+
+        self.add_binary(!value);
+        self.a = result;
+    }
 }
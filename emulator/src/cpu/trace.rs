@@ -0,0 +1,252 @@
+use crate::bus::BusLike;
+use crate::cpu::cpu::{CpuError, CPU};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// CPU registers before an instruction, written as one line of a nestest-like log:
+// C000  A:00 X:00 Y:00 P:24 SP:FD CYC:7
+// Logs without a CYC column (e.g. from run_traced) leave cycles empty
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TraceEntry {
+    pub program_counter: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub status: u8,
+    pub stack_ptr: u8,
+    pub cycles: Option<u64>,
+}
+
+impl TraceEntry {
+    // An expected entry without cycles matches any cycle count
+    pub fn matches(&self, actual: &TraceEntry) -> bool {
+        let cycles = self.cycles.is_none() || self.cycles == actual.cycles;
+        cycles
+            && TraceEntry {
+                cycles: None,
+                ..*self
+            } == TraceEntry {
+                cycles: None,
+                ..*actual
+            }
+    }
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04X}  A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+            self.program_counter, self.a, self.x, self.y, self.status, self.stack_ptr
+        )?;
+        if let Some(cycles) = self.cycles {
+            write!(f, " CYC:{}", cycles)?;
+        }
+        Ok(())
+    }
+}
+
+fn cycles_note(expected: &TraceEntry) -> &'static str {
+    match expected.cycles {
+        Some(_) => "",
+        None => " (the log has no CYC column, cycles were not compared)",
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TraceError {
+    #[error("malformed trace line {line}: {text}")]
+    Malformed { line: usize, text: String },
+
+    #[error(
+        "trace diverged at instruction {instruction}{}\nexpected: {expected}\n  actual: {actual}",
+        cycles_note(.expected)
+    )]
+    Divergence {
+        instruction: usize,
+        expected: TraceEntry,
+        actual: TraceEntry,
+    },
+
+    #[error(transparent)]
+    Cpu(#[from] CpuError),
+}
+
+// Also takes full nestest.log and run_traced lines, the opcode bytes, the disassembly and
+// the PPU column are skipped
+impl FromStr for TraceEntry {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut fields = line.split_whitespace();
+        let program_counter = u16::from_str_radix(fields.next().ok_or(())?, 16).map_err(|_| ())?;
+
+        let (mut a, mut x, mut y, mut status, mut stack_ptr) = (None, None, None, None, None);
+        let mut cycles = None;
+        for (name, value) in fields.filter_map(|field| field.split_once(':')) {
+            let register = || u8::from_str_radix(value, 16).map_err(|_| ());
+            match name {
+                "A" => a = Some(register()?),
+                "X" => x = Some(register()?),
+                "Y" => y = Some(register()?),
+                "P" => status = Some(register()?),
+                "SP" => stack_ptr = Some(register()?),
+                "CYC" => cycles = Some(value.parse().map_err(|_| ())?),
+                _ => {}
+            }
+        }
+
+        Ok(TraceEntry {
+            program_counter,
+            a: a.ok_or(())?,
+            x: x.ok_or(())?,
+            y: y.ok_or(())?,
+            status: status.ok_or(())?,
+            stack_ptr: stack_ptr.ok_or(())?,
+            cycles,
+        })
+    }
+}
+
+// Steps the CPU through a recorded log, one instruction per non-empty line, and stops at
+// the first line the registers do not match. Returns the number of matched instructions.
+pub fn replay_trace<T: BusLike>(cpu: &mut CPU<T>, log: &str) -> Result<usize, TraceError> {
+    let mut matched = 0;
+    for (instruction, line) in log
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+    {
+        let expected = line
+            .parse::<TraceEntry>()
+            .map_err(|_| TraceError::Malformed {
+                line: instruction,
+                text: line.to_string(),
+            })?;
+
+        let actual = cpu.trace_entry();
+        if !expected.matches(&actual) {
+            return Err(TraceError::Divergence {
+                instruction,
+                expected,
+                actual,
+            });
+        }
+        cpu.step_instruction()?;
+        matched += 1;
+    }
+
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+
+    // LDA #$01, LDX #$02, SEC, INX, LDY #$03, CLC
+    const PROGRAM: [u8; 9] = [0xA9, 0x01, 0xA2, 0x02, 0x38, 0xE8, 0xA0, 0x03, 0x18];
+    const INSTRUCTIONS: usize = 6;
+
+    fn setup_cpu() -> CPU<Bus> {
+        let mut bus = Bus::new_cpu();
+        for (address, byte) in PROGRAM.iter().enumerate() {
+            bus.write(address as u16, *byte);
+        }
+        CPU::new(bus)
+    }
+
+    fn record_trace() -> Vec<String> {
+        let mut cpu = setup_cpu();
+        (0..INSTRUCTIONS)
+            .map(|_| {
+                let line = cpu.trace_entry().to_string();
+                cpu.step_instruction().unwrap();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trace_entry_round_trips() {
+        let line = "C000  A:01 X:02 Y:03 P:24 SP:FD CYC:7";
+
+        let entry = line.parse::<TraceEntry>().unwrap();
+
+        assert_eq!(entry.program_counter, 0xC000);
+        assert_eq!(entry.status, 0x24);
+        assert_eq!(entry.to_string(), line);
+    }
+
+    #[test]
+    fn test_replay_matches_own_trace() {
+        let log = record_trace().join("\n");
+        let mut cpu = setup_cpu();
+
+        assert_eq!(replay_trace(&mut cpu, &log), Ok(INSTRUCTIONS));
+    }
+
+    #[test]
+    fn test_trace_entry_skips_disassembly_and_ppu_columns() {
+        let line = "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7";
+
+        let entry = line.parse::<TraceEntry>().unwrap();
+
+        assert_eq!(entry.to_string(), "C000  A:00 X:00 Y:00 P:24 SP:FD CYC:7");
+    }
+
+    #[test]
+    fn test_replay_matches_run_traced() {
+        let log = setup_cpu().run_traced(INSTRUCTIONS).join("\n");
+        let mut cpu = setup_cpu();
+
+        assert_eq!(replay_trace(&mut cpu, &log), Ok(INSTRUCTIONS));
+    }
+
+    #[test]
+    fn test_divergence_without_cycles_says_so() {
+        let mut lines = setup_cpu().run_traced(INSTRUCTIONS);
+        lines[3] = lines[3].replace("X:02", "X:07");
+        let mut cpu = setup_cpu();
+
+        let error = replay_trace(&mut cpu, &lines.join("\n")).unwrap_err();
+
+        assert!(matches!(
+            error,
+            TraceError::Divergence { expected, .. } if expected.cycles.is_none()
+        ));
+        assert!(error.to_string().contains("cycles were not compared"));
+    }
+
+    #[test]
+    fn test_replay_reports_first_divergence() {
+        let mut lines = record_trace();
+        lines[3] = lines[3].replace("X:02", "X:07");
+        let mut cpu = setup_cpu();
+
+        let result = replay_trace(&mut cpu, &lines.join("\n"));
+
+        match result {
+            Err(TraceError::Divergence {
+                instruction,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(instruction, 3);
+                assert_eq!(expected.x, 0x07);
+                assert_eq!(actual.x, 0x02);
+                assert_eq!(cpu.trace_entry(), actual);
+            }
+            other => panic!("expected a divergence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_replay_rejects_malformed_line() {
+        let mut cpu = setup_cpu();
+
+        let result = replay_trace(&mut cpu, "0000  A:00 Q:11");
+
+        assert!(matches!(result, Err(TraceError::Malformed { line: 0, .. })));
+    }
+}
@@ -1,11 +1,75 @@
 use crate::addressing::Addressable;
+use std::fmt::Debug;
 
-pub struct EmptyDevice;
+// Stands in for the unmapped parts of an address space. Nothing drives the data bus there,
+// so reads see whatever was last put on it (open bus) unless a fixed value is configured
+// See https://www.nesdev.org/wiki/Open_bus_behavior
+#[derive(Default)]
+pub struct EmptyDevice {
+    value: Option<u8>,
+    data_bus: u8,
+}
+
+impl EmptyDevice {
+    pub fn new() -> EmptyDevice {
+        EmptyDevice::default()
+    }
+
+    pub fn with_value(value: u8) -> EmptyDevice {
+        EmptyDevice {
+            value: Some(value),
+            data_bus: 0,
+        }
+    }
+
+    // Called by the bus with every byte transferred, read or written
+    pub fn latch(&mut self, data: u8) {
+        self.data_bus = data;
+    }
+}
 
-// TODO: Should it behave differently
 impl Addressable for EmptyDevice {
     fn read(&mut self, _address: u16) -> u8 {
-        0
+        self.value.unwrap_or(self.data_bus)
     }
+
     fn write(&mut self, _address: u16, _data: u8) {}
+
+    fn peek(&self, _address: u16) -> Option<u8> {
+        Some(self.value.unwrap_or(self.data_bus))
+    }
+}
+
+impl Debug for EmptyDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmptyDevice")
+            .field("value", &self.value)
+            .field("data_bus", &self.data_bus)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_last_latched_byte() {
+        let mut empty_device = EmptyDevice::new();
+        assert_eq!(empty_device.read(0x5000), 0x00);
+
+        empty_device.latch(0x42);
+
+        assert_eq!(empty_device.read(0x5000), 0x42);
+    }
+
+    #[test]
+    fn fixed_value_ignores_data_bus() {
+        let mut empty_device = EmptyDevice::with_value(0xFF);
+
+        empty_device.latch(0x42);
+        empty_device.write(0x5000, 0x12);
+
+        assert_eq!(empty_device.read(0x5000), 0xFF);
+    }
 }
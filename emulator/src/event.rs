@@ -0,0 +1,14 @@
+// Notable things happening inside the emulator, reported to the host through a single callback
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmulatorEvent {
+    // A whole frame has been rendered into the PPU frame buffer
+    FrameReady,
+    // Reserved for the audio mixer, nothing emits it yet
+    AudioSamples(Vec<f32>),
+    Nmi,
+    Irq,
+    // Execution reached the address of a breakpoint
+    Breakpoint(u16),
+}
+
+pub type EventSink = Box<dyn FnMut(EmulatorEvent)>;
@@ -0,0 +1,185 @@
+use crate::addressing::Addressable;
+use crate::apu::apu::Apu;
+use crate::controller::Controller;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+const CONTROLLER_1_ADDRESS: u16 = 0x4016;
+const CONTROLLER_2_ADDRESS: u16 = 0x4017;
+
+// APU and I/O registers at 0x4000-0x401F, see https://www.nesdev.org/wiki/2A03
+// 0x4016 and 0x4017 are shared: reads go to the controllers, the strobe write goes to both
+// controllers and 0x4017 writes belong to the APU frame counter
+pub struct IoRegisters {
+    apu: Rc<RefCell<Apu>>,
+    controllers: [Rc<RefCell<Controller>>; 2],
+    // Accuracy option reproducing controller reads corrupted by DMC DMA
+    dmc_conflict: bool,
+    dmc_fetch_pending: bool,
+}
+
+impl IoRegisters {
+    pub fn new(apu: Rc<RefCell<Apu>>, controllers: [Rc<RefCell<Controller>>; 2]) -> Self {
+        Self {
+            apu,
+            controllers,
+            dmc_conflict: false,
+            dmc_fetch_pending: false,
+        }
+    }
+
+    // The APU is the only IRQ source among the I/O registers
+    pub fn irq(&self) -> bool {
+        self.apu.borrow().irq()
+    }
+
+    pub fn set_dmc_conflict(&mut self, enabled: bool) {
+        self.dmc_conflict = enabled;
+    }
+
+    // A DMC sample fetch halts the CPU on its next read, see https://www.nesdev.org/wiki/APU_DMC#Conflict_with_controller_and_PPU_read
+    pub fn dmc_fetch(&mut self) {
+        self.dmc_fetch_pending = self.dmc_conflict;
+    }
+
+    // The halted CPU repeats its read, the controller sees both and shifts an extra bit out
+    fn read_controller(&mut self, port: usize, address: u16) -> u8 {
+        if std::mem::take(&mut self.dmc_fetch_pending) {
+            self.controllers[port].read(address);
+        }
+        self.controllers[port].read(address)
+    }
+}
+
+impl Addressable for IoRegisters {
+    fn read(&mut self, address: u16) -> u8 {
+        match address {
+            CONTROLLER_1_ADDRESS => self.read_controller(0, address),
+            CONTROLLER_2_ADDRESS => self.read_controller(1, address),
+            _ => self.apu.read(address),
+        }
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        match address {
+            CONTROLLER_1_ADDRESS => {
+                for controller in self.controllers.iter_mut() {
+                    controller.write(address, data);
+                }
+            }
+            _ => self.apu.write(address, data),
+        }
+    }
+}
+
+impl Debug for IoRegisters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IoRegisters")
+            .field("apu", &self.apu)
+            .field("controllers", &self.controllers)
+            .field("dmc_conflict", &self.dmc_conflict)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apu::frame_counter::FrameCounterMode;
+    use crate::controller::Buttons;
+
+    fn setup_io_registers() -> (IoRegisters, [Rc<RefCell<Controller>>; 2]) {
+        let controllers = [
+            Rc::new(RefCell::new(Controller::new())),
+            Rc::new(RefCell::new(Controller::new())),
+        ];
+        let io_registers = IoRegisters::new(
+            Rc::new(RefCell::new(Apu::new())),
+            [controllers[0].clone(), controllers[1].clone()],
+        );
+        (io_registers, controllers)
+    }
+
+    #[test]
+    fn test_strobe_reaches_both_controllers() {
+        let (mut io_registers, controllers) = setup_io_registers();
+        controllers[0].borrow_mut().set_buttons(Buttons::A);
+        controllers[1].borrow_mut().set_buttons(Buttons::B);
+
+        io_registers.write(CONTROLLER_1_ADDRESS, 1);
+        io_registers.write(CONTROLLER_1_ADDRESS, 0);
+
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 1);
+        assert_eq!(io_registers.read(CONTROLLER_2_ADDRESS), 0);
+        assert_eq!(io_registers.read(CONTROLLER_2_ADDRESS), 1);
+    }
+
+    #[test]
+    fn test_frame_counter_write_leaves_controller_2_alone() {
+        let (mut io_registers, controllers) = setup_io_registers();
+        controllers[1]
+            .borrow_mut()
+            .set_buttons(Buttons::A | Buttons::SELECT);
+        io_registers.write(CONTROLLER_1_ADDRESS, 1);
+        io_registers.write(CONTROLLER_1_ADDRESS, 0);
+
+        assert_eq!(io_registers.read(CONTROLLER_2_ADDRESS), 1);
+        // 5-step mode, written to the same address controller 2 is read from
+        io_registers.write(CONTROLLER_2_ADDRESS, 0x80);
+        assert_eq!(io_registers.read(CONTROLLER_2_ADDRESS), 0);
+        assert_eq!(io_registers.read(CONTROLLER_2_ADDRESS), 1);
+
+        for _ in 0..4 {
+            io_registers.apu.borrow_mut().step();
+        }
+        assert_eq!(
+            io_registers.apu.borrow().frame_counter().mode(),
+            FrameCounterMode::FiveStep
+        );
+        // Controller 1 was not strobed by the frame counter write either
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 0);
+    }
+
+    fn strobe_with(
+        io_registers: &mut IoRegisters,
+        controller: &Rc<RefCell<Controller>>,
+        buttons: Buttons,
+    ) {
+        controller.borrow_mut().set_buttons(buttons);
+        io_registers.write(CONTROLLER_1_ADDRESS, 1);
+        io_registers.write(CONTROLLER_1_ADDRESS, 0);
+    }
+
+    #[test]
+    fn test_dmc_fetch_during_controller_read_drops_a_bit() {
+        let (mut io_registers, controllers) = setup_io_registers();
+        io_registers.set_dmc_conflict(true);
+        strobe_with(
+            &mut io_registers,
+            &controllers[0],
+            Buttons::A | Buttons::SELECT,
+        );
+
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 1);
+        io_registers.dmc_fetch();
+        // B is clocked out by the repeated read, Select shows up in its place
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 1);
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 0);
+    }
+
+    #[test]
+    fn test_dmc_fetch_without_conflict_emulation_keeps_reads_intact() {
+        let (mut io_registers, controllers) = setup_io_registers();
+        strobe_with(
+            &mut io_registers,
+            &controllers[0],
+            Buttons::A | Buttons::SELECT,
+        );
+
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 1);
+        io_registers.dmc_fetch();
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 0);
+        assert_eq!(io_registers.read(CONTROLLER_1_ADDRESS), 1);
+    }
+}
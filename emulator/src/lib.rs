@@ -1,9 +1,17 @@
 pub mod addressing;
+pub mod apu;
+pub mod builder;
 pub mod bus;
 pub mod cartridge;
+pub mod controller;
 pub mod cpu;
 pub mod empty_device;
+pub mod event;
+pub mod io_registers;
 pub mod logging;
 pub mod memory;
 mod mirroring;
+pub mod nes;
 pub mod ppu;
+pub mod save_state;
+pub mod test_status;
@@ -5,6 +5,10 @@ use log4rs::append::file::FileAppender;
 use log4rs::config::{Appender, Config, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
 
+const NES_LOGGER: &str = "nes";
+// Per instruction CPU messages, they go to the "nes" logger and are off unless debug is enabled
+pub const CPU_LOG_TARGET: &str = "nes::cpu";
+
 pub fn init_logging() {
     let current_time = chrono::Local::now().format("%d%m%Y_%H%M%S_%3f").to_string();
     let log_file = format!("logs/nes_{}.log", current_time);
@@ -21,7 +25,7 @@ pub fn init_logging() {
         .logger(
             Logger::builder()
                 .appender("logfile")
-                .build("nes", LevelFilter::Debug),
+                .build(NES_LOGGER, LevelFilter::Debug),
         )
         .build(
             Root::builder()
@@ -1,23 +1,142 @@
 use crate::addressing::Addressable;
+use std::fmt::Debug;
 
+// Plain memory which repeats itself every `size` bytes of the range it is mapped to
 pub struct Memory {
     mem: Vec<u8>,
 }
 
 impl Memory {
     pub fn new(size: usize) -> Memory {
-        Memory {
-            mem: Vec::with_capacity(size),
-        }
+        Memory { mem: vec![0; size] }
     }
 }
 
 impl Addressable for Memory {
     fn read(&mut self, address: u16) -> u8 {
-        self.mem[address as usize]
+        self.mem[address as usize % self.mem.len()]
     }
 
     fn write(&mut self, address: u16, data: u8) {
-        self.mem[address as usize] = data;
+        let size = self.mem.len();
+        self.mem[address as usize % size] = data;
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        Some(self.mem[address as usize % self.mem.len()])
+    }
+}
+
+impl Debug for Memory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memory")
+            .field("size", &self.mem.len())
+            .finish()
+    }
+}
+
+pub const RAM_SIZE: usize = 0x800;
+const RAM_MIRROR_MASK: u16 = 0x07FF;
+
+// Work RAM contents at power on, real consoles leave it in an unpredictable state
+// See https://www.nesdev.org/wiki/CPU_power_up_state
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RamInit {
+    #[default]
+    Zeroed,
+    Filled(u8),
+}
+
+// The 2KB CPU work RAM, only 11 address lines are decoded so it repeats every 0x800 bytes
+pub struct Ram {
+    mem: [u8; RAM_SIZE],
+}
+
+impl Default for Ram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ram {
+    pub fn new() -> Ram {
+        Self::with_init(RamInit::Zeroed)
+    }
+
+    pub fn with_init(init: RamInit) -> Ram {
+        let value = match init {
+            RamInit::Zeroed => 0,
+            RamInit::Filled(value) => value,
+        };
+        Ram {
+            mem: [value; RAM_SIZE],
+        }
+    }
+}
+
+impl Addressable for Ram {
+    fn read(&mut self, address: u16) -> u8 {
+        self.mem[(address & RAM_MIRROR_MASK) as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.mem[(address & RAM_MIRROR_MASK) as usize] = data;
+    }
+
+    fn peek(&self, address: u16) -> Option<u8> {
+        Some(self.mem[(address & RAM_MIRROR_MASK) as usize])
+    }
+}
+
+impl Debug for Ram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ram").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIRROR_WINDOWS: [u16; 4] = [0x0000, 0x0800, 0x1000, 0x1800];
+
+    #[test]
+    fn ram_initializes_to_zero() {
+        let mut ram = Ram::new();
+        assert_eq!(ram.read(0x0000), 0);
+        assert_eq!(ram.read(0x07FF), 0);
+    }
+
+    #[test]
+    fn ram_filled_init_sets_every_byte() {
+        let mut ram = Ram::with_init(RamInit::Filled(0xFF));
+        assert_eq!(ram.read(0x0000), 0xFF);
+        assert_eq!(ram.read(0x07FF), 0xFF);
+    }
+
+    #[test]
+    fn ram_mirror_windows_alias_the_same_byte() {
+        for window in MIRROR_WINDOWS {
+            let mut ram = Ram::new();
+            ram.write(window + 0x0123, 0x42);
+
+            for mirror in MIRROR_WINDOWS {
+                assert_eq!(ram.read(mirror + 0x0123), 0x42);
+            }
+        }
+    }
+
+    #[test]
+    fn ram_last_byte_is_mirrored() {
+        let mut ram = Ram::new();
+        ram.write(0x1FFF, 0x37);
+        assert_eq!(ram.read(0x07FF), 0x37);
+    }
+
+    #[test]
+    fn memory_repeats_every_size_bytes() {
+        let mut memory = Memory::new(0x10);
+        memory.write(0x0003, 0x11);
+        assert_eq!(memory.read(0x0013), 0x11);
     }
 }
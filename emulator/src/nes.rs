@@ -0,0 +1,1099 @@
+use crate::addressing::AddressRange;
+use crate::apu::apu::Apu;
+use crate::apu::filter::FilterChain;
+use crate::bus::{Bus, BusLike};
+use crate::cartridge::cartridge::{Cartridge, CartridgeChr};
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+use crate::cartridge::common::traits::cartridge_data::CartridgeData;
+use crate::controller::Controller;
+use crate::cpu::cpu::{CpuError, CpuVariant, CPU};
+use crate::event::{EmulatorEvent, EventSink};
+use crate::io_registers::IoRegisters;
+use crate::memory::RamInit;
+use crate::ppu::frame_buffer::frame_buffer::{RGBA_BYTES_PER_PIXEL, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::ppu::palette_ram::palette_ram::PaletteRAM;
+use crate::ppu::ppu::PPU;
+use crate::ppu::region::Region;
+use crate::ppu::vram::vram::VRAM;
+use crate::test_status::TestStatus;
+use log::{error, info, warn};
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::rc::Rc;
+
+const CHR_START_ADDRESS: u16 = 0x0000;
+const CHR_END_ADDRESS: u16 = 0x1FFF;
+// Nametables, 0x3000-0x3EFF mirrors 0x2000-0x2EFF
+const VRAM_START_ADDRESS: u16 = 0x2000;
+const VRAM_END_ADDRESS: u16 = 0x3EFF;
+const PALETTE_RAM_START_ADDRESS: u16 = 0x3F00;
+const PALETTE_RAM_END_ADDRESS: u16 = 0x3FFF;
+// Vblank comes at least once a frame, the margin covers instructions straddling the boundary.
+// PAL has the longest frames, the budget covers every region
+const VBLANK_CYCLE_MARGIN: u64 = 1024;
+pub const DEFAULT_CYCLE_BUDGET: u64 = Region::Pal.cpu_cycles_per_frame() + VBLANK_CYCLE_MARGIN;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Vblank,
+    // The console is paused, nothing ran
+    Paused,
+    // The cycle budget ran out before vblank, something is stuck
+    BudgetExhausted,
+    // The CPU hit an instruction it cannot run
+    CpuError(CpuError),
+    // The next instruction to run sits at a breakpoint
+    Breakpoint(u16),
+}
+
+// Steps until the PPU reports vblank or the CPU cycles spent exceed the budget,
+// each step returns the cycles it took and whether vblank started
+fn run_until_vblank(
+    cycle_budget: u64,
+    mut step: impl FnMut() -> Result<(u64, bool), CpuError>,
+) -> StopReason {
+    let mut cycles = 0;
+    while cycles <= cycle_budget {
+        let (step_cycles, vblank) = match step() {
+            Ok(step) => step,
+            Err(error) => return StopReason::CpuError(error),
+        };
+        if vblank {
+            return StopReason::Vblank;
+        }
+        cycles += step_cycles;
+    }
+    StopReason::BudgetExhausted
+}
+
+// The whole console: CPU with its bus and the devices shared with it
+pub struct Nes {
+    cpu: CPU<Bus>,
+    ppu: Rc<RefCell<PPU>>,
+    apu: Rc<RefCell<Apu>>,
+    controllers: [Rc<RefCell<Controller>>; 2],
+    io_registers: Rc<RefCell<IoRegisters>>,
+    cartridge: Rc<RefCell<Cartridge>>,
+    event_sink: Option<EventSink>,
+    frame_count: u64,
+    paused: bool,
+    // RGBA copy of the last rendered frame handed out to the host
+    frame: Vec<u8>,
+    // CPU cycles step_until_vblank may spend before giving up
+    cycle_budget: u64,
+    // Set by the host to run a game in another region than its header says
+    region_override: Option<TimingMode>,
+    breakpoints: Vec<u16>,
+    // Breakpoint reached by the last step, step_until_vblank stops on it
+    breakpoint_hit: Option<u16>,
+}
+
+impl Nes {
+    pub fn new(cartridge: Cartridge) -> Nes {
+        Self::with_ram_init(cartridge, RamInit::Zeroed)
+    }
+
+    pub fn with_ram_init(mut cartridge: Cartridge, ram_init: RamInit) -> Nes {
+        info!("NES is initializing");
+        // Mirroring starts as the cartridge header says, mappers like MMC1 switch it later
+        let vram = Rc::new(RefCell::new(VRAM::with_mirroring(cartridge.mirroring())));
+        let listener_vram = vram.clone();
+        cartridge.set_mirroring_listener(Box::new(move |mirroring| {
+            listener_vram.borrow_mut().set_mirroring(mirroring)
+        }));
+
+        let cartridge = Rc::new(RefCell::new(cartridge));
+
+        let mut ppu_bus = Bus::new();
+        ppu_bus.register(
+            CartridgeChr::new(cartridge.clone()),
+            AddressRange::new(CHR_START_ADDRESS, CHR_END_ADDRESS),
+        );
+        ppu_bus.register(
+            vram,
+            AddressRange::new(VRAM_START_ADDRESS, VRAM_END_ADDRESS),
+        );
+        ppu_bus.register(
+            PaletteRAM::new(),
+            AddressRange::new(PALETTE_RAM_START_ADDRESS, PALETTE_RAM_END_ADDRESS),
+        );
+
+        let region = Region::from(cartridge.borrow().timing_mode());
+        let ppu = Rc::new(RefCell::new(PPU::with_region(ppu_bus, region)));
+        let apu = Rc::new(RefCell::new(Apu::new()));
+        let controllers = [
+            Rc::new(RefCell::new(Controller::new())),
+            Rc::new(RefCell::new(Controller::new())),
+        ];
+        let io_registers = Rc::new(RefCell::new(IoRegisters::new(
+            apu.clone(),
+            [controllers[0].clone(), controllers[1].clone()],
+        )));
+
+        let cpu_bus = Bus::new_cpu_with_ram(ram_init)
+            .with_ppu(ppu.clone())
+            .with_io_registers(io_registers.clone())
+            .with_cartridge(cartridge.clone());
+
+        Nes {
+            cpu: CPU::new(cpu_bus),
+            ppu,
+            apu,
+            controllers,
+            io_registers,
+            cartridge,
+            event_sink: None,
+            frame_count: 0,
+            paused: false,
+            frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL],
+            cycle_budget: DEFAULT_CYCLE_BUDGET,
+            region_override: None,
+            breakpoints: Vec::new(),
+            breakpoint_hit: None,
+        }
+    }
+
+    // The host receives every emulator event through this one callback
+    pub fn set_event_sink(&mut self, event_sink: EventSink) {
+        self.event_sink = Some(event_sink);
+    }
+
+    // Freezes every clock until resumed, for front-end pause menus
+    pub fn pause(&mut self) {
+        info!("NES is paused");
+        self.paused = true;
+        self.apu.borrow_mut().set_muted(true);
+    }
+
+    pub fn resume(&mut self) {
+        info!("NES is resumed");
+        self.paused = false;
+        self.apu.borrow_mut().set_muted(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn set_cycle_budget(&mut self, cycle_budget: u64) {
+        self.cycle_budget = cycle_budget;
+    }
+
+    // Execution stops before the instruction at the address runs
+    pub fn add_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.retain(|&breakpoint| breakpoint != address);
+    }
+
+    // Runs one whole CPU instruction with the PPU ticking at the region's clock ratio,
+    // returns the CPU cycles it took, none while paused
+    pub fn step(&mut self) -> Result<u64, CpuError> {
+        if self.paused {
+            return Ok(0);
+        }
+
+        let cycles = self.cpu.step_instruction()?;
+        if self.cpu.take_irq_serviced() {
+            self.emit(EmulatorEvent::Irq);
+        }
+        // The CPU picks the NMI up from the bus, the host is notified as well
+        if self.ppu.borrow_mut().tick_cpu_cycles(cycles as u32) {
+            self.emit(EmulatorEvent::Nmi);
+        }
+        self.apu.borrow_mut().tick_cpu_cycles(cycles as u32);
+
+        let program_counter = self.cpu.program_counter();
+        if self.breakpoints.contains(&program_counter) {
+            self.breakpoint_hit = Some(program_counter);
+            self.emit(EmulatorEvent::Breakpoint(program_counter));
+        }
+        Ok(cycles)
+    }
+
+    // Steps whole instructions until the PPU enters vblank, giving up once the cycle
+    // budget is spent. Calling it again after a breakpoint runs on from the instruction
+    // it stopped at
+    pub fn step_until_vblank(&mut self) -> StopReason {
+        if self.paused {
+            return StopReason::Paused;
+        }
+
+        self.breakpoint_hit = None;
+        let frame = self.ppu.borrow().frame_count();
+        let stop_reason = run_until_vblank(self.cycle_budget, || {
+            let cycles = self.step()?;
+            let stop = self.ppu.borrow().frame_count() != frame || self.breakpoint_hit.is_some();
+            Ok((cycles, stop))
+        });
+
+        match self.breakpoint_hit.take() {
+            Some(address) => StopReason::Breakpoint(address),
+            None => stop_reason,
+        }
+    }
+
+    // Like step_until_vblank, a finished frame is counted and announced to the host
+    pub fn run_frame(&mut self) -> StopReason {
+        let frame = self.ppu.borrow().frame_count();
+        let stop_reason = self.step_until_vblank();
+
+        if self.ppu.borrow().frame_count() != frame {
+            self.frame_count += 1;
+            self.emit(EmulatorEvent::FrameReady);
+        }
+        stop_reason
+    }
+
+    // Like run_frame, for hosts that only want a dropped frame logged
+    pub fn step_frame(&mut self) {
+        match self.run_frame() {
+            StopReason::Vblank | StopReason::Paused => {}
+            StopReason::Breakpoint(address) => info!("Stopped at breakpoint {:#06X}", address),
+            StopReason::BudgetExhausted => warn!(
+                "No vblank within {} CPU cycles, the frame is dropped",
+                self.cycle_budget
+            ),
+            StopReason::CpuError(error) => error!("{}, the frame is dropped", error),
+        }
+    }
+
+    // Runs n frames back to back without any pacing and returns the final frame hash,
+    // for batch regression tests. Fails with the reason of the first frame that didn't
+    // reach vblank
+    pub fn step_frames(&mut self, n: usize) -> Result<u64, StopReason> {
+        for _ in 0..n {
+            match self.run_frame() {
+                StopReason::Vblank => {}
+                stop_reason => return Err(stop_reason),
+            }
+        }
+        Ok(self.frame_hash())
+    }
+
+    // Runs the system up to the next frame boundary and returns the picture as RGBA
+    pub fn render_frame(&mut self) -> &[u8] {
+        self.step_frame();
+        self.ppu
+            .borrow()
+            .frame_buffer()
+            .copy_rgba_into(&mut self.frame);
+        &self.frame
+    }
+
+    // Hash of the last rendered picture, for golden image tests running ROMs headless
+    pub fn frame_hash(&self) -> u64 {
+        self.ppu.borrow().frame_buffer().hash()
+    }
+
+    fn emit(&mut self, event: EmulatorEvent) {
+        if let Some(event_sink) = self.event_sink.as_mut() {
+            event_sink(event);
+        }
+    }
+
+    // Pressing the reset button, unlike a power cycle, keeps RAM and VRAM contents
+    // See https://www.nesdev.org/wiki/CPU_power_up_state
+    pub fn reset(&mut self) {
+        info!("NES is resetting");
+        self.apu.borrow_mut().reset();
+        self.ppu.borrow_mut().reset();
+        self.cpu.reset();
+    }
+
+    pub fn cpu(&self) -> &CPU<Bus> {
+        &self.cpu
+    }
+
+    pub fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu.bus().read(address)
+    }
+
+    pub fn cpu_write(&mut self, address: u16, data: u8) {
+        self.cpu.bus().write(address, data);
+    }
+
+    // Status reported by a test ROM through PRG RAM, for running test suites headless
+    pub fn test_rom_status(&mut self) -> TestStatus {
+        TestStatus::read(|address| self.cpu_read(address))
+    }
+
+    // Timing the console runs with, games working on any region run as NTSC
+    pub fn region(&self) -> TimingMode {
+        if let Some(region) = self.region_override {
+            return region;
+        }
+        match self.cartridge.borrow().timing_mode() {
+            TimingMode::MultipleRegion => TimingMode::Ntsc,
+            timing_mode => timing_mode,
+        }
+    }
+
+    pub fn set_region(&mut self, region: TimingMode) {
+        self.region_override = Some(region);
+        self.ppu.borrow_mut().set_region(Region::from(region));
+    }
+
+    // Rebuilds the audio output filters for the host's sample rate
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        *self.apu.borrow_mut().output_filter_mut() = FilterChain::nes(sample_rate);
+    }
+
+    // The 2A03 by default, other variants are for running plain 6502 programs
+    pub fn set_cpu_variant(&mut self, variant: CpuVariant) {
+        self.cpu.set_variant(variant);
+    }
+
+    // Off by default, few games rely on it and the rest read their controllers twice
+    pub fn set_dmc_controller_conflict(&mut self, enabled: bool) {
+        self.io_registers.borrow_mut().set_dmc_conflict(enabled);
+    }
+
+    // Off by default, the plain 9th sprite rule is what most games expect
+    pub fn set_accurate_sprite_overflow(&mut self, enabled: bool) {
+        self.ppu.borrow_mut().set_accurate_sprite_overflow(enabled);
+    }
+
+    pub fn controller(&self, port: usize) -> Rc<RefCell<Controller>> {
+        self.controllers[port].clone()
+    }
+
+    pub fn cartridge(&self) -> Rc<RefCell<Cartridge>> {
+        self.cartridge.clone()
+    }
+
+    pub fn apu(&self) -> Rc<RefCell<Apu>> {
+        self.apu.clone()
+    }
+
+    pub fn ppu(&self) -> Rc<RefCell<PPU>> {
+        self.ppu.clone()
+    }
+}
+
+impl Debug for Nes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nes")
+            .field("ppu", &self.ppu)
+            .field("apu", &self.apu)
+            .field("cartridge", &self.cartridge)
+            .field("frame_count", &self.frame_count)
+            .field("paused", &self.paused)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::common::consts::{CHR_UNIT_SIZE, PRG_UNIT_SIZE};
+    use crate::cartridge::common::enums::mirroring::Mirroring;
+    use crate::cartridge::mappers::mmc1::Mmc1;
+    use crate::cartridge::mappers::nrom::Nrom;
+    use crate::cartridge::registers::chr_rom::ChrRom;
+    use crate::cartridge::registers::prg_rom::PrgRom;
+    use crate::controller::Buttons;
+
+    const APU_STATUS_ADDRESS: u16 = 0x4015;
+    const PPU_CTRL_ADDRESS: u16 = 0x2000;
+    const PPU_ADDR_ADDRESS: u16 = 0x2006;
+    const PPU_DATA_ADDRESS: u16 = 0x2007;
+    const CLC_OPCODE: u8 = 0x18;
+    const BVC_OPCODE: u8 = 0x50;
+
+    fn setup_nes() -> Nes {
+        setup_nes_with_prg_banks(1)
+    }
+
+    // A frame of CLC fits in one PRG bank, NOP and jumps are not decoded yet
+    fn setup_nes_with_prg_banks(banks: usize) -> Nes {
+        let size = banks * PRG_UNIT_SIZE as usize;
+        let mut prg = vec![CLC_OPCODE; size];
+        // Reset vector pointing at the start of PRG ROM, 0xC000 for a single bank
+        let start = (0x10000 - size) as u16;
+        prg[size - 4] = start as u8;
+        prg[size - 3] = (start >> 8) as u8;
+        let nrom = Nrom::new(PrgRom::new_with_data(prg), None, Mirroring::Vertical);
+
+        Nes::new(Cartridge::new(Box::new(nrom)))
+    }
+
+    // The first PPUDATA read after setting the address fills the buffer, the second returns it
+    fn ppu_bus_read(nes: &mut Nes, address: u16) -> u8 {
+        nes.cpu_write(PPU_ADDR_ADDRESS, (address >> 8) as u8);
+        nes.cpu_write(PPU_ADDR_ADDRESS, address as u8);
+        nes.cpu_read(PPU_DATA_ADDRESS);
+        nes.cpu_write(PPU_ADDR_ADDRESS, (address >> 8) as u8);
+        nes.cpu_write(PPU_ADDR_ADDRESS, address as u8);
+        nes.cpu_read(PPU_DATA_ADDRESS)
+    }
+
+    fn ppu_bus_write(nes: &mut Nes, address: u16, data: u8) {
+        nes.cpu_write(PPU_ADDR_ADDRESS, (address >> 8) as u8);
+        nes.cpu_write(PPU_ADDR_ADDRESS, address as u8);
+        nes.cpu_write(PPU_DATA_ADDRESS, data);
+    }
+
+    #[test]
+    fn test_ppu_reads_chr_rom_from_cartridge() {
+        let mut chr = vec![0; CHR_UNIT_SIZE as usize];
+        chr[0x0010] = 0x5A;
+        chr[0x1FFF] = 0xA5;
+        let nrom = Nrom::new(
+            PrgRom::new_with_data(vec![CLC_OPCODE; PRG_UNIT_SIZE as usize]),
+            Some(ChrRom::new_with_data(chr)),
+            Mirroring::Vertical,
+        );
+        let mut nes = Nes::new(Cartridge::new(Box::new(nrom)));
+
+        assert_eq!(ppu_bus_read(&mut nes, 0x0010), 0x5A);
+        assert_eq!(ppu_bus_read(&mut nes, 0x1FFF), 0xA5);
+    }
+
+    #[test]
+    fn test_ppu_writes_chr_ram_on_cartridge() {
+        let mut nes = setup_nes();
+
+        ppu_bus_write(&mut nes, 0x0123, 0x45);
+
+        assert_eq!(nes.cartridge.borrow_mut().ppu_read(0x0123), 0x45);
+        assert_eq!(ppu_bus_read(&mut nes, 0x0123), 0x45);
+    }
+
+    #[test]
+    fn test_ppu_bus_routes_nametables_and_palette() {
+        let mut nes = setup_nes();
+
+        ppu_bus_write(&mut nes, 0x2F00, 0x11);
+        ppu_bus_write(&mut nes, 0x3F00, 0x22);
+
+        assert_eq!(ppu_bus_read(&mut nes, 0x2F00), 0x11);
+        assert_eq!(ppu_bus_read(&mut nes, 0x3F00), 0x22);
+        // 0x3000-0x3EFF mirrors the nametables
+        ppu_bus_write(&mut nes, 0x3123, 0x33);
+        assert_eq!(ppu_bus_read(&mut nes, 0x2123), 0x33);
+    }
+
+    #[test]
+    fn test_reset_keeps_ram() {
+        let mut nes = setup_nes();
+        nes.cpu_write(0x0000, 0x12);
+        nes.cpu_write(0x07FF, 0x34);
+
+        nes.reset();
+
+        assert_eq!(nes.cpu_read(0x0000), 0x12);
+        assert_eq!(nes.cpu_read(0x07FF), 0x34);
+    }
+
+    #[test]
+    fn test_reset_disables_apu_channels() {
+        let mut nes = setup_nes();
+        nes.cpu_write(APU_STATUS_ADDRESS, 0x1F);
+        // Loads the pulse 1 length counter
+        nes.cpu_write(0x4003, 0x08);
+        assert_eq!(nes.cpu_read(APU_STATUS_ADDRESS), 0x11);
+
+        nes.reset();
+
+        assert_eq!(nes.cpu_read(APU_STATUS_ADDRESS), 0x00);
+    }
+
+    #[test]
+    fn test_reset_jumps_to_reset_vector() {
+        let mut nes = setup_nes();
+
+        nes.reset();
+
+        assert_eq!(nes.cpu().program_counter(), 0xC000);
+    }
+
+    #[test]
+    fn test_reset_keeps_vram() {
+        let mut nes = setup_nes();
+        nes.cpu_write(0x2006, 0x21);
+        nes.cpu_write(0x2006, 0x00);
+        nes.cpu_write(0x2007, 0x66);
+
+        nes.reset();
+        nes.cpu_write(0x2006, 0x21);
+        nes.cpu_write(0x2006, 0x00);
+        nes.cpu_read(0x2007);
+
+        assert_eq!(nes.cpu_read(0x2007), 0x66);
+    }
+
+    fn write_vram(nes: &mut Nes, address: u16, data: u8) {
+        nes.cpu_write(0x2006, (address >> 8) as u8);
+        nes.cpu_write(0x2006, address as u8);
+        nes.cpu_write(0x2007, data);
+    }
+
+    // The first PPUDATA read only fills the read buffer
+    fn read_vram(nes: &mut Nes, address: u16) -> u8 {
+        nes.cpu_write(0x2006, (address >> 8) as u8);
+        nes.cpu_write(0x2006, address as u8);
+        nes.cpu_read(0x2007);
+        nes.cpu_read(0x2007)
+    }
+
+    #[test]
+    fn test_vram_follows_cartridge_vertical_mirroring() {
+        let mut nes = setup_nes();
+
+        write_vram(&mut nes, 0x2005, 0x42);
+        write_vram(&mut nes, 0x2405, 0x24);
+
+        assert_eq!(read_vram(&mut nes, 0x2805), 0x42);
+        assert_eq!(read_vram(&mut nes, 0x2C05), 0x24);
+    }
+
+    #[test]
+    fn test_vram_follows_four_screen_header() {
+        let path =
+            std::env::temp_dir().join(format!("baldnes-{}-four_screen.nes", std::process::id()));
+        // iNES, one PRG bank, no CHR ROM, four-screen VRAM
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x08, 0x00];
+        data.extend([0x00; 8]);
+        data.extend(vec![CLC_OPCODE; PRG_UNIT_SIZE as usize]);
+        std::fs::write(&path, data).unwrap();
+        let cartridge = Cartridge::from_file(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let mut nes = Nes::new(cartridge);
+
+        for (nametable, data) in [
+            (0x2005, 0x11),
+            (0x2405, 0x22),
+            (0x2805, 0x33),
+            (0x2C05, 0x44),
+        ] {
+            write_vram(&mut nes, nametable, data);
+        }
+
+        assert_eq!(nes.cartridge().borrow().mirroring(), Mirroring::FourScreen);
+        assert_eq!(read_vram(&mut nes, 0x2005), 0x11);
+        assert_eq!(read_vram(&mut nes, 0x2405), 0x22);
+        assert_eq!(read_vram(&mut nes, 0x2805), 0x33);
+        assert_eq!(read_vram(&mut nes, 0x2C05), 0x44);
+    }
+
+    #[test]
+    fn test_vram_follows_mapper_mirroring_changes() {
+        let prg = PrgRom::new_with_data(vec![0; 2 * PRG_UNIT_SIZE as usize]);
+        let mut nes = Nes::new(Cartridge::new(Box::new(Mmc1::new(prg, None))));
+        // MMC1 control register, loaded one bit per write: horizontal mirroring
+        for bit in 0..5 {
+            nes.cpu_write(0x8000, (0b0_1111 >> bit) & 1);
+        }
+
+        write_vram(&mut nes, 0x2005, 0x42);
+        write_vram(&mut nes, 0x2805, 0x24);
+
+        assert_eq!(read_vram(&mut nes, 0x2405), 0x42);
+        assert_eq!(read_vram(&mut nes, 0x2C05), 0x24);
+    }
+
+    fn write_test_status(nes: &mut Nes, status: u8, message: &str) {
+        for (offset, byte) in [0xDE, 0xB0, 0x61].into_iter().enumerate() {
+            nes.cpu_write(0x6001 + offset as u16, byte);
+        }
+        nes.cpu_write(0x6000, status);
+        for (offset, byte) in message.bytes().chain([0]).enumerate() {
+            nes.cpu_write(0x6004 + offset as u16, byte);
+        }
+    }
+
+    #[test]
+    fn test_rom_status_without_signature_is_not_started() {
+        let mut nes = setup_nes();
+        nes.cpu_write(0x6000, 0x80);
+
+        assert_eq!(nes.test_rom_status(), TestStatus::NotStarted);
+    }
+
+    #[test]
+    fn test_rom_status_running_and_reset_required() {
+        let mut nes = setup_nes();
+
+        write_test_status(&mut nes, 0x80, "");
+        assert_eq!(nes.test_rom_status(), TestStatus::Running);
+        assert!(!nes.test_rom_status().is_finished());
+
+        write_test_status(&mut nes, 0x81, "");
+        assert_eq!(nes.test_rom_status(), TestStatus::ResetRequired);
+    }
+
+    #[test]
+    fn test_rom_status_passed_with_message() {
+        let mut nes = setup_nes();
+        write_test_status(&mut nes, 0x00, "\nPassed\n");
+
+        let status = nes.test_rom_status();
+
+        assert!(status.is_finished());
+        assert_eq!(status, TestStatus::Passed("\nPassed\n".to_string()));
+    }
+
+    #[test]
+    fn test_rom_status_failed_with_code_and_message() {
+        let mut nes = setup_nes();
+        write_test_status(&mut nes, 0x03, "Wrong timing");
+
+        assert_eq!(
+            nes.test_rom_status(),
+            TestStatus::Failed(0x03, "Wrong timing".to_string())
+        );
+    }
+
+    // 16KB NES 2.0 image with the given CPU/PPU timing byte
+    fn load_nes_2_with_timing(name: &str, timing: u8) -> Nes {
+        let path = std::env::temp_dir().join(format!("baldnes-{}-{}", std::process::id(), name));
+        let mut data = vec![b'N', b'E', b'S', 0x1A, 0x01, 0x00, 0x00, 0x08];
+        data.extend([0x00, 0x00, 0x00, 0x00, timing, 0x00, 0x00, 0x01]);
+        data.extend(vec![CLC_OPCODE; PRG_UNIT_SIZE as usize]);
+        std::fs::write(&path, data).unwrap();
+
+        let cartridge = Cartridge::from_file(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        Nes::new(cartridge)
+    }
+
+    #[test]
+    fn test_nes_2_pal_timing_selects_pal() {
+        let nes = load_nes_2_with_timing("pal.nes", 0x01);
+
+        assert_eq!(nes.region(), TimingMode::Pal);
+        assert_eq!(nes.ppu.borrow().region(), Region::Pal);
+        assert_eq!(nes.cartridge().borrow().expansion_device(), 0x01);
+    }
+
+    #[test]
+    fn test_set_region_reaches_the_ppu() {
+        let mut nes = setup_nes();
+
+        nes.set_region(TimingMode::Pal);
+
+        assert_eq!(nes.ppu.borrow().region(), Region::Pal);
+    }
+
+    #[test]
+    fn test_multiple_region_runs_as_ntsc() {
+        let nes = load_nes_2_with_timing("multi_region.nes", 0x02);
+
+        assert_eq!(nes.region(), TimingMode::Ntsc);
+    }
+
+    #[test]
+    fn test_ines_runs_as_ntsc() {
+        assert_eq!(setup_nes().region(), TimingMode::Ntsc);
+    }
+
+    #[test]
+    fn test_run_until_vblank_guard_fires_when_ppu_is_stuck() {
+        let mut steps = 0;
+
+        // A jammed CPU burning cycles next to a PPU that never advances
+        let stop_reason = run_until_vblank(DEFAULT_CYCLE_BUDGET, || {
+            steps += 1;
+            Ok((3, false))
+        });
+
+        assert_eq!(stop_reason, StopReason::BudgetExhausted);
+        assert_eq!(steps, DEFAULT_CYCLE_BUDGET / 3 + 1);
+    }
+
+    #[test]
+    fn test_run_until_vblank_stops_at_vblank() {
+        let mut cycles = 0;
+
+        let stop_reason = run_until_vblank(DEFAULT_CYCLE_BUDGET, || {
+            cycles += 2;
+            Ok((2, cycles == 100))
+        });
+
+        assert_eq!(stop_reason, StopReason::Vblank);
+        assert_eq!(cycles, 100);
+    }
+
+    #[test]
+    fn test_run_until_vblank_stops_on_cpu_error() {
+        let error = CpuError::UnknownOpcode {
+            opcode: 0x02,
+            address: 0x8000,
+        };
+
+        let stop_reason = run_until_vblank(DEFAULT_CYCLE_BUDGET, || Err(error));
+
+        assert_eq!(stop_reason, StopReason::CpuError(error));
+    }
+
+    #[test]
+    fn test_step_until_vblank_reaches_vblank_within_default_budget() {
+        let mut nes = setup_nes_with_prg_banks(2);
+        nes.reset();
+
+        assert_eq!(nes.step_until_vblank(), StopReason::Vblank);
+        assert_eq!(nes.ppu.borrow().scanline(), 241);
+    }
+
+    #[test]
+    fn test_controller_is_read_through_the_cpu_bus() {
+        let mut nes = setup_nes();
+        nes.controller(0)
+            .borrow_mut()
+            .set_buttons(Buttons::A | Buttons::START);
+
+        nes.cpu_write(0x4016, 1);
+        nes.cpu_write(0x4016, 0);
+        let bits: Vec<u8> = (0..8).map(|_| nes.cpu_read(0x4016) & 1).collect();
+
+        assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_step_ticks_ppu_three_dots_per_cpu_cycle() {
+        let mut nes = setup_nes();
+        nes.reset();
+        let scanline = nes.ppu.borrow().scanline();
+        let dot = nes.ppu.borrow().dot();
+
+        let cycles = nes.step().unwrap();
+
+        let ppu = nes.ppu.borrow();
+        assert_eq!(cycles, 2);
+        assert_eq!(ppu.scanline(), scanline);
+        assert_eq!(ppu.dot() - dot, 6);
+    }
+
+    #[test]
+    fn test_run_frame_reaches_vblank() {
+        let mut nes = setup_nes_with_prg_banks(2);
+        nes.reset();
+        let frame = nes.ppu.borrow().frame_count();
+
+        assert_eq!(nes.run_frame(), StopReason::Vblank);
+
+        assert_eq!(nes.frame_count(), 1);
+        let ppu = nes.ppu.borrow();
+        assert_eq!(ppu.frame_count(), frame + 1);
+        assert_eq!(ppu.scanline(), 241);
+    }
+
+    #[test]
+    fn test_step_and_run_frame_while_paused_do_nothing() {
+        let mut nes = setup_nes();
+        let events = record_events(&mut nes);
+        nes.reset();
+        let cycles = nes.cpu().cycle_count();
+        let dot = nes.ppu.borrow().dot();
+
+        nes.pause();
+
+        assert_eq!(nes.step(), Ok(0));
+        assert_eq!(nes.run_frame(), StopReason::Paused);
+        assert_eq!(nes.cpu().cycle_count(), cycles);
+        assert_eq!(nes.ppu.borrow().dot(), dot);
+        assert_eq!(nes.frame_count(), 0);
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_run_frame_and_step_frame_count_frames_alike() {
+        let mut nes = setup_nes_with_prg_banks(2);
+        let events = record_events(&mut nes);
+        nes.reset();
+
+        nes.run_frame();
+        nes.step_frame();
+
+        assert_eq!(nes.frame_count(), 2);
+        assert_eq!(
+            *events.borrow(),
+            vec![EmulatorEvent::FrameReady, EmulatorEvent::FrameReady]
+        );
+    }
+
+    #[test]
+    fn test_run_frame_stops_at_breakpoint() {
+        let mut nes = setup_nes_with_prg_banks(2);
+        let events = record_events(&mut nes);
+        nes.reset();
+        nes.add_breakpoint(0x8010);
+
+        assert_eq!(nes.run_frame(), StopReason::Breakpoint(0x8010));
+        assert_eq!(nes.cpu().program_counter(), 0x8010);
+        assert_eq!(*events.borrow(), vec![EmulatorEvent::Breakpoint(0x8010)]);
+
+        // Running again carries on past the breakpoint
+        assert_eq!(nes.run_frame(), StopReason::Vblank);
+        assert_eq!(nes.frame_count(), 1);
+
+        nes.remove_breakpoint(0x8010);
+        nes.reset();
+        events.borrow_mut().clear();
+        assert_eq!(nes.run_frame(), StopReason::Vblank);
+        assert_eq!(*events.borrow(), vec![EmulatorEvent::FrameReady]);
+    }
+
+    #[test]
+    fn test_step_frame_drops_frame_when_budget_runs_out() {
+        let mut nes = setup_nes();
+        let events = record_events(&mut nes);
+        nes.reset();
+        nes.set_cycle_budget(100);
+
+        nes.step_frame();
+
+        assert_eq!(nes.frame_count(), 0);
+        assert!(events.borrow().is_empty());
+    }
+
+    fn record_events(nes: &mut Nes) -> Rc<RefCell<Vec<EmulatorEvent>>> {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink_events = events.clone();
+        nes.set_event_sink(Box::new(move |event| sink_events.borrow_mut().push(event)));
+        events
+    }
+
+    #[test]
+    fn test_step_frame_emits_frame_ready() {
+        let mut nes = setup_nes();
+        let events = record_events(&mut nes);
+        nes.reset();
+
+        nes.step_frame();
+
+        assert_eq!(*events.borrow(), vec![EmulatorEvent::FrameReady]);
+    }
+
+    #[test]
+    fn test_step_frame_emits_nmi_when_enabled() {
+        let mut nes = setup_nes();
+        let events = record_events(&mut nes);
+        nes.reset();
+        nes.cpu_write(PPU_CTRL_ADDRESS, 0x80);
+
+        nes.step_frame();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![EmulatorEvent::Nmi, EmulatorEvent::FrameReady]
+        );
+    }
+
+    #[test]
+    fn test_step_frame_while_paused_does_nothing() {
+        let mut nes = setup_nes();
+        let events = record_events(&mut nes);
+        nes.reset();
+        let cycles = nes.cpu().cycle_count();
+
+        nes.pause();
+        nes.step_frame();
+
+        assert!(nes.is_paused());
+        assert!(nes.apu.borrow().is_muted());
+        assert_eq!(nes.cpu().cycle_count(), cycles);
+        assert_eq!(nes.frame_count(), 0);
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_step_frame_after_resume() {
+        let mut nes = setup_nes();
+        nes.reset();
+        nes.pause();
+
+        nes.resume();
+        nes.step_frame();
+
+        assert!(!nes.is_paused());
+        assert!(!nes.apu.borrow().is_muted());
+        assert_eq!(nes.frame_count(), 1);
+    }
+
+    #[test]
+    fn test_render_frame_returns_rgba_frame() {
+        let mut nes = setup_nes();
+        nes.reset();
+
+        let frame = nes.render_frame();
+
+        assert_eq!(
+            frame.len(),
+            SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL
+        );
+        assert_eq!(frame[3], 0xFF);
+    }
+
+    #[test]
+    fn test_frame_hash_matches_frame_buffer() {
+        let mut nes = setup_nes();
+
+        nes.render_frame();
+
+        assert_eq!(nes.frame_hash(), nes.ppu.borrow().frame_buffer().hash());
+    }
+
+    // Spins on BVC to itself with the backdrop set to a fixed color
+    fn setup_nes_with_idle_loop() -> Nes {
+        let mut prg = vec![0; PRG_UNIT_SIZE as usize];
+        prg[0] = BVC_OPCODE;
+        prg[1] = 0xFE;
+        prg[PRG_UNIT_SIZE as usize - 4] = 0x00;
+        prg[PRG_UNIT_SIZE as usize - 3] = 0xC0;
+        let nrom = Nrom::new(PrgRom::new_with_data(prg), None, Mirroring::Vertical);
+        let mut nes = Nes::new(Cartridge::new(Box::new(nrom)));
+        nes.reset();
+        write_vram(&mut nes, 0x3F00, 0x21);
+        nes
+    }
+
+    const CLI_OPCODE: u8 = 0x58;
+    const IRQ_HANDLER_ADDRESS: u16 = 0xC100;
+
+    // Clears I, then spins at 0xC001, the IRQ handler spins at IRQ_HANDLER_ADDRESS
+    fn setup_nes_waiting_for_irq() -> Nes {
+        let mut prg = vec![0; PRG_UNIT_SIZE as usize];
+        prg[..3].copy_from_slice(&[CLI_OPCODE, BVC_OPCODE, 0xFE]);
+        let handler = (IRQ_HANDLER_ADDRESS - 0xC000) as usize;
+        prg[handler..handler + 2].copy_from_slice(&[BVC_OPCODE, 0xFE]);
+        let size = PRG_UNIT_SIZE as usize;
+        prg[size - 4..].copy_from_slice(&[0x00, 0xC0, IRQ_HANDLER_ADDRESS as u8, 0xC1]);
+        let nrom = Nrom::new(PrgRom::new_with_data(prg), None, Mirroring::Vertical);
+        let mut nes = Nes::new(Cartridge::new(Box::new(nrom)));
+        nes.reset();
+        nes
+    }
+
+    fn run_cycles(nes: &mut Nes, cycles: u64) {
+        let start = nes.cpu().cycle_count();
+        while nes.cpu().cycle_count() - start < cycles {
+            nes.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_frame_irq_enters_irq_vector() {
+        let mut nes = setup_nes_waiting_for_irq();
+
+        // The 4-step sequence raises its IRQ on CPU cycle 29829
+        run_cycles(&mut nes, 29000);
+        assert_eq!(nes.cpu().program_counter(), 0xC001);
+
+        run_cycles(&mut nes, 1000);
+        assert_eq!(nes.cpu().program_counter(), IRQ_HANDLER_ADDRESS);
+    }
+
+    // Runs the program from 0xC000 with I set, so no IRQ gets in
+    fn setup_nes_running(program: &[u8]) -> Nes {
+        let mut prg = vec![0; PRG_UNIT_SIZE as usize];
+        prg[..program.len()].copy_from_slice(program);
+        prg[PRG_UNIT_SIZE as usize - 4] = 0x00;
+        prg[PRG_UNIT_SIZE as usize - 3] = 0xC0;
+        let nrom = Nrom::new(PrgRom::new_with_data(prg), None, Mirroring::Vertical);
+        let mut nes = Nes::new(Cartridge::new(Box::new(nrom)));
+        nes.reset();
+        nes
+    }
+
+    #[test]
+    fn test_trace_does_not_read_ppu_status() {
+        // LDA $2002
+        let mut nes = setup_nes_running(&[0xAD, 0x02, 0x20]);
+        nes.ppu.borrow_mut().set_vblank(true);
+
+        assert_eq!(
+            nes.cpu().trace(),
+            "C000  AD 02 20  LDA $2002                       A:00 X:00 Y:00 P:24 SP:FD"
+        );
+
+        // Vblank survived the trace, so the instruction itself still sees it
+        nes.step().unwrap();
+        assert_eq!(nes.cpu().a() & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_trace_does_not_acknowledge_frame_irq() {
+        // LDA $4015
+        let mut nes = setup_nes_running(&[0xAD, 0x15, 0x40]);
+        nes.apu.borrow_mut().tick_cpu_cycles(30000);
+        assert!(nes.apu.borrow().irq());
+
+        assert_eq!(
+            nes.cpu().trace(),
+            "C000  AD 15 40  LDA $4015                       A:00 X:00 Y:00 P:24 SP:FD"
+        );
+        assert!(nes.apu.borrow().irq());
+
+        // Only the real read reports and acknowledges it
+        nes.step().unwrap();
+        assert_eq!(nes.cpu().a() & 0x40, 0x40);
+        assert!(!nes.apu.borrow().irq());
+    }
+
+    #[test]
+    fn test_serviced_irq_emits_irq() {
+        let mut nes = setup_nes_waiting_for_irq();
+        let events = record_events(&mut nes);
+
+        run_cycles(&mut nes, 30000);
+
+        assert_eq!(*events.borrow(), vec![EmulatorEvent::Irq]);
+    }
+
+    #[test]
+    fn test_inhibited_frame_irq_is_not_taken() {
+        let mut nes = setup_nes_waiting_for_irq();
+        nes.cpu_write(0x4017, 0x40);
+
+        run_cycles(&mut nes, 30000);
+
+        assert_eq!(nes.cpu().program_counter(), 0xC001);
+    }
+
+    #[test]
+    fn test_step_frames_is_deterministic() {
+        let mut nes = setup_nes_with_idle_loop();
+
+        let hash = nes.step_frames(3);
+
+        assert_eq!(nes.frame_count(), 3);
+        assert_eq!(hash, Ok(0x4A71_8922_6DCD_B325));
+        assert_eq!(nes.ppu.borrow().frame_buffer().get_pixel(128, 120), 0x21);
+        assert_eq!(setup_nes_with_idle_loop().step_frames(3), hash);
+    }
+
+    #[test]
+    fn test_step_frames_reports_frames_that_did_not_finish() {
+        let mut nes = setup_nes_with_idle_loop();
+        nes.pause();
+        assert_eq!(nes.step_frames(3), Err(StopReason::Paused));
+        assert_eq!(nes.frame_count(), 0);
+
+        nes.resume();
+        nes.set_cycle_budget(1000);
+        assert_eq!(nes.step_frames(3), Err(StopReason::BudgetExhausted));
+        assert_eq!(nes.frame_count(), 0);
+    }
+
+    #[test]
+    fn test_render_frame_advances_frame_count() {
+        let mut nes = setup_nes_with_prg_banks(2);
+        nes.reset();
+
+        nes.render_frame();
+        assert_eq!(nes.frame_count(), 1);
+
+        nes.render_frame();
+        assert_eq!(nes.frame_count(), 2);
+    }
+}
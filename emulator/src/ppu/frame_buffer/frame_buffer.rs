@@ -0,0 +1,179 @@
+use crate::ppu::palette_ram::palette_ram::SYSTEM_PALETTE;
+use std::fmt::Debug;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+pub const RGB_BYTES_PER_PIXEL: usize = 3;
+pub const RGBA_BYTES_PER_PIXEL: usize = 4;
+
+// 64-bit FNV-1a, see http://www.isthe.com/chongo/tech/comp/fnv/
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+const OPAQUE_ALPHA: u8 = 0xFF;
+
+// Visible picture as indices into the system palette, one byte per pixel,
+// along with its RGB and RGBA conversions ready for a front-end
+pub struct FrameBuffer {
+    pixels: Vec<u8>,
+    rgb: Vec<u8>,
+    rgba: Vec<u8>,
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameBuffer {
+    pub fn new() -> FrameBuffer {
+        let (red, green, blue) = SYSTEM_PALETTE[0];
+        FrameBuffer {
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            rgb: [red, green, blue].repeat(SCREEN_WIDTH * SCREEN_HEIGHT),
+            rgba: [red, green, blue, OPAQUE_ALPHA].repeat(SCREEN_WIDTH * SCREEN_HEIGHT),
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * SCREEN_WIDTH + x]
+    }
+
+    pub fn get_rgb(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let offset = (y * SCREEN_WIDTH + x) * RGB_BYTES_PER_PIXEL;
+        (self.rgb[offset], self.rgb[offset + 1], self.rgb[offset + 2])
+    }
+
+    pub fn get_rgba(&self, x: usize, y: usize) -> [u8; RGBA_BYTES_PER_PIXEL] {
+        let offset = (y * SCREEN_WIDTH + x) * RGBA_BYTES_PER_PIXEL;
+        let mut rgba = [0; RGBA_BYTES_PER_PIXEL];
+        rgba.copy_from_slice(&self.rgba[offset..offset + RGBA_BYTES_PER_PIXEL]);
+        rgba
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: u8) {
+        self.set_pixel_rgb(x, y, color, SYSTEM_PALETTE[color as usize]);
+    }
+
+    // Stores a pixel whose RGB differs from the plain system palette, e.g. with color emphasis
+    pub fn set_pixel_rgb(&mut self, x: usize, y: usize, color: u8, rgb: (u8, u8, u8)) {
+        self.pixels[y * SCREEN_WIDTH + x] = color;
+
+        let (red, green, blue) = rgb;
+        let offset = (y * SCREEN_WIDTH + x) * RGB_BYTES_PER_PIXEL;
+        self.rgb[offset..offset + RGB_BYTES_PER_PIXEL].copy_from_slice(&[red, green, blue]);
+
+        let offset = (y * SCREEN_WIDTH + x) * RGBA_BYTES_PER_PIXEL;
+        self.rgba[offset..offset + RGBA_BYTES_PER_PIXEL].copy_from_slice(&[
+            red,
+            green,
+            blue,
+            OPAQUE_ALPHA,
+        ]);
+    }
+
+    pub fn scanline(&self, y: usize) -> &[u8] {
+        &self.pixels[y * SCREEN_WIDTH..(y + 1) * SCREEN_WIDTH]
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    // Row-major RGB triples, SCREEN_WIDTH * SCREEN_HEIGHT * RGB_BYTES_PER_PIXEL bytes
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb
+    }
+
+    // Row-major packed RGBA8888 with opaque alpha, what most GUI and WASM canvases take,
+    // SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL bytes
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    // Stable fingerprint of the RGB picture for comparing frames against known good ones
+    pub fn hash(&self) -> u64 {
+        self.rgb.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    // Fills a SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL buffer with opaque pixels
+    pub fn copy_rgba_into(&self, buffer: &mut [u8]) {
+        let length = buffer.len().min(self.rgba.len());
+        buffer[..length].copy_from_slice(&self.rgba[..length]);
+    }
+}
+
+impl Debug for FrameBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FrameBuffer")
+            .field("width", &SCREEN_WIDTH)
+            .field("height", &SCREEN_HEIGHT)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_of_blank_frame() {
+        assert_eq!(FrameBuffer::new().hash(), 0x0936_0430_3092_6325);
+    }
+
+    #[test]
+    fn hash_of_pattern_is_fixed() {
+        let mut frame_buffer = FrameBuffer::new();
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                frame_buffer.set_pixel(x, y, ((x / 8 + y / 8) % 64) as u8);
+            }
+        }
+
+        assert_eq!(frame_buffer.hash(), 0x6456_9619_4F09_4725);
+    }
+
+    #[test]
+    fn hash_changes_with_a_single_pixel() {
+        let mut frame_buffer = FrameBuffer::new();
+        let blank = frame_buffer.hash();
+
+        frame_buffer.set_pixel(128, 120, 0x30);
+
+        assert_ne!(frame_buffer.hash(), blank);
+    }
+
+    #[test]
+    fn rgba_follows_rendered_pixel() {
+        let mut frame_buffer = FrameBuffer::new();
+
+        frame_buffer.set_pixel(3, 2, 0x16);
+
+        let (red, green, blue) = SYSTEM_PALETTE[0x16];
+        assert_eq!(frame_buffer.get_rgba(3, 2), [red, green, blue, 0xFF]);
+        let offset = (2 * SCREEN_WIDTH + 3) * RGBA_BYTES_PER_PIXEL;
+        assert_eq!(
+            frame_buffer.rgba()[offset..offset + RGBA_BYTES_PER_PIXEL],
+            [red, green, blue, 0xFF]
+        );
+        assert_eq!(
+            frame_buffer.rgba().len(),
+            SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL
+        );
+    }
+
+    #[test]
+    fn copy_rgba_into_matches_rgba() {
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer.set_pixel_rgb(0, 0, 0x16, (0x12, 0x34, 0x56));
+        let mut buffer = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * RGBA_BYTES_PER_PIXEL];
+
+        frame_buffer.copy_rgba_into(&mut buffer);
+
+        assert_eq!(buffer[..RGBA_BYTES_PER_PIXEL], [0x12, 0x34, 0x56, 0xFF]);
+        assert_eq!(buffer, frame_buffer.rgba());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod frame_buffer;
 pub mod palette_ram;
+#[allow(clippy::module_inception)]
 pub mod ppu;
+pub mod region;
 mod registers;
+pub mod sprite;
 pub mod vram;
@@ -1,5 +1,8 @@
 use crate::addressing::Addressable;
+use crate::ppu::registers::ppu_mask::PPUMask;
+use crate::save_state::{self, SaveStateError};
 use log::{debug, info};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 pub static SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
@@ -69,6 +72,42 @@ pub static SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
     (0x11, 0x11, 0x11),
 ];
 
+pub const GRAYSCALE_MASK: u8 = 0x30;
+// Channels left out of the emphasis are dimmed to roughly 82%, see https://www.nesdev.org/wiki/NTSC_video
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+// RGB of a system palette color as displayed with the PPUMASK grayscale and emphasis bits
+pub fn masked_color(color: u8, mask: &PPUMask) -> (u8, u8, u8) {
+    let color = if mask.grayscale() {
+        color & GRAYSCALE_MASK
+    } else {
+        color & 0x3F
+    };
+    let (red, green, blue) = SYSTEM_PALETTE[color as usize];
+
+    let emphasis = [
+        mask.emphasize_red(),
+        mask.emphasize_green(),
+        mask.emphasize_blue(),
+    ];
+    if !emphasis.contains(&true) {
+        return (red, green, blue);
+    }
+
+    let attenuate = |channel: u8, emphasized: bool| {
+        if emphasized {
+            channel
+        } else {
+            (channel as f32 * EMPHASIS_ATTENUATION) as u8
+        }
+    };
+    (
+        attenuate(red, emphasis[0]),
+        attenuate(green, emphasis[1]),
+        attenuate(blue, emphasis[2]),
+    )
+}
+
 enum PaletteType {
     Background,
     Sprite,
@@ -83,6 +122,7 @@ impl Debug for PaletteType {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 struct Palette {
     index: u8,
     background_entries: [u8; 4],
@@ -121,10 +161,17 @@ impl Palette {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct PaletteRAM {
     palettes: [Palette; 4],
 }
 
+impl Default for PaletteRAM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PaletteRAM {
     pub fn new() -> Self {
         info!("PaletteRAM is initializing");
@@ -138,6 +185,15 @@ impl PaletteRAM {
         }
     }
 
+    pub fn save_state(&self) -> Vec<u8> {
+        save_state::encode(self)
+    }
+
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        *self = save_state::decode(bytes)?;
+        Ok(())
+    }
+
     fn read_from_palette(&self, address: u16) -> u8 {
         let palette_type = match address {
             0x3F00..=0x3F0F => PaletteType::Background,
@@ -146,7 +202,7 @@ impl PaletteRAM {
         };
 
         let index_in_palette = ((address & 0x0F) % 4) as u8;
-        let index = ((address & 0x0F) >> 4) as usize;
+        let index = ((address & 0x0F) >> 2) as usize;
 
         self.palettes[index].get_palette(palette_type, index_in_palette)
     }
@@ -159,19 +215,23 @@ impl PaletteRAM {
         };
 
         let index_in_palette = ((address & 0x0F) % 4) as u8;
-        let index = ((address & 0x0F) >> 4) as usize;
+        let index = ((address & 0x0F) >> 2) as usize;
 
         self.palettes[index].set_palette(palette_type, index_in_palette, data);
     }
 
     fn mirror_address(&self, address: u16) -> u16 {
         // Reduces the address to the range 0x3F00 - 0x3F1F
+        let mut mirrored = 0x3F00 + (address & 0x1F);
+        // Entry 0 of each sprite palette is shared with the background one, 0x3F10 is 0x3F00
+        if mirrored & 0x13 == 0x10 {
+            mirrored &= !0x10;
+        }
         debug!(
             "Mirroring address: {:#6X} down to {:#6X}",
-            address,
-            0x3F00 + (address & 0x1F)
+            address, mirrored
         );
-        0x3F00 + (address & 0x1F)
+        mirrored
     }
 }
 
@@ -181,8 +241,7 @@ impl Addressable for PaletteRAM {
     fn read(&mut self, address: u16) -> u8 {
         debug!("Reading from palette address: {:#6X}", address);
         match address {
-            0x3F00..=0x3F1F => self.read_from_palette(address),
-            0x3F20..=0x3FFF => self.read_from_palette(self.mirror_address(address)),
+            0x3F00..=0x3FFF => self.read_from_palette(self.mirror_address(address)),
             _ => panic!("Invalid palette address: {:#6X}", address),
         }
     }
@@ -190,8 +249,7 @@ impl Addressable for PaletteRAM {
     fn write(&mut self, address: u16, data: u8) {
         debug!("Reading from palette address: {:#6X}", address);
         match address {
-            0x3F00..=0x3F1F => self.write_to_palette(address, data),
-            0x3F20..=0x3FFF => self.write_to_palette(self.mirror_address(address), data),
+            0x3F00..=0x3FFF => self.write_to_palette(self.mirror_address(address), data),
             _ => panic!("Invalid palette address: {:#6X}", address),
         }
     }
@@ -251,6 +309,69 @@ mod tests {
         assert_eq!(palette_ram.read(0x3F00), 0x34);
     }
 
+    #[test]
+    fn backdrop_written_at_0x3f00_reads_back_at_0x3f10() {
+        let mut palette_ram = PaletteRAM::new();
+        palette_ram.write(0x3F00, 0x21);
+        assert_eq!(palette_ram.read(0x3F10), 0x21);
+    }
+
+    #[test]
+    fn backdrop_written_at_0x3f10_reads_back_at_0x3f00() {
+        let mut palette_ram = PaletteRAM::new();
+        palette_ram.write(0x3F10, 0x2A);
+        assert_eq!(palette_ram.read(0x3F00), 0x2A);
+    }
+
+    #[test]
+    fn sprite_palette_entry_0_mirrors_background_entry_0() {
+        let mut palette_ram = PaletteRAM::new();
+        for (sprite_address, background_address) in
+            [(0x3F14, 0x3F04), (0x3F18, 0x3F08), (0x3F1C, 0x3F0C)]
+        {
+            palette_ram.write(sprite_address, sprite_address as u8);
+            assert_eq!(palette_ram.read(background_address), sprite_address as u8);
+        }
+        // Also through the 0x3F20-0x3FFF mirrors
+        palette_ram.write(0x3F30, 0x15);
+        assert_eq!(palette_ram.read(0x3F00), 0x15);
+    }
+
+    #[test]
+    fn other_sprite_palette_entries_are_separate() {
+        let mut palette_ram = PaletteRAM::new();
+        palette_ram.write(0x3F01, 0x11);
+        palette_ram.write(0x3F11, 0x22);
+        palette_ram.write(0x3F05, 0x33);
+
+        assert_eq!(palette_ram.read(0x3F01), 0x11);
+        assert_eq!(palette_ram.read(0x3F11), 0x22);
+        assert_eq!(palette_ram.read(0x3F05), 0x33);
+    }
+
+    #[test]
+    fn masked_color_without_effects_is_the_system_color() {
+        assert_eq!(masked_color(0x16, &PPUMask::new()), SYSTEM_PALETTE[0x16]);
+    }
+
+    #[test]
+    fn masked_color_grayscale_drops_the_hue() {
+        let mut mask = PPUMask::new();
+        mask.write(0b0000_0001);
+
+        assert_eq!(masked_color(0x16, &mask), SYSTEM_PALETTE[0x10]);
+        assert_eq!(masked_color(0x3C, &mask), SYSTEM_PALETTE[0x30]);
+    }
+
+    #[test]
+    fn masked_color_red_emphasis_dims_green_and_blue() {
+        let mut mask = PPUMask::new();
+        mask.write(0b0010_0000);
+
+        // 0x20 is white, so only the emphasis changes it
+        assert_eq!(masked_color(0x20, &mask), (0xFF, 0xD0, 0xD0));
+    }
+
     #[test]
     #[should_panic(expected = "Invalid palette address: 0x4000")]
     fn read_palette_ram_out_of_bounds() {
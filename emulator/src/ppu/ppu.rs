@@ -3,41 +3,246 @@ use std::fmt::Debug;
 
 use crate::addressing::Addressable;
 use crate::bus::Bus;
+use crate::ppu::frame_buffer::frame_buffer::{
+    FrameBuffer, RGBA_BYTES_PER_PIXEL, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+use crate::ppu::palette_ram::palette_ram::{masked_color, GRAYSCALE_MASK};
+use crate::ppu::region::{Region, DOTS_PER_SCANLINE};
 use crate::ppu::registers::ppu_addr::PPUAddr;
 use crate::ppu::registers::ppu_ctrl::PPUCtrl;
 use crate::ppu::registers::ppu_data::PPUData;
+use crate::ppu::registers::ppu_mask::PPUMask;
+use crate::ppu::registers::ppu_status::PPUStatus;
+use crate::ppu::sprite::sprite::{Sprite, SPRITE_SIZE_IN_BYTES};
+use crate::save_state::{self, SaveStateError};
+use serde::{Deserialize, Serialize};
 
+// The eight registers at 0x2000-0x2007 repeat every eight bytes up to 0x3FFF
+const REGISTERS_START_ADDRESS: u16 = 0x2000;
+const REGISTER_SELECT_MASK: u16 = 0x0007;
 const MIRRORS_START_ADDRESS: u16 = 0x2008;
 const MIRRORS_END_ADDRESS: u16 = 0x3FFF;
 
+const TILE_SIZE: usize = 8;
+const NAMETABLE_WIDTH_IN_TILES: usize = 32;
+const ATTRIBUTE_TABLE_OFFSET: u16 = 0x3C0;
+const PATTERN_TILE_SIZE: u16 = 16;
+const PALETTE_RAM_START_ADDRESS: u16 = 0x3F00;
+// Distance from a palette address down to the nametable mirror behind it
+const PALETTE_NAMETABLE_OFFSET: u16 = 0x1000;
+const SYSTEM_PALETTE_MASK: u8 = 0x3F;
+// PPUSTATUS only drives its top three bits, the rest comes from the I/O latch
+const PPU_STATUS_FLAGS_MASK: u8 = 0xE0;
+
+// Frame timing, see https://www.nesdev.org/wiki/PPU_rendering
+// Vblank starts on the same scanline in every region, PAL has a longer vblank instead
+const VBLANK_SCANLINE: u16 = 241;
+// Flags change on the second dot of a scanline
+const FLAG_UPDATE_DOT: u16 = 1;
+
+const PATTERN_TABLE_SIZE: u16 = 0x1000;
+// A pattern table drawn as 16x16 tiles of 8x8 pixels
+pub const PATTERN_TABLE_VIEW_SIZE: usize = 128;
+const PATTERN_TABLE_VIEW_TILES: usize = PATTERN_TABLE_VIEW_SIZE / TILE_SIZE;
+const SPRITES_PER_SCANLINE: usize = 8;
+// Sprite 0 hit never happens on the last dot of a scanline
+const SPRITE_ZERO_HIT_EXCLUDED_X: usize = 255;
+
+// 64 sprites, four bytes each, see https://www.nesdev.org/wiki/PPU_OAM
+pub const OAM_SIZE: usize = 256;
+const OAM_SPRITES: usize = OAM_SIZE / SPRITE_SIZE_IN_BYTES;
+
+// Loopy register layout, see https://www.nesdev.org/wiki/PPU_scrolling
+// yyy NN YYYYY XXXXX: fine Y, nametable, coarse Y, coarse X
+const COARSE_X_MASK: u16 = 0x001F;
+const COARSE_Y_MASK: u16 = 0x03E0;
+const NAMETABLE_MASK: u16 = 0x0C00;
+const FINE_Y_MASK: u16 = 0x7000;
+const LOOPY_HIGH_BYTE_MASK: u16 = 0x3F00;
+const LOOPY_LOW_BYTE_MASK: u16 = 0x00FF;
+
 pub struct PPU {
     ppu_addr: PPUAddr,
     ppu_data: PPUData,
     ppu_ctrl: PPUCtrl,
+    ppu_mask: PPUMask,
+    ppu_status: PPUStatus,
+    internal_read_buffer: u8,
+    // Set while the next $2005/$2006 write is the first one, which is the cleared latch (w = 0)
+    internal_w_register: bool,
+    // Current and temporary VRAM address and fine X scroll, shared by $2005 and $2006
+    v: u16,
+    t: u16,
+    x: u8,
+    oam: [u8; OAM_SIZE],
+    oam_addr: u8,
+    frame_buffer: FrameBuffer,
+    scanline: u16,
+    dot: u16,
+    frame_count: u64,
+    nmi_pending: bool,
+    // Raised once the last visible pixel of a frame is drawn, until the front-end takes it
+    frame_complete: bool,
+    // Sprites evaluated for the scanline being drawn
+    scanline_sprites: Vec<(usize, Sprite)>,
+    // Last value on the data lines between the CPU and the PPU, read back from write-only registers
+    // See https://www.nesdev.org/wiki/PPU_registers#Ports
+    io_latch: u8,
+    region: Region,
+    // CPU cycles not yet turned into whole dots, PAL has 3.2 dots per CPU cycle
+    dot_remainder: u32,
+    // Reproduce the hardware's buggy overflow search instead of the plain 9th sprite rule
+    accurate_sprite_overflow: bool,
+}
+
+// Registers, latches, OAM and timing of the PPU. The frame buffer is left out, the next
+// frame redraws it, and so is the bus, VRAM and palette RAM are saved on their own
+#[derive(Serialize, Deserialize)]
+struct PpuSaveState {
+    ppu_addr: u16,
+    ppu_ctrl: u8,
+    ppu_mask: u8,
+    ppu_status: u8,
     internal_read_buffer: u8,
     internal_w_register: bool,
+    v: u16,
+    t: u16,
+    x: u8,
+    oam: Vec<u8>,
+    oam_addr: u8,
+    scanline: u16,
+    dot: u16,
+    frame_count: u64,
+    nmi_pending: bool,
+    scanline_sprites: Vec<(usize, Sprite)>,
+    io_latch: u8,
 }
 
 impl PPU {
     pub fn new(ppu_bus: Bus) -> PPU {
+        Self::with_region(ppu_bus, Region::Ntsc)
+    }
+
+    // Power-up state, see https://www.nesdev.org/wiki/PPU_power_up_state
+    // PPUSTATUS is left unspecified by the hardware, it powers up cleared here
+    pub fn with_region(ppu_bus: Bus, region: Region) -> PPU {
         info!("PPU is initializing");
         PPU {
             ppu_addr: PPUAddr::new(),
             ppu_data: PPUData::new(ppu_bus),
             ppu_ctrl: PPUCtrl::new(),
+            ppu_mask: PPUMask::new(),
+            ppu_status: PPUStatus::new(),
             internal_read_buffer: 0,
             internal_w_register: true,
+            v: 0,
+            t: 0,
+            x: 0,
+            oam: [0; OAM_SIZE],
+            oam_addr: 0,
+            frame_buffer: FrameBuffer::new(),
+            scanline: 0,
+            dot: 0,
+            frame_count: 0,
+            nmi_pending: false,
+            frame_complete: false,
+            scanline_sprites: Vec::with_capacity(SPRITES_PER_SCANLINE),
+            io_latch: 0,
+            region,
+            dot_remainder: 0,
+            accurate_sprite_overflow: false,
+        }
+    }
+
+    // The reset line only clears part of the state, PPUSTATUS, OAMADDR, PPUADDR and VRAM are kept
+    pub fn reset(&mut self) {
+        info!("PPU is resetting");
+        self.ppu_ctrl = PPUCtrl::new();
+        self.ppu_mask = PPUMask::new();
+        self.internal_read_buffer = 0;
+        self.internal_w_register = true;
+        // PPUSCROLL is cleared, v holds PPUADDR so only t and fine X go
+        self.t = 0;
+        self.x = 0;
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        save_state::encode(&PpuSaveState {
+            ppu_addr: self.ppu_addr.read(),
+            ppu_ctrl: self.ppu_ctrl.bits(),
+            ppu_mask: self.ppu_mask.bits(),
+            ppu_status: self.ppu_status.read(),
+            internal_read_buffer: self.internal_read_buffer,
+            internal_w_register: self.internal_w_register,
+            v: self.v,
+            t: self.t,
+            x: self.x,
+            oam: self.oam.to_vec(),
+            oam_addr: self.oam_addr,
+            scanline: self.scanline,
+            dot: self.dot,
+            frame_count: self.frame_count,
+            nmi_pending: self.nmi_pending,
+            scanline_sprites: self.scanline_sprites.clone(),
+            io_latch: self.io_latch,
+        })
+    }
+
+    // A malformed blob leaves the PPU untouched
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        let saved: PpuSaveState = save_state::decode(bytes)?;
+        self.ppu_addr.high_addr = (saved.ppu_addr >> 8) as u8;
+        self.ppu_addr.low_addr = saved.ppu_addr as u8;
+        self.ppu_ctrl.write(saved.ppu_ctrl);
+        self.ppu_mask.write(saved.ppu_mask);
+        self.ppu_status = PPUStatus::from_bits_truncate(saved.ppu_status);
+        self.internal_read_buffer = saved.internal_read_buffer;
+        self.internal_w_register = saved.internal_w_register;
+        self.v = saved.v;
+        self.t = saved.t;
+        self.x = saved.x;
+        for (byte, saved_byte) in self.oam.iter_mut().zip(saved.oam) {
+            *byte = saved_byte;
         }
+        self.oam_addr = saved.oam_addr;
+        self.scanline = saved.scanline;
+        self.dot = saved.dot;
+        self.frame_count = saved.frame_count;
+        self.nmi_pending = saved.nmi_pending;
+        self.scanline_sprites = saved.scanline_sprites;
+        self.io_latch = saved.io_latch;
+        Ok(())
     }
 
     // Read operations -----------------------------------------------------------------------------
 
+    // Reading the status acknowledges vblank and resets the PPUSCROLL/PPUADDR write latch
     fn read_from_ppu_status(&mut self) -> u8 {
-        todo!()
+        let status = self.ppu_status.read();
+        self.ppu_status.remove(PPUStatus::VBLANK);
+        self.internal_w_register = true;
+        status
+    }
+
+    pub fn nmi_enabled(&self) -> bool {
+        self.ppu_ctrl.contains(PPUCtrl::NMI)
+    }
+
+    pub fn set_vblank(&mut self, value: bool) {
+        self.ppu_status.set(PPUStatus::VBLANK, value);
+    }
+
+    pub fn set_sprite_zero_hit(&mut self, value: bool) {
+        self.ppu_status.set(PPUStatus::SPRITE_ZERO_HIT, value);
     }
 
+    pub fn set_sprite_overflow(&mut self, value: bool) {
+        self.ppu_status.set(PPUStatus::SPRITE_OVERFLOW, value);
+    }
+
+    // Reads do not increment OAMADDR, unlike writes
     fn read_from_oam_data(&mut self) -> u8 {
-        todo!()
+        self.oam[self.oam_addr as usize]
     }
 
     fn read_from_ppu_data(&mut self) -> u8 {
@@ -45,6 +250,14 @@ impl PPU {
         debug!("PPU read from bus at address {:#06X}", addr);
         self.increment_addr();
 
+        // Palette reads skip the buffer, which is filled from the nametable underneath instead
+        if addr >= PALETTE_RAM_START_ADDRESS {
+            let result = self.ppu_data.read(addr);
+            let nametable = self.ppu_data.read(addr - PALETTE_NAMETABLE_OFFSET);
+            self.set_internal_read_buffer(nametable);
+            return result;
+        }
+
         let current_buffer = self.internal_read_buffer;
         let result = self.ppu_data.read(addr);
         self.set_internal_read_buffer(result);
@@ -54,26 +267,60 @@ impl PPU {
     // Write operations ----------------------------------------------------------------------------
 
     fn write_to_ppu_ctrl(&mut self, data: u8) {
+        // Enabling NMI during vblank raises it right away
+        let nmi_was_enabled = self.nmi_enabled();
         self.ppu_ctrl.write(data);
+        if !nmi_was_enabled && self.nmi_enabled() && self.ppu_status.contains(PPUStatus::VBLANK) {
+            self.nmi_pending = true;
+        }
+        self.t = (self.t & !NAMETABLE_MASK) | (((data & 0b11) as u16) << 10);
+    }
+
+    fn write_to_ppu_mask(&mut self, data: u8) {
+        self.ppu_mask.write(data);
+    }
+
+    fn write_to_oam_addr(&mut self, data: u8) {
+        self.oam_addr = data;
     }
 
-    fn write_to_ppu_mask(&mut self, _data: u8) {
-        todo!()
+    fn write_to_oam_data(&mut self, data: u8) {
+        self.oam[self.oam_addr as usize] = data;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
     }
 
-    fn write_to_oam_addr(&mut self, _data: u8) {
-        todo!()
+    // OAM DMA copies a whole CPU page through OAMDATA, starting at the current OAMADDR
+    pub fn write_oam_dma(&mut self, page: &[u8; OAM_SIZE]) {
+        for data in page {
+            self.write_to_oam_data(*data);
+        }
     }
 
-    fn write_to_oam_data(&mut self, _data: u8) {
-        todo!()
+    pub fn oam(&self) -> &[u8; OAM_SIZE] {
+        &self.oam
     }
 
-    fn write_to_ppu_scroll(&mut self, _data: u8) {
-        todo!()
+    fn write_to_ppu_scroll(&mut self, data: u8) {
+        let data = data as u16;
+        if self.internal_w_register {
+            self.t = (self.t & !COARSE_X_MASK) | (data >> 3);
+            self.x = (data & 0b111) as u8;
+        } else {
+            self.t = (self.t & !(FINE_Y_MASK | COARSE_Y_MASK))
+                | ((data & 0b111) << 12)
+                | ((data & 0xF8) << 2);
+        }
+        self.invert_w_register();
     }
 
+    // The second write copies t into v, the address used by PPUDATA
     fn write_to_ppu_addr(&mut self, data: u8) {
+        if self.internal_w_register {
+            self.t = (self.t & LOOPY_LOW_BYTE_MASK) | (((data as u16) << 8) & LOOPY_HIGH_BYTE_MASK);
+        } else {
+            self.t = (self.t & !LOOPY_LOW_BYTE_MASK) | data as u16;
+            self.v = self.t;
+        }
         self.ppu_addr.write(data, self.internal_w_register);
         self.invert_w_register();
     }
@@ -85,12 +332,372 @@ impl PPU {
             addr, data
         );
         self.ppu_data.write(addr, data);
+        self.increment_addr();
+    }
+
+    // Timing --------------------------------------------------------------------------------------
+
+    // Advances the PPU by the given number of dots, returns whether an NMI was raised
+    pub fn tick(&mut self, dots: u32) -> bool {
+        let mut nmi = false;
+        for _ in 0..dots {
+            nmi |= self.tick_dot();
+        }
+        nmi
+    }
+
+    // Advances the PPU alongside the given number of CPU cycles at the region's clock ratio
+    pub fn tick_cpu_cycles(&mut self, cpu_cycles: u32) -> bool {
+        let (dots, per_cpu_cycles) = self.region.ppu_dots_per_cpu_cycles();
+        let scaled = cpu_cycles * dots + self.dot_remainder;
+        self.dot_remainder = scaled % per_cpu_cycles;
+        self.tick(scaled / per_cpu_cycles)
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.dot_remainder = 0;
+    }
+
+    fn tick_dot(&mut self) -> bool {
+        self.dot += 1;
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline = (self.scanline + 1) % self.region.scanlines_per_frame();
+        }
+
+        // Visible pixels are output on dots 1 to 256
+        let y = self.scanline as usize;
+        if y < SCREEN_HEIGHT && (1..=SCREEN_WIDTH as u16).contains(&self.dot) {
+            if self.dot == 1 {
+                self.scanline_sprites = self.evaluate_sprites(y);
+            }
+            self.render_pixel(self.dot as usize - 1, y);
+            if y == SCREEN_HEIGHT - 1 && self.dot == SCREEN_WIDTH as u16 {
+                self.frame_complete = true;
+            }
+        }
+
+        if self.dot != FLAG_UPDATE_DOT {
+            return false;
+        }
+
+        match self.scanline {
+            VBLANK_SCANLINE => {
+                self.ppu_status.insert(PPUStatus::VBLANK);
+                self.frame_count += 1;
+                if self.nmi_enabled() {
+                    self.nmi_pending = true;
+                    return true;
+                }
+            }
+            // The pre-render scanline is the last one of the frame
+            scanline if scanline == self.region.scanlines_per_frame() - 1 => {
+                self.ppu_status.remove(
+                    PPUStatus::VBLANK | PPUStatus::SPRITE_ZERO_HIT | PPUStatus::SPRITE_OVERFLOW,
+                );
+            }
+            _ => {}
+        }
+        false
+    }
+
+    // Hands a raised NMI over to the CPU, once
+    pub fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi_pending)
+    }
+
+    // Whether a whole picture is ready in the frame buffer since the last call
+    pub fn take_frame_complete(&mut self) -> bool {
+        std::mem::take(&mut self.frame_complete)
+    }
+
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    // Frames that reached vblank since power on
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // Rendering -----------------------------------------------------------------------------------
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    pub fn ppu_mask(&self) -> &PPUMask {
+        &self.ppu_mask
+    }
+
+    // Renders the visible scanlines from start to end inclusive, leaving the others untouched
+    pub fn render_scanline_range(&mut self, start: usize, end: usize) {
+        let end = end.min(SCREEN_HEIGHT - 1);
+        for y in start..=end {
+            self.render_scanline(y as u16);
+        }
+    }
+
+    pub fn render_frame(&mut self) {
+        self.render_scanline_range(0, SCREEN_HEIGHT - 1);
+    }
+
+    // RGBA image of all 256 tiles of a pattern table, for CHR viewers
+    pub fn render_pattern_table(
+        &mut self,
+        table: u8,
+        palette: u8,
+    ) -> [u8; PATTERN_TABLE_VIEW_SIZE * PATTERN_TABLE_VIEW_SIZE * RGBA_BYTES_PER_PIXEL] {
+        let mut image =
+            [0; PATTERN_TABLE_VIEW_SIZE * PATTERN_TABLE_VIEW_SIZE * RGBA_BYTES_PER_PIXEL];
+        let colors: Vec<(u8, u8, u8)> = (0..4)
+            .map(|color_index| {
+                let color = self.palette_color(palette, color_index);
+                masked_color(color, &self.ppu_mask)
+            })
+            .collect();
+        let pattern_table_address = (table as u16 & 1) * PATTERN_TABLE_SIZE;
+
+        for tile in 0..(PATTERN_TABLE_VIEW_TILES * PATTERN_TABLE_VIEW_TILES) {
+            let tile_address = pattern_table_address + tile as u16 * PATTERN_TILE_SIZE;
+            let tile_x = (tile % PATTERN_TABLE_VIEW_TILES) * TILE_SIZE;
+            let tile_y = (tile / PATTERN_TABLE_VIEW_TILES) * TILE_SIZE;
+
+            for row in 0..TILE_SIZE {
+                // Both bit planes are read once per row instead of once per pixel
+                let low_plane = self.ppu_data.read(tile_address + row as u16);
+                let high_plane = self.ppu_data.read(tile_address + row as u16 + 8);
+
+                for column in 0..TILE_SIZE {
+                    let bit = 7 - column;
+                    let color_index = ((low_plane >> bit) & 1) | (((high_plane >> bit) & 1) << 1);
+                    let (red, green, blue) = colors[color_index as usize];
+
+                    let offset = ((tile_y + row) * PATTERN_TABLE_VIEW_SIZE + tile_x + column)
+                        * RGBA_BYTES_PER_PIXEL;
+                    image[offset..offset + RGBA_BYTES_PER_PIXEL]
+                        .copy_from_slice(&[red, green, blue, 0xFF]);
+                }
+            }
+        }
+
+        image
+    }
+
+    // Draws one visible scanline into the frame buffer, other scanlines are ignored
+    pub fn render_scanline(&mut self, scanline: u16) {
+        let y = scanline as usize;
+        if y >= SCREEN_HEIGHT {
+            return;
+        }
+
+        self.scanline_sprites = self.evaluate_sprites(y);
+        for x in 0..SCREEN_WIDTH {
+            self.render_pixel(x, y);
+        }
+    }
+
+    fn render_pixel(&mut self, x: usize, y: usize) {
+        let sprites = std::mem::take(&mut self.scanline_sprites);
+        let (background_palette, background_color_index) = self.background_pixel(x, y);
+        let sprite_pixel = self.sprite_pixel(&sprites, x, y);
+        self.scanline_sprites = sprites;
+
+        let (palette, color_index) = match sprite_pixel {
+            Some((index, sprite, sprite_color_index)) => {
+                if index == 0 && background_color_index != 0 {
+                    self.check_sprite_zero_hit(x);
+                }
+
+                if background_color_index != 0 && sprite.behind_background() {
+                    (background_palette, background_color_index)
+                } else {
+                    (sprite.palette(), sprite_color_index)
+                }
+            }
+            None => (background_palette, background_color_index),
+        };
+
+        let mut color = self.palette_color(palette, color_index);
+        if self.ppu_mask.grayscale() {
+            color &= GRAYSCALE_MASK;
+        }
+        let rgb = masked_color(color, &self.ppu_mask);
+        self.frame_buffer.set_pixel_rgb(x, y, color, rgb);
+    }
+
+    // Sprites covering the scanline in OAM order, only the first eight are drawn
+    fn evaluate_sprites(&mut self, y: usize) -> Vec<(usize, Sprite)> {
+        let height = self.ppu_ctrl.get_sprite_height();
+        // OAM stores the sprite's Y minus one
+        let in_range =
+            |y_byte: u8| (y_byte as usize + 1..y_byte as usize + 1 + height).contains(&y);
+        let mut sprites = Vec::with_capacity(SPRITES_PER_SCANLINE);
+
+        let mut index = 0;
+        while index < OAM_SPRITES && sprites.len() < SPRITES_PER_SCANLINE {
+            let offset = index * SPRITE_SIZE_IN_BYTES;
+            let sprite = Sprite::from_bytes(&self.oam[offset..offset + SPRITE_SIZE_IN_BYTES]);
+            if in_range(self.oam[offset]) {
+                sprites.push((index, sprite));
+            }
+            index += 1;
+        }
+
+        let overflow = if self.accurate_sprite_overflow {
+            // Once eight sprites are found the PPU also steps the byte index along with the
+            // sprite index, comparing tile, attribute and X bytes as if they were Y
+            // See https://www.nesdev.org/wiki/PPU_sprite_evaluation#Sprite_overflow_bug
+            (index..OAM_SPRITES).enumerate().any(|(step, sprite)| {
+                let byte = step % SPRITE_SIZE_IN_BYTES;
+                in_range(self.oam[sprite * SPRITE_SIZE_IN_BYTES + byte])
+            })
+        } else {
+            (index..OAM_SPRITES).any(|sprite| in_range(self.oam[sprite * SPRITE_SIZE_IN_BYTES]))
+        };
+        if overflow {
+            self.ppu_status.insert(PPUStatus::SPRITE_OVERFLOW);
+        }
+
+        sprites
+    }
+
+    pub fn set_accurate_sprite_overflow(&mut self, enabled: bool) {
+        self.accurate_sprite_overflow = enabled;
+    }
+
+    // First opaque sprite pixel, sprites earlier in OAM are drawn on top
+    fn sprite_pixel(
+        &mut self,
+        sprites: &[(usize, Sprite)],
+        x: usize,
+        y: usize,
+    ) -> Option<(usize, Sprite, u8)> {
+        if !self.ppu_mask.show_sprites() || (x < TILE_SIZE && !self.ppu_mask.show_sprites_left()) {
+            return None;
+        }
+
+        for (index, sprite) in sprites {
+            if !(sprite.left()..sprite.left() + TILE_SIZE).contains(&x) {
+                continue;
+            }
+
+            let color_index = self.sprite_color_index(sprite, x - sprite.left(), y - sprite.top());
+            if color_index != 0 {
+                return Some((*index, *sprite, color_index));
+            }
+        }
+
+        None
+    }
+
+    fn sprite_color_index(&mut self, sprite: &Sprite, column: usize, row: usize) -> u8 {
+        let height = self.ppu_ctrl.get_sprite_height();
+        let row = if sprite.flip_vertical() {
+            height - 1 - row
+        } else {
+            row
+        };
+        let column = if sprite.flip_horizontal() {
+            TILE_SIZE - 1 - column
+        } else {
+            column
+        };
+
+        // 8x16 sprites pick their pattern table with bit 0 of the tile number
+        let (pattern_address, tile) = if height == TILE_SIZE {
+            (
+                self.ppu_ctrl.get_sprite_pattern_address(),
+                sprite.tile as u16,
+            )
+        } else {
+            let pattern_address = (sprite.tile as u16 & 1) * PATTERN_TABLE_SIZE;
+            let tile = (sprite.tile & 0xFE) as u16 + (row / TILE_SIZE) as u16;
+            (pattern_address, tile)
+        };
+
+        self.pattern_color_index(pattern_address, tile, row % TILE_SIZE, column)
+    }
+
+    // Sprite 0 hit needs both layers enabled and never happens on the last dot
+    fn check_sprite_zero_hit(&mut self, x: usize) {
+        // Either leftmost 8 pixel mask hides the overlap there
+        let clipped = x < TILE_SIZE
+            && !(self.ppu_mask.show_background_left() && self.ppu_mask.show_sprites_left());
+        if x != SPRITE_ZERO_HIT_EXCLUDED_X && !clipped && self.ppu_mask.show_background() {
+            self.ppu_status.insert(PPUStatus::SPRITE_ZERO_HIT);
+        }
+    }
+
+    // Two bit color of a pattern table tile pixel, see https://www.nesdev.org/wiki/PPU_pattern_tables
+    fn pattern_color_index(
+        &mut self,
+        pattern_table_address: u16,
+        tile: u16,
+        row: usize,
+        column: usize,
+    ) -> u8 {
+        let pattern_address = pattern_table_address + tile * PATTERN_TILE_SIZE + row as u16;
+        let low_plane = self.ppu_data.read(pattern_address);
+        let high_plane = self.ppu_data.read(pattern_address + 8);
+        let bit = 7 - column;
+        ((low_plane >> bit) & 1) | (((high_plane >> bit) & 1) << 1)
+    }
+
+    // Palettes 0-3 are used by the background, 4-7 by sprites
+    fn palette_color(&mut self, palette: u8, color_index: u8) -> u8 {
+        // Color 0 of every palette is the universal backdrop color
+        let palette_address = if color_index == 0 {
+            PALETTE_RAM_START_ADDRESS
+        } else {
+            PALETTE_RAM_START_ADDRESS + (palette * 4 + color_index) as u16
+        };
+        self.ppu_data.read(palette_address) & SYSTEM_PALETTE_MASK
+    }
+
+    // Palette and color index of the base nametable pixel, see https://www.nesdev.org/wiki/PPU_nametables
+    fn background_pixel(&mut self, x: usize, y: usize) -> (u8, u8) {
+        let nametable_address = self.ppu_ctrl.get_base_nametable_address();
+        let tile_column = x / TILE_SIZE;
+        let tile_row = y / TILE_SIZE;
+
+        let tile_address =
+            nametable_address + (tile_row * NAMETABLE_WIDTH_IN_TILES + tile_column) as u16;
+        let tile = self.ppu_data.read(tile_address) as u16;
+
+        let pattern_address = self.ppu_ctrl.get_background_pattern_address();
+        let color_index =
+            self.pattern_color_index(pattern_address, tile, y % TILE_SIZE, x % TILE_SIZE);
+
+        let palette = self.attribute_palette(nametable_address, tile_column, tile_row);
+
+        (palette, color_index)
+    }
+
+    // Background palette of a tile, see https://www.nesdev.org/wiki/PPU_attribute_tables
+    // Every attribute byte covers 4x4 tiles, two bits per 2x2 tile quadrant
+    pub fn attribute_palette(&mut self, nametable_base: u16, tile_x: usize, tile_y: usize) -> u8 {
+        let attribute_address =
+            nametable_base + ATTRIBUTE_TABLE_OFFSET + ((tile_y / 4) * 8 + tile_x / 4) as u16;
+        let attribute = self.ppu_data.read(attribute_address);
+        let shift = ((tile_y % 4) / 2) * 4 + ((tile_x % 4) / 2) * 2;
+        (attribute >> shift) & 0b11
     }
 
     // Utility functions ---------------------------------------------------------------------------
 
     fn increment_addr(&mut self) {
         self.ppu_addr.increment(self.ppu_ctrl.get_vram_increment());
+        self.v = self.ppu_addr.read();
     }
 
     fn invert_w_register(&mut self) {
@@ -98,13 +705,16 @@ impl PPU {
     }
 
     fn mirror_write(&mut self, address: u16, data: u8) {
-        let mirrored_address = address & 0x2007;
-        self.write(mirrored_address, data);
+        self.write(mirrored_register(address), data);
     }
 
     fn mirror_read(&mut self, address: u16) -> u8 {
-        let mirrored_address = address & 0x2007;
-        self.read(mirrored_address)
+        self.read(mirrored_register(address))
+    }
+
+    // Puts the shared $2005/$2006 write latch back to its first-write state, for tests and debuggers
+    pub fn reset_write_latch(&mut self) {
+        self.internal_w_register = true;
     }
 
     fn set_internal_read_buffer(&mut self, data: u8) {
@@ -112,18 +722,30 @@ impl PPU {
     }
 }
 
+// Only the three low address lines reach the PPU
+fn mirrored_register(address: u16) -> u16 {
+    REGISTERS_START_ADDRESS + (address & REGISTER_SELECT_MASK)
+}
+
 impl Addressable for PPU {
     fn read(&mut self, address: u16) -> u8 {
         debug!("PPU read at address {:#06X}", address);
-        match address {
-            0x2002 => self.read_from_ppu_status(),
+        let data = match address {
+            0x2002 => {
+                let status = self.read_from_ppu_status() & PPU_STATUS_FLAGS_MASK;
+                status | (self.io_latch & !PPU_STATUS_FLAGS_MASK)
+            }
             0x2004 => self.read_from_oam_data(),
             0x2007 => self.read_from_ppu_data(),
-            MIRRORS_START_ADDRESS..=MIRRORS_END_ADDRESS => self.mirror_read(address),
+            // Write-only registers
+            0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => self.io_latch,
+            MIRRORS_START_ADDRESS..=MIRRORS_END_ADDRESS => return self.mirror_read(address),
             _ => {
                 panic!("PPU read at address {:#06X} not implemented", address);
             }
-        }
+        };
+        self.io_latch = data;
+        data
     }
 
     fn write(&mut self, address: u16, data: u8) {
@@ -131,18 +753,17 @@ impl Addressable for PPU {
             "PPU write at address {:#06X} with data {:#04X}",
             address, data
         );
+        self.io_latch = data;
         match address {
             0x2000 => self.write_to_ppu_ctrl(data),
             0x2001 => self.write_to_ppu_mask(data),
+            0x2002 => debug!("Ignoring write to read-only PPUSTATUS"),
             0x2003 => self.write_to_oam_addr(data),
             0x2004 => self.write_to_oam_data(data),
             0x2005 => self.write_to_ppu_scroll(data),
             0x2006 => self.write_to_ppu_addr(data),
             0x2007 => self.write_to_ppu_data(data),
             MIRRORS_START_ADDRESS..=MIRRORS_END_ADDRESS => self.mirror_write(address, data),
-            0x4014 => {
-                todo!()
-            }
             _ => {
                 panic!("PPU write at address {:#06X} not implemented", address);
             }
@@ -161,7 +782,14 @@ impl Debug for PPU {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::bus::Bus;
+    use crate::addressing::AddressRange;
+    use crate::bus::{Bus, BusLike};
+    use crate::memory::Memory;
+    use crate::ppu::frame_buffer::frame_buffer::RGB_BYTES_PER_PIXEL;
+    use crate::ppu::palette_ram::palette_ram::{PaletteRAM, SYSTEM_PALETTE};
+    use crate::ppu::vram::vram::VRAM;
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     fn setup_ppu() -> PPU {
         let bus = Bus::new();
@@ -176,6 +804,20 @@ mod tests {
         assert!(ppu.internal_w_register);
     }
 
+    #[test]
+    fn ppu_power_up_state() {
+        let ppu = setup_ppu();
+
+        assert_eq!(ppu.ppu_ctrl.read(), 0);
+        assert_eq!(ppu.ppu_mask.bits(), 0);
+        assert_eq!(ppu.ppu_status.read(), 0);
+        assert_eq!(ppu.ppu_addr.read(), 0);
+        assert_eq!(ppu.oam_addr, 0);
+        assert_eq!((ppu.v, ppu.t, ppu.x), (0, 0, 0));
+        assert_eq!(ppu.internal_read_buffer, 0);
+        assert!(ppu.internal_w_register);
+    }
+
     #[test]
     fn ppu_write_to_ppu_ctrl() {
         let mut ppu = setup_ppu();
@@ -185,6 +827,33 @@ mod tests {
         assert_eq!(ppu.ppu_ctrl.read(), 0b10000001);
     }
 
+    #[test]
+    fn ppu_write_to_ppu_mask_rendering_flags() {
+        let mut ppu = setup_ppu();
+
+        ppu.write_to_ppu_mask(0b00011011);
+
+        assert!(ppu.ppu_mask.grayscale());
+        assert!(ppu.ppu_mask.show_background_left());
+        assert!(!ppu.ppu_mask.show_sprites_left());
+        assert!(ppu.ppu_mask.show_background());
+        assert!(ppu.ppu_mask.show_sprites());
+    }
+
+    #[test]
+    fn ppu_write_to_ppu_mask_color_emphasis() {
+        let mut ppu = setup_ppu();
+
+        ppu.write_to_ppu_mask(0b10100100);
+
+        assert!(!ppu.ppu_mask.grayscale());
+        assert!(ppu.ppu_mask.show_sprites_left());
+        assert!(!ppu.ppu_mask.show_background());
+        assert!(ppu.ppu_mask.emphasize_red());
+        assert!(!ppu.ppu_mask.emphasize_green());
+        assert!(ppu.ppu_mask.emphasize_blue());
+    }
+
     #[test]
     fn ppu_write_to_ppu_addr() {
         let mut ppu = setup_ppu();
@@ -196,6 +865,39 @@ mod tests {
         assert_eq!(ppu.ppu_addr.read(), 0x2137);
     }
 
+    #[test]
+    fn ppu_addr_increment_by_one_wraps_at_0x3fff() {
+        let mut ppu = setup_ppu();
+        ppu.ppu_addr.write(0x3F, true);
+        ppu.ppu_addr.write(0xFF, false);
+
+        ppu.ppu_addr.increment(1);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x0000);
+    }
+
+    #[test]
+    fn ppu_addr_increment_by_32_wraps_at_0x3fff() {
+        let mut ppu = setup_ppu();
+        ppu.ppu_addr.write(0x3F, true);
+        ppu.ppu_addr.write(0xE0, false);
+
+        ppu.ppu_addr.increment(32);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x0000);
+    }
+
+    #[test]
+    fn ppu_addr_write_mirrors_above_0x3fff() {
+        let mut ppu = setup_ppu();
+
+        ppu.write_to_ppu_addr(0x7F);
+        ppu.write_to_ppu_addr(0x12);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x3F12);
+        assert_eq!(ppu.ppu_addr.high_addr, 0x3F);
+    }
+
     #[test]
     fn ppu_read_from_bus_returns_internal_buffer() {
         let mut ppu = setup_ppu();
@@ -209,6 +911,56 @@ mod tests {
         assert_eq!(result, internal_buffer);
     }
 
+    #[test]
+    fn ppu_palette_read_is_not_buffered() {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        let mut ppu = PPU::new(bus);
+        ppu.ppu_data.write(0x3F01, 0x22);
+        ppu.ppu_data.write(0x2F01, 0x11);
+        ppu.set_internal_read_buffer(0x69);
+
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0x01);
+
+        assert_eq!(ppu.read_from_ppu_data(), 0x22);
+        assert_eq!(ppu.internal_read_buffer, 0x11);
+    }
+
+    #[test]
+    fn ppu_nametable_read_after_palette_read_is_still_delayed() {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        let mut ppu = PPU::new(bus);
+        ppu.ppu_data.write(0x3F00, 0x22);
+        ppu.ppu_data.write(0x2F00, 0x11);
+        ppu.ppu_data.write(0x2000, 0x33);
+
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0x00);
+        ppu.read_from_ppu_data();
+        ppu.write_to_ppu_addr(0x20);
+        ppu.write_to_ppu_addr(0x00);
+
+        assert_eq!(ppu.read_from_ppu_data(), 0x11);
+        assert_eq!(ppu.read_from_ppu_data(), 0x33);
+    }
+
+    #[test]
+    fn ppu_reset_write_latch_starts_a_clean_ppu_addr_sequence() {
+        let mut ppu = setup_ppu();
+        // A stray first write leaves the latch waiting for the low byte
+        ppu.write(0x2006, 0x3F);
+
+        ppu.reset_write_latch();
+        ppu.write(0x2006, 0x21);
+        ppu.write(0x2006, 0x37);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x2137);
+        assert_eq!(ppu.v, 0x2137);
+        assert!(ppu.internal_w_register);
+    }
+
     #[test]
     fn ppu_increment_addr_by_one_on_default_ppu_ctrl_mode() {
         let mut ppu = setup_ppu();
@@ -235,7 +987,59 @@ mod tests {
     #[test]
     fn ppu_mirror_write_to_ppu_addr() {
         let mut ppu = setup_ppu();
-        assert_eq!(ppu.ppu_addr.read(), 0x0000);
+
+        ppu.write(0x3456, 0x21);
+        ppu.write(0x3456, 0x08);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x2108);
+    }
+
+    #[test]
+    fn ppu_mirrored_register_lands_on_base_register() {
+        assert_eq!(mirrored_register(0x2008), 0x2000);
+        assert_eq!(mirrored_register(0x3456), 0x2006);
+        assert_eq!(mirrored_register(0x3FFF), 0x2007);
+    }
+
+    #[test]
+    fn ppu_mirror_write_to_ppu_ctrl() {
+        let mut ppu = setup_ppu();
+
+        ppu.write(0x2008, 0x80);
+
+        assert!(ppu.nmi_enabled());
+    }
+
+    #[test]
+    fn ppu_mirror_read_from_ppu_data() {
+        let mut ppu = setup_ppu();
+        ppu.set_internal_read_buffer(0x12);
+
+        assert_eq!(ppu.read(0x3FFF), 0x12);
+    }
+
+    #[test]
+    fn ppu_data_writes_advance_address_and_wrap() {
+        let memory = Rc::new(RefCell::new(Memory::new(0x4000)));
+        let mut bus = Bus::new();
+        bus.register(memory.clone(), AddressRange::new(0x0000, 0x3FFF));
+        let mut ppu = PPU::new(bus);
+
+        ppu.write(0x2006, 0x21);
+        ppu.write(0x2006, 0x00);
+        ppu.write(0x2007, 0x11);
+        ppu.write(0x2007, 0x22);
+        ppu.write(0x2006, 0x3F);
+        ppu.write(0x2006, 0xFF);
+        ppu.write(0x2007, 0x33);
+        ppu.write(0x2007, 0x44);
+
+        let mut memory = memory.borrow_mut();
+        assert_eq!(memory.read(0x2100), 0x11);
+        assert_eq!(memory.read(0x2101), 0x22);
+        assert_eq!(memory.read(0x3FFF), 0x33);
+        assert_eq!(memory.read(0x0000), 0x44);
+        assert_eq!(ppu.ppu_addr.read(), 0x0001);
     }
 
     #[test]
@@ -250,10 +1054,10 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = "PPU read at address 0x2003 not implemented")]
+    #[should_panic(expected = "PPU read at address 0x4001 not implemented")]
     fn ppu_read_unimplemented_address() {
         let mut ppu = setup_ppu();
-        ppu.read(0x2003);
+        ppu.read(0x4001);
     }
 
     #[test]
@@ -262,4 +1066,788 @@ mod tests {
         let mut ppu = setup_ppu();
         ppu.write(0x4001, 0xFF);
     }
+
+    #[test]
+    fn ppu_reset_keeps_address_and_clears_ctrl() {
+        let mut ppu = setup_ppu();
+        ppu.write_to_ppu_ctrl(0b10000001);
+        ppu.write_to_ppu_addr(0x21);
+        ppu.set_internal_read_buffer(0x42);
+
+        ppu.reset();
+
+        assert_eq!(ppu.ppu_ctrl.read(), 0);
+        assert_eq!(ppu.ppu_addr.read(), 0x2100);
+        assert_eq!(ppu.internal_read_buffer, 0);
+        assert!(ppu.internal_w_register);
+    }
+
+    #[test]
+    fn ppu_reset_state() {
+        let mut ppu = setup_ppu();
+        ppu.write_to_ppu_mask(0b0001_1110);
+        ppu.write_to_ppu_scroll(0x7D);
+        ppu.write_to_ppu_scroll(0x5E);
+        ppu.write_to_ppu_scroll(0x12);
+        ppu.write_to_oam_addr(0x20);
+        ppu.set_vblank(true);
+
+        ppu.reset();
+
+        assert_eq!(ppu.ppu_ctrl.read(), 0);
+        assert_eq!(ppu.ppu_mask.bits(), 0);
+        assert_eq!((ppu.t, ppu.x), (0, 0));
+        assert_eq!(ppu.internal_read_buffer, 0);
+        assert!(ppu.internal_w_register);
+        // Left alone by the reset line
+        assert_eq!(ppu.oam_addr, 0x20);
+        assert!(ppu.ppu_status.contains(PPUStatus::VBLANK));
+    }
+
+    // Every tile of the nametable is tile 0 with all pixels using color 3 of palette 0
+    fn setup_ppu_with_solid_background(color: u8) -> PPU {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        for address in 0x0000..0x0010 {
+            bus.write(address, 0xFF);
+        }
+        bus.write(PALETTE_RAM_START_ADDRESS + 3, color);
+        PPU::new(bus)
+    }
+
+    #[test]
+    fn ppu_save_state_round_trips_with_vram_and_palette() {
+        let vram = Rc::new(RefCell::new(VRAM::new()));
+        let palette_ram = Rc::new(RefCell::new(PaletteRAM::new()));
+        let mut bus = Bus::new();
+        bus.register(vram.clone(), AddressRange::new(0x2000, 0x3EFF));
+        bus.register(palette_ram.clone(), AddressRange::new(0x3F00, 0x3FFF));
+        let mut ppu = PPU::new(bus);
+
+        for (address, data) in [
+            (0x2042, 0x11),
+            (0x2BFF, 0x22),
+            (0x3F01, 0x16),
+            (0x3F1D, 0x2A),
+        ] {
+            ppu.write(0x2006, (address >> 8) as u8);
+            ppu.write(0x2006, address as u8);
+            ppu.write(0x2007, data);
+        }
+        let mut page = [0; OAM_SIZE];
+        page[5] = 0x99;
+        ppu.write_oam_dma(&page);
+        // Leave the scroll latches half way through a write pair
+        ppu.write(0x2005, 0x7D);
+        ppu.set_internal_read_buffer(0x5A);
+        ppu.tick(1000);
+
+        let ppu_state = ppu.save_state();
+        let vram_state = vram.borrow().save_state();
+        let palette_state = palette_ram.borrow().save_state();
+        let (v, t, x, w) = (ppu.v, ppu.t, ppu.x, ppu.internal_w_register);
+
+        vram.borrow_mut()
+            .load_state(&VRAM::new().save_state())
+            .unwrap();
+        palette_ram
+            .borrow_mut()
+            .load_state(&PaletteRAM::new().save_state())
+            .unwrap();
+        ppu.reset_write_latch();
+        ppu.write(0x2006, 0x00);
+        ppu.write(0x2006, 0x00);
+        ppu.write_oam_dma(&[0; OAM_SIZE]);
+        ppu.tick(1000);
+        assert_eq!(vram.borrow_mut().read(0x2042), 0x00);
+
+        ppu.load_state(&ppu_state).unwrap();
+        vram.borrow_mut().load_state(&vram_state).unwrap();
+        palette_ram.borrow_mut().load_state(&palette_state).unwrap();
+
+        assert_eq!(ppu.save_state(), ppu_state);
+        assert_eq!(vram.borrow().save_state(), vram_state);
+        assert_eq!(palette_ram.borrow().save_state(), palette_state);
+        assert_eq!((ppu.v, ppu.t, ppu.x, ppu.internal_w_register), (v, t, x, w));
+        assert!(!ppu.internal_w_register);
+        assert_eq!(ppu.internal_read_buffer, 0x5A);
+        assert_eq!(ppu.oam()[5], 0x99);
+        assert_eq!(vram.borrow_mut().read(0x2042), 0x11);
+        assert_eq!(vram.borrow_mut().read(0x2BFF), 0x22);
+        assert_eq!(palette_ram.borrow_mut().read(0x3F01), 0x16);
+        assert_eq!(palette_ram.borrow_mut().read(0x3F1D), 0x2A);
+    }
+
+    #[test]
+    fn ppu_render_pattern_table_uses_chosen_palette() {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        // Tile 0x11 of the second table: top row is color 1, the next one color 2, the rest color 3
+        let tile_address = PATTERN_TABLE_SIZE + 0x11 * PATTERN_TILE_SIZE;
+        bus.write(tile_address, 0xFF);
+        bus.write(tile_address + 9, 0xFF);
+        for row in 2..8 {
+            bus.write(tile_address + row, 0xFF);
+            bus.write(tile_address + row + 8, 0xFF);
+        }
+        bus.write(PALETTE_RAM_START_ADDRESS, 0x0F);
+        bus.write(PALETTE_RAM_START_ADDRESS + 9, 0x16);
+        bus.write(PALETTE_RAM_START_ADDRESS + 10, 0x2A);
+        bus.write(PALETTE_RAM_START_ADDRESS + 11, 0x30);
+        let mut ppu = PPU::new(bus);
+
+        let image = ppu.render_pattern_table(1, 2);
+
+        let pixel = |x: usize, y: usize| {
+            let offset = (y * PATTERN_TABLE_VIEW_SIZE + x) * RGBA_BYTES_PER_PIXEL;
+            &image[offset..offset + RGBA_BYTES_PER_PIXEL]
+        };
+        let rgba = |color: usize| {
+            let (red, green, blue) = SYSTEM_PALETTE[color];
+            [red, green, blue, 0xFF]
+        };
+        // Tile 0x11 sits in the second tile row and column
+        assert_eq!(pixel(8, 8), rgba(0x16));
+        assert_eq!(pixel(15, 9), rgba(0x2A));
+        assert_eq!(pixel(12, 15), rgba(0x30));
+        assert_eq!(pixel(0, 0), rgba(0x0F));
+    }
+
+    #[test]
+    fn ppu_render_scanline_range_updates_only_those_lines() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+
+        ppu.render_scanline_range(50, 60);
+
+        for y in 0..SCREEN_HEIGHT {
+            let expected = if (50..=60).contains(&y) { 0x16 } else { 0x00 };
+            assert!(
+                ppu.frame_buffer()
+                    .scanline(y)
+                    .iter()
+                    .all(|&pixel| pixel == expected),
+                "unexpected pixels on scanline {}",
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn ppu_render_scanline_range_clamps_to_visible_lines() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+
+        ppu.render_scanline_range(239, 300);
+
+        assert_eq!(ppu.frame_buffer().get_pixel(0, 239), 0x16);
+        assert_eq!(ppu.frame_buffer().get_pixel(0, 238), 0x00);
+    }
+
+    fn setup_ppu_with_vram() -> PPU {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        PPU::new(bus)
+    }
+
+    #[test]
+    fn ppu_attribute_palette_reads_each_quadrant() {
+        let mut ppu = setup_ppu_with_vram();
+        // Bottom right 3, bottom left 2, top right 1, top left 0
+        ppu.ppu_data
+            .write(0x2400 + ATTRIBUTE_TABLE_OFFSET, 0b11_10_01_00);
+
+        assert_eq!(ppu.attribute_palette(0x2400, 0, 0), 0);
+        assert_eq!(ppu.attribute_palette(0x2400, 3, 1), 1);
+        assert_eq!(ppu.attribute_palette(0x2400, 1, 2), 2);
+        assert_eq!(ppu.attribute_palette(0x2400, 2, 3), 3);
+    }
+
+    #[test]
+    fn ppu_attribute_palette_picks_byte_for_tile() {
+        let mut ppu = setup_ppu_with_vram();
+        // Tiles 4-7 of rows 8-11 use the second byte of the third attribute row
+        ppu.ppu_data
+            .write(0x2000 + ATTRIBUTE_TABLE_OFFSET + 17, 0b0000_0011);
+
+        assert_eq!(ppu.attribute_palette(0x2000, 4, 8), 3);
+        assert_eq!(ppu.attribute_palette(0x2000, 0, 8), 0);
+    }
+
+    #[test]
+    fn ppu_render_background_uses_attribute_palette() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+        // Bottom right quadrant of the first attribute byte uses palette 1
+        ppu.ppu_data
+            .write(0x2000 + ATTRIBUTE_TABLE_OFFSET, 0b0100_0000);
+        ppu.ppu_data.write(PALETTE_RAM_START_ADDRESS + 7, 0x2A);
+
+        ppu.render_frame();
+
+        assert_eq!(ppu.frame_buffer().get_pixel(0, 0), 0x16);
+        assert_eq!(ppu.frame_buffer().get_pixel(16, 16), 0x2A);
+    }
+
+    #[test]
+    fn ppu_render_scanline_produces_rgb() {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        // Tile 1 has columns using colors 0, 1, 2, 3, 0, 1, 2, 3 on every row
+        for row in 0..8 {
+            bus.write(0x0010 + row, 0b0101_0101);
+            bus.write(0x0018 + row, 0b0011_0011);
+        }
+        bus.write(0x2000, 0x01);
+        bus.write(PALETTE_RAM_START_ADDRESS, 0x0F);
+        bus.write(PALETTE_RAM_START_ADDRESS + 1, 0x01);
+        bus.write(PALETTE_RAM_START_ADDRESS + 2, 0x16);
+        bus.write(PALETTE_RAM_START_ADDRESS + 3, 0x30);
+        let mut ppu = PPU::new(bus);
+
+        ppu.render_scanline(3);
+
+        let frame_buffer = ppu.frame_buffer();
+        assert_eq!(frame_buffer.get_rgb(0, 3), (0x05, 0x05, 0x05));
+        assert_eq!(frame_buffer.get_rgb(1, 3), (0x00, 0x3D, 0xA6));
+        assert_eq!(frame_buffer.get_rgb(2, 3), (0xFF, 0x22, 0x00));
+        assert_eq!(frame_buffer.get_rgb(3, 3), (0xFF, 0xFF, 0xFF));
+        let offset = (3 * SCREEN_WIDTH + 3) * RGB_BYTES_PER_PIXEL;
+        assert_eq!(frame_buffer.rgb()[offset..offset + 3], [0xFF, 0xFF, 0xFF]);
+        // The second tile of the row is tile 0, which is blank
+        assert_eq!(frame_buffer.get_rgb(8, 3), (0x05, 0x05, 0x05));
+        assert_eq!(
+            frame_buffer.rgb().len(),
+            SCREEN_WIDTH * SCREEN_HEIGHT * RGB_BYTES_PER_PIXEL
+        );
+    }
+
+    #[test]
+    fn ppu_read_status_reports_flags() {
+        let mut ppu = setup_ppu();
+        ppu.set_vblank(true);
+        ppu.set_sprite_zero_hit(true);
+        ppu.set_sprite_overflow(true);
+
+        assert_eq!(ppu.read(0x2002), 0b11100000);
+    }
+
+    #[test]
+    fn ppu_read_status_clears_vblank() {
+        let mut ppu = setup_ppu();
+        ppu.set_vblank(true);
+        ppu.set_sprite_zero_hit(true);
+
+        assert_eq!(ppu.read(0x2002) & 0x80, 0x80);
+        assert_eq!(ppu.read(0x2002), 0b01000000);
+    }
+
+    #[test]
+    fn ppu_read_status_resets_write_latch() {
+        let mut ppu = setup_ppu();
+        ppu.write(0x2006, 0x21);
+
+        ppu.read(0x2002);
+        ppu.write(0x2006, 0x23);
+        ppu.write(0x2006, 0x45);
+
+        assert_eq!(ppu.ppu_addr.read(), 0x2345);
+    }
+
+    #[test]
+    fn ppu_oam_data_writes_increment_address() {
+        let mut ppu = setup_ppu();
+        ppu.write(0x2003, 0x10);
+
+        // Y, tile, attributes and X of sprite 4
+        for data in [0x40, 0x21, 0x03, 0x80] {
+            ppu.write(0x2004, data);
+        }
+
+        assert_eq!(ppu.oam_addr, 0x14);
+        assert_eq!(ppu.oam[0x10..0x14], [0x40, 0x21, 0x03, 0x80]);
+    }
+
+    #[test]
+    fn ppu_oam_data_reads_do_not_increment_address() {
+        let mut ppu = setup_ppu();
+        ppu.write(0x2003, 0x10);
+        for data in [0x40, 0x21, 0x03, 0x80] {
+            ppu.write(0x2004, data);
+        }
+
+        ppu.write(0x2003, 0x12);
+
+        assert_eq!(ppu.read(0x2004), 0x03);
+        assert_eq!(ppu.read(0x2004), 0x03);
+        ppu.write(0x2003, 0x13);
+        assert_eq!(ppu.read(0x2004), 0x80);
+    }
+
+    #[test]
+    fn ppu_oam_address_wraps_around() {
+        let mut ppu = setup_ppu();
+        ppu.write(0x2003, 0xFF);
+
+        ppu.write(0x2004, 0x11);
+        ppu.write(0x2004, 0x22);
+
+        assert_eq!(ppu.oam[0xFF], 0x11);
+        assert_eq!(ppu.oam[0x00], 0x22);
+    }
+
+    #[test]
+    fn ppu_scroll_writes_set_coarse_and_fine_scroll() {
+        let mut ppu = setup_ppu();
+
+        // X = 125 (coarse 15, fine 5), Y = 94 (coarse 11, fine 6)
+        ppu.write(0x2005, 0x7D);
+        assert_eq!(ppu.t, 0x000F);
+        assert_eq!(ppu.x, 0b101);
+        assert!(!ppu.internal_w_register);
+
+        ppu.write(0x2005, 0x5E);
+        assert_eq!(ppu.t, 0x616F);
+        assert_eq!(ppu.x, 0b101);
+        assert!(ppu.internal_w_register);
+    }
+
+    #[test]
+    fn ppu_ctrl_write_sets_nametable_in_t() {
+        let mut ppu = setup_ppu();
+        ppu.write(0x2005, 0x7D);
+        ppu.write(0x2005, 0x5E);
+
+        ppu.write(0x2000, 0b10);
+
+        assert_eq!(ppu.t, 0x696F);
+    }
+
+    #[test]
+    fn ppu_addr_second_write_copies_t_to_v() {
+        let mut ppu = setup_ppu();
+
+        ppu.write(0x2006, 0x3D);
+        assert_eq!(ppu.t, 0x3D00);
+        assert_eq!(ppu.v, 0x0000);
+
+        ppu.write(0x2006, 0xF0);
+        assert_eq!(ppu.t, 0x3DF0);
+        assert_eq!(ppu.v, 0x3DF0);
+    }
+
+    #[test]
+    fn ppu_write_only_registers_read_back_io_latch() {
+        let mut ppu = setup_ppu();
+
+        ppu.write(0x2005, 0x5A);
+
+        assert_eq!(ppu.read(0x2000), 0x5A);
+        assert_eq!(ppu.read(0x2006), 0x5A);
+        assert_eq!(ppu.read(0x200B), 0x5A);
+    }
+
+    #[test]
+    fn ppu_status_low_bits_come_from_io_latch() {
+        let mut ppu = setup_ppu();
+        ppu.set_vblank(true);
+
+        ppu.write(0x2002, 0xFF);
+
+        assert_eq!(ppu.read(0x2002), 0x80 | 0x1F);
+        // The status read itself refreshed the latch
+        assert_eq!(ppu.read(0x2000), 0x9F);
+    }
+
+    #[test]
+    fn ppu_scroll_and_addr_share_write_latch() {
+        let mut ppu = setup_ppu();
+
+        ppu.write(0x2005, 0x7D);
+        ppu.write(0x2006, 0x21);
+
+        // The $2006 write was the second one, so only the low byte of t changed
+        assert_eq!(ppu.t, 0x0021);
+        assert_eq!(ppu.v, 0x0021);
+        assert!(ppu.internal_w_register);
+    }
+
+    // Solid background with sprite 0 using the same solid tile, both layers enabled
+    fn setup_ppu_with_sprite_zero(sprite_x: u8, sprite_y: u8) -> PPU {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+        ppu.write_to_ppu_mask(0b0001_1110);
+        ppu.write_oam_dma(&[0; OAM_SIZE]);
+        ppu.oam[0] = sprite_y;
+        ppu.oam[3] = sprite_x;
+        ppu
+    }
+
+    #[test]
+    fn ppu_grayscale_renders_gray_column_of_the_color() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+        ppu.write_to_ppu_mask(0b0000_0001);
+
+        ppu.render_scanline(10);
+
+        assert_eq!(ppu.frame_buffer.get_pixel(20, 10), 0x10);
+        assert_eq!(ppu.frame_buffer.get_rgb(20, 10), SYSTEM_PALETTE[0x10]);
+    }
+
+    #[test]
+    fn ppu_red_emphasis_only_changes_rgb() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+        ppu.write_to_ppu_mask(0b0010_0000);
+
+        ppu.render_scanline(10);
+
+        // 0x16 is (0xFF, 0x22, 0x00), green is dimmed
+        assert_eq!(ppu.frame_buffer.get_pixel(20, 10), 0x16);
+        assert_eq!(ppu.frame_buffer.get_rgb(20, 10), (0xFF, 0x1B, 0x00));
+    }
+
+    #[test]
+    fn ppu_sprite_zero_hit_is_set_on_overlap() {
+        let mut ppu = setup_ppu_with_sprite_zero(100, 49);
+
+        ppu.render_scanline(49);
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0);
+
+        ppu.render_scanline(50);
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0x40);
+    }
+
+    #[test]
+    fn ppu_sprite_zero_hit_never_happens_at_last_dot() {
+        let mut ppu = setup_ppu_with_sprite_zero(248, 49);
+        // Only the rightmost column of sprite 0 is opaque
+        for row in 0..8 {
+            ppu.ppu_data.write(0x0010 + row, 0x01);
+            ppu.ppu_data.write(0x0018 + row, 0x00);
+        }
+        ppu.oam[1] = 0x01;
+
+        ppu.render_scanline(50);
+
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0);
+    }
+
+    // Blank background, tile 1 only has its top left pixel set, using color 1 of palette 4
+    fn setup_ppu_with_sprite(attributes: u8) -> PPU {
+        let mut bus = Bus::new();
+        bus.register(Memory::new(0x4000), AddressRange::new(0x0000, 0x3FFF));
+        bus.write(0x0010, 0b1000_0000);
+        bus.write(PALETTE_RAM_START_ADDRESS, 0x0F);
+        bus.write(PALETTE_RAM_START_ADDRESS + 0x11, 0x2A);
+        let mut ppu = PPU::new(bus);
+        ppu.write_to_ppu_mask(0b0001_1110);
+        ppu.write_oam_dma(&[0xFF; OAM_SIZE]);
+        ppu.oam[0..4].copy_from_slice(&[19, 0x01, attributes, 10]);
+        ppu
+    }
+
+    fn render_sprite(attributes: u8) -> PPU {
+        let mut ppu = setup_ppu_with_sprite(attributes);
+        ppu.render_scanline_range(20, 27);
+        ppu
+    }
+
+    #[test]
+    fn ppu_render_sprite_unflipped() {
+        let ppu = render_sprite(0b0000_0000);
+
+        assert_eq!(ppu.frame_buffer().get_pixel(10, 20), 0x2A);
+        assert_eq!(ppu.frame_buffer().get_pixel(17, 20), 0x0F);
+        assert_eq!(ppu.frame_buffer().get_pixel(10, 27), 0x0F);
+        assert_eq!(ppu.frame_buffer().get_pixel(10, 19), 0x00);
+    }
+
+    #[test]
+    fn ppu_render_sprite_flipped() {
+        let horizontal = render_sprite(0b0100_0000);
+        assert_eq!(horizontal.frame_buffer().get_pixel(10, 20), 0x0F);
+        assert_eq!(horizontal.frame_buffer().get_pixel(17, 20), 0x2A);
+
+        let vertical = render_sprite(0b1000_0000);
+        assert_eq!(vertical.frame_buffer().get_pixel(10, 20), 0x0F);
+        assert_eq!(vertical.frame_buffer().get_pixel(10, 27), 0x2A);
+
+        let both = render_sprite(0b1100_0000);
+        assert_eq!(both.frame_buffer().get_pixel(17, 27), 0x2A);
+    }
+
+    #[test]
+    fn ppu_render_sprite_behind_background() {
+        let mut ppu = setup_ppu_with_solid_background(0x16);
+        ppu.ppu_data.write(PALETTE_RAM_START_ADDRESS + 0x13, 0x2A);
+        ppu.write_to_ppu_mask(0b0001_1110);
+        ppu.write_oam_dma(&[0xFF; OAM_SIZE]);
+        ppu.oam[0..8].copy_from_slice(&[19, 0x00, 0b0000_0000, 10, 19, 0x00, 0b0010_0000, 30]);
+
+        ppu.render_scanline(20);
+
+        assert_eq!(ppu.frame_buffer().get_pixel(10, 20), 0x2A);
+        assert_eq!(ppu.frame_buffer().get_pixel(30, 20), 0x16);
+    }
+
+    #[test]
+    fn ppu_ninth_sprite_on_scanline_sets_overflow() {
+        let mut ppu = setup_ppu_with_sprite(0);
+        for sprite in 0..9 {
+            let offset = sprite * 4;
+            ppu.oam[offset..offset + 4].copy_from_slice(&[19, 0x01, 0, (sprite * 16) as u8]);
+        }
+
+        ppu.render_scanline(20);
+
+        assert_eq!(ppu.read_from_ppu_status() & 0x20, 0x20);
+        assert_eq!(ppu.frame_buffer().get_pixel(7 * 16, 20), 0x2A);
+        // Only eight sprites make it onto the scanline
+        assert_eq!(ppu.frame_buffer().get_pixel(8 * 16, 20), 0x0F);
+    }
+
+    // Eight sprites on scanline 20 followed by the given bytes for sprites 8 and 9
+    fn setup_ppu_with_full_scanline(sprite_8: [u8; 4], sprite_9: [u8; 4]) -> PPU {
+        let mut ppu = setup_ppu_with_sprite(0);
+        for sprite in 0..8 {
+            let offset = sprite * 4;
+            ppu.oam[offset..offset + 4].copy_from_slice(&[19, 0x01, 0, (sprite * 16) as u8]);
+        }
+        ppu.oam[32..36].copy_from_slice(&sprite_8);
+        ppu.oam[36..40].copy_from_slice(&sprite_9);
+        ppu
+    }
+
+    fn sprite_overflow(mut ppu: PPU, accurate: bool) -> bool {
+        ppu.set_accurate_sprite_overflow(accurate);
+        ppu.render_scanline(20);
+        ppu.read_from_ppu_status() & 0x20 != 0
+    }
+
+    #[test]
+    fn ppu_overflow_bug_reads_tile_byte_as_y() {
+        // Sprite 9 is off the scanline but its tile number looks like a Y on it
+        let sprite_8 = [0xFF, 0xFF, 0xFF, 0xFF];
+        let sprite_9 = [0xFF, 19, 0xFF, 0xFF];
+
+        assert!(!sprite_overflow(
+            setup_ppu_with_full_scanline(sprite_8, sprite_9),
+            false
+        ));
+        assert!(sprite_overflow(
+            setup_ppu_with_full_scanline(sprite_8, sprite_9),
+            true
+        ));
+    }
+
+    #[test]
+    fn ppu_overflow_bug_misses_ninth_sprite() {
+        // Sprite 9 is on the scanline, but the buggy search looks at its tile number
+        let sprite_8 = [0xFF, 0xFF, 0xFF, 0xFF];
+        let sprite_9 = [19, 0xFF, 0xFF, 0xFF];
+
+        assert!(sprite_overflow(
+            setup_ppu_with_full_scanline(sprite_8, sprite_9),
+            false
+        ));
+        assert!(!sprite_overflow(
+            setup_ppu_with_full_scanline(sprite_8, sprite_9),
+            true
+        ));
+    }
+
+    #[test]
+    fn ppu_overflow_bug_finds_ninth_sprite_right_after_eighth() {
+        let sprite_8 = [19, 0x01, 0, 0xF0];
+
+        assert!(sprite_overflow(
+            setup_ppu_with_full_scanline(sprite_8, [0xFF; 4]),
+            true
+        ));
+    }
+
+    #[test]
+    fn ppu_eight_sprites_on_scanline_do_not_overflow() {
+        let mut ppu = setup_ppu_with_sprite(0);
+        for sprite in 0..8 {
+            let offset = sprite * 4;
+            ppu.oam[offset..offset + 4].copy_from_slice(&[19, 0x01, 0, (sprite * 16) as u8]);
+        }
+
+        ppu.render_scanline(20);
+
+        assert_eq!(ppu.read_from_ppu_status() & 0x20, 0);
+    }
+
+    const NTSC_SCANLINES: u16 = Region::Ntsc.scanlines_per_frame();
+    const PRE_RENDER_SCANLINE: u16 = NTSC_SCANLINES - 1;
+    const DOTS_PER_FRAME: u32 = DOTS_PER_SCANLINE as u32 * NTSC_SCANLINES as u32;
+    const DOTS_TO_VBLANK: u32 = VBLANK_SCANLINE as u32 * DOTS_PER_SCANLINE as u32 + 1;
+
+    #[test]
+    fn ppu_tick_enters_vblank_at_scanline_241() {
+        let mut ppu = setup_ppu();
+
+        assert!(!ppu.tick(DOTS_TO_VBLANK - 1));
+        assert_eq!(ppu.ppu_status.read() & 0x80, 0);
+
+        ppu.tick(1);
+        assert_eq!((ppu.scanline(), ppu.dot()), (VBLANK_SCANLINE, 1));
+        assert_eq!(ppu.ppu_status.read() & 0x80, 0x80);
+        assert_eq!(ppu.frame_count(), 1);
+        // NMI is disabled
+        assert!(!ppu.take_nmi());
+    }
+
+    #[test]
+    fn ppu_tick_signals_nmi_once_per_frame() {
+        let mut ppu = setup_ppu();
+        ppu.write_to_ppu_ctrl(0x80);
+
+        assert!(ppu.tick(DOTS_TO_VBLANK));
+        assert!(ppu.take_nmi());
+        assert!(!ppu.take_nmi());
+
+        let mut nmi_count = 0;
+        for _ in 0..DOTS_PER_FRAME {
+            if ppu.tick(1) {
+                nmi_count += 1;
+            }
+        }
+        assert_eq!(nmi_count, 1);
+        assert_eq!(ppu.frame_count(), 2);
+    }
+
+    #[test]
+    fn ppu_frame_complete_is_raised_once_per_frame() {
+        let mut ppu = setup_ppu();
+
+        let mut completions = 0;
+        for _ in 0..DOTS_PER_FRAME {
+            ppu.tick(1);
+            if ppu.take_frame_complete() {
+                completions += 1;
+                // Right after the last visible pixel
+                assert_eq!((ppu.scanline(), ppu.dot()), (239, 256));
+            }
+        }
+
+        assert_eq!(completions, 1);
+        assert!(!ppu.take_frame_complete());
+    }
+
+    #[test]
+    fn ppu_pre_render_scanline_clears_flags() {
+        let mut ppu = setup_ppu();
+        ppu.tick(DOTS_TO_VBLANK);
+        ppu.set_sprite_zero_hit(true);
+        ppu.set_sprite_overflow(true);
+
+        ppu.tick((PRE_RENDER_SCANLINE - VBLANK_SCANLINE) as u32 * DOTS_PER_SCANLINE as u32);
+
+        assert_eq!(ppu.ppu_status.read(), 0);
+    }
+
+    #[test]
+    fn ppu_pre_render_flags_clear_on_flag_update_dot() {
+        for region in [Region::Ntsc, Region::Pal] {
+            let mut ppu = PPU::with_region(Bus::new(), region);
+            let pre_render_scanline = region.scanlines_per_frame() - 1;
+            ppu.tick(pre_render_scanline as u32 * DOTS_PER_SCANLINE as u32);
+            ppu.set_vblank(true);
+            ppu.set_sprite_zero_hit(true);
+            ppu.set_sprite_overflow(true);
+            assert_eq!((ppu.scanline(), ppu.dot()), (pre_render_scanline, 0));
+
+            ppu.tick(FLAG_UPDATE_DOT as u32);
+            assert_eq!(ppu.ppu_status.read(), 0, "{:?}", region);
+
+            // Nothing brings them back before the next frame
+            ppu.tick((DOTS_PER_SCANLINE - FLAG_UPDATE_DOT) as u32);
+            assert_eq!((ppu.scanline(), ppu.dot()), (0, 0));
+            assert_eq!(ppu.ppu_status.read(), 0, "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn ppu_frame_length_follows_region() {
+        for region in [Region::Ntsc, Region::Pal] {
+            let mut ppu = PPU::with_region(Bus::new(), region);
+            let scanlines = region.scanlines_per_frame();
+
+            ppu.tick((scanlines - 1) as u32 * DOTS_PER_SCANLINE as u32);
+            assert_eq!(ppu.scanline(), scanlines - 1, "{:?}", region);
+
+            ppu.tick(DOTS_PER_SCANLINE as u32);
+            assert_eq!((ppu.scanline(), ppu.dot()), (0, 0), "{:?}", region);
+        }
+    }
+
+    #[test]
+    fn ppu_cpu_cycles_per_frame_cover_the_frame() {
+        for region in [Region::Ntsc, Region::Pal] {
+            let mut ppu = PPU::with_region(Bus::new(), region);
+            let frame_dots = DOTS_PER_SCANLINE as u32 * region.scanlines_per_frame() as u32;
+
+            ppu.tick_cpu_cycles(region.cpu_cycles_per_frame() as u32);
+
+            // Both clock ratios leave the frame a fraction of a CPU cycle short
+            let dots = ppu.scanline() as u32 * DOTS_PER_SCANLINE as u32 + ppu.dot() as u32;
+            assert!(frame_dots - dots < 4, "{:?} stopped at {}", region, dots);
+            assert_eq!(ppu.frame_count(), 1);
+        }
+    }
+
+    #[test]
+    fn ppu_pal_ticks_sixteen_dots_every_five_cpu_cycles() {
+        let mut ppu = PPU::with_region(Bus::new(), Region::Pal);
+
+        for _ in 0..5 {
+            ppu.tick_cpu_cycles(1);
+        }
+
+        assert_eq!(ppu.dot(), 16);
+    }
+
+    #[test]
+    fn ppu_enabling_nmi_during_vblank_raises_it() {
+        let mut ppu = setup_ppu();
+        ppu.tick(DOTS_TO_VBLANK);
+        assert!(!ppu.take_nmi());
+
+        ppu.write_to_ppu_ctrl(0x80);
+
+        assert!(ppu.take_nmi());
+    }
+
+    #[test]
+    fn ppu_sprite_zero_hit_is_set_at_first_overlapping_pixel() {
+        let mut ppu = setup_ppu_with_sprite_zero(100, 49);
+
+        // Up to and including pixel 99 of scanline 50
+        ppu.tick(50 * DOTS_PER_SCANLINE as u32 + 100);
+        assert_eq!(ppu.ppu_status.read() & 0x40, 0);
+
+        ppu.tick(1);
+        assert_eq!(ppu.ppu_status.read() & 0x40, 0x40);
+    }
+
+    #[test]
+    fn ppu_sprite_zero_hit_respects_left_column_masks() {
+        let mut ppu = setup_ppu_with_sprite_zero(0, 49);
+        ppu.write_to_ppu_mask(0b0001_1000);
+
+        ppu.render_scanline(50);
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0);
+
+        ppu.write_to_ppu_mask(0b0001_1110);
+        ppu.render_scanline(50);
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0x40);
+    }
+
+    #[test]
+    fn ppu_sprite_zero_hit_outside_left_columns_ignores_masks() {
+        let mut ppu = setup_ppu_with_sprite_zero(4, 49);
+        ppu.write_to_ppu_mask(0b0001_1000);
+
+        ppu.render_scanline(50);
+
+        assert_eq!(ppu.read_from_ppu_status() & 0x40, 0x40);
+    }
 }
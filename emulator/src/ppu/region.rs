@@ -0,0 +1,68 @@
+use crate::cartridge::common::enums::timing_mode::TimingMode;
+
+// Frame timing of the console, see https://www.nesdev.org/wiki/Cycle_reference_chart
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+pub const DOTS_PER_SCANLINE: u16 = 341;
+
+impl Region {
+    pub const fn scanlines_per_frame(&self) -> u16 {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal => 312,
+        }
+    }
+
+    // PPU dots per CPU cycles as a fraction, PAL runs 3.2 dots per CPU cycle
+    pub const fn ppu_dots_per_cpu_cycles(&self) -> (u32, u32) {
+        match self {
+            Region::Ntsc => (3, 1),
+            Region::Pal => (16, 5),
+        }
+    }
+
+    // Rounded down, the leftover dots carry over into the next frame
+    pub const fn cpu_cycles_per_frame(&self) -> u64 {
+        let (dots, cpu_cycles) = self.ppu_dots_per_cpu_cycles();
+        DOTS_PER_SCANLINE as u64 * self.scanlines_per_frame() as u64 * cpu_cycles as u64
+            / dots as u64
+    }
+}
+
+impl From<TimingMode> for Region {
+    // Dendy shares the PAL frame length, its 3:1 clock ratio is not modelled
+    fn from(timing_mode: TimingMode) -> Region {
+        match timing_mode {
+            TimingMode::Ntsc | TimingMode::MultipleRegion => Region::Ntsc,
+            TimingMode::Pal | TimingMode::Dendy => Region::Pal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanlines_per_frame() {
+        assert_eq!(Region::Ntsc.scanlines_per_frame(), 262);
+        assert_eq!(Region::Pal.scanlines_per_frame(), 312);
+    }
+
+    #[test]
+    fn test_cpu_cycles_per_frame() {
+        assert_eq!(Region::Ntsc.cpu_cycles_per_frame(), 29780);
+        assert_eq!(Region::Pal.cpu_cycles_per_frame(), 33247);
+    }
+
+    #[test]
+    fn test_from_timing_mode() {
+        assert_eq!(Region::from(TimingMode::Ntsc), Region::Ntsc);
+        assert_eq!(Region::from(TimingMode::MultipleRegion), Region::Ntsc);
+        assert_eq!(Region::from(TimingMode::Pal), Region::Pal);
+    }
+}
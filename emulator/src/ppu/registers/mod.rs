@@ -1,3 +1,5 @@
 pub mod ppu_addr;
 pub mod ppu_ctrl;
 pub mod ppu_data;
+pub mod ppu_mask;
+pub mod ppu_status;
@@ -1,5 +1,8 @@
 use log::debug;
 
+// The PPU only decodes 14 address lines, everything above 0x3FFF mirrors down
+const PPU_ADDRESS_MASK: u16 = 0x3FFF;
+
 pub struct PPUAddr {
     pub high_addr: u8,
     pub low_addr: u8,
@@ -14,7 +17,7 @@ impl PPUAddr {
     }
 
     pub fn read(&self) -> u16 {
-        ((self.high_addr as u16) << 8) | self.low_addr as u16
+        (((self.high_addr as u16) << 8) | self.low_addr as u16) & PPU_ADDRESS_MASK
     }
 
     pub fn write(&mut self, data: u8, byte_flag: bool) {
@@ -25,30 +28,22 @@ impl PPUAddr {
             self.low_addr = data;
         }
 
-        if self.read() > 0x3FFF {
-            self.mirror_address()
-        }
+        self.mirror_address();
 
         debug!("Current PPUAddr: {:#06X}", self.read());
     }
 
     pub fn increment(&mut self, increment: u8) {
-        let current_low = self.low_addr;
-        self.low_addr = self.low_addr.wrapping_add(increment);
-
-        if current_low > self.low_addr {
-            // That means we have overflowed
-            self.high_addr = self.high_addr.wrapping_add(1);
-        }
+        self.set(self.read().wrapping_add(increment as u16));
+    }
 
-        if self.read() > 0x3FFF {
-            self.mirror_address();
-        }
+    fn set(&mut self, address: u16) {
+        let address = address & PPU_ADDRESS_MASK;
+        self.high_addr = (address >> 8) as u8;
+        self.low_addr = (address & 0xFF) as u8;
     }
 
     fn mirror_address(&mut self) {
-        let mirrored_data = self.read() & 0x3FFF;
-        self.high_addr = (mirrored_data >> 8) as u8;
-        self.low_addr = (mirrored_data & 0xFF) as u8;
+        self.set(self.read());
     }
 }
@@ -28,6 +28,34 @@ impl PPUCtrl {
         }
     }
 
+    pub fn get_base_nametable_address(&self) -> u16 {
+        0x2000 + ((self.bits() & 0b11) as u16) * 0x400
+    }
+
+    pub fn get_sprite_pattern_address(&self) -> u16 {
+        if self.contains(PPUCtrl::PATTERN_SPRITE) {
+            0x1000
+        } else {
+            0x0000
+        }
+    }
+
+    pub fn get_sprite_height(&self) -> usize {
+        if self.contains(PPUCtrl::SPRITE_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+
+    pub fn get_background_pattern_address(&self) -> u16 {
+        if self.contains(PPUCtrl::PATTERN_BACKGROUND) {
+            0x1000
+        } else {
+            0x0000
+        }
+    }
+
     pub fn write(&mut self, data: u8) {
         *self = PPUCtrl::from_bits_truncate(data);
     }
@@ -1,5 +1,9 @@
 use crate::bus::{Bus, BusLike};
 
+// PPU address space behind $2007, laid out by the console:
+// 0x0000-0x1FFF: pattern tables in cartridge CHR ROM or RAM, through the mapper
+// 0x2000-0x2FFF: nametables in VRAM, mirrored at 0x3000-0x3EFF
+// 0x3F00-0x3FFF: palette RAM
 pub struct PPUData {
     ppu_bus: Bus,
 }
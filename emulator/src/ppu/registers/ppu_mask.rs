@@ -0,0 +1,58 @@
+use bitflags::bitflags;
+
+bitflags! {
+    // Documentation taken from https://www.nesdev.org/wiki/PPU_registers
+
+    pub struct PPUMask: u8 {
+        const GRAYSCALE = 0b00000001;           // 0: normal color, 1: produce a grayscale display
+        const SHOW_BACKGROUND_LEFT = 0b00000010; // 1: Show background in leftmost 8 pixels of screen, 0: Hide
+        const SHOW_SPRITES_LEFT = 0b00000100;   // 1: Show sprites in leftmost 8 pixels of screen, 0: Hide
+        const SHOW_BACKGROUND = 0b00001000;     // 1: Show background
+        const SHOW_SPRITES = 0b00010000;        // 1: Show sprites
+        const EMPHASIZE_RED = 0b00100000;       // Emphasize red (green on PAL/Dendy)
+        const EMPHASIZE_GREEN = 0b01000000;     // Emphasize green (red on PAL/Dendy)
+        const EMPHASIZE_BLUE = 0b10000000;      // Emphasize blue
+    }
+}
+
+impl PPUMask {
+    pub fn new() -> PPUMask {
+        PPUMask::from_bits_truncate(0)
+    }
+
+    pub fn write(&mut self, data: u8) {
+        *self = PPUMask::from_bits_truncate(data);
+    }
+
+    pub fn grayscale(&self) -> bool {
+        self.contains(PPUMask::GRAYSCALE)
+    }
+
+    pub fn show_background_left(&self) -> bool {
+        self.contains(PPUMask::SHOW_BACKGROUND_LEFT)
+    }
+
+    pub fn show_sprites_left(&self) -> bool {
+        self.contains(PPUMask::SHOW_SPRITES_LEFT)
+    }
+
+    pub fn show_background(&self) -> bool {
+        self.contains(PPUMask::SHOW_BACKGROUND)
+    }
+
+    pub fn show_sprites(&self) -> bool {
+        self.contains(PPUMask::SHOW_SPRITES)
+    }
+
+    pub fn emphasize_red(&self) -> bool {
+        self.contains(PPUMask::EMPHASIZE_RED)
+    }
+
+    pub fn emphasize_green(&self) -> bool {
+        self.contains(PPUMask::EMPHASIZE_GREEN)
+    }
+
+    pub fn emphasize_blue(&self) -> bool {
+        self.contains(PPUMask::EMPHASIZE_BLUE)
+    }
+}
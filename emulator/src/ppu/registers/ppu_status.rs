@@ -0,0 +1,21 @@
+use bitflags::bitflags;
+
+bitflags! {
+    // Documentation taken from https://www.nesdev.org/wiki/PPU_registers
+
+    pub struct PPUStatus: u8 {
+        const SPRITE_OVERFLOW = 0b00100000;     // More than eight sprites on a scanline
+        const SPRITE_ZERO_HIT = 0b01000000;     // Opaque pixel of sprite 0 overlaps opaque background
+        const VBLANK = 0b10000000;              // 0: not in vblank; 1: in vblank
+    }
+}
+
+impl PPUStatus {
+    pub fn new() -> PPUStatus {
+        PPUStatus::from_bits_truncate(0)
+    }
+
+    pub fn read(&self) -> u8 {
+        self.bits()
+    }
+}
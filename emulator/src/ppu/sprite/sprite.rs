@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+// One OAM entry, see https://www.nesdev.org/wiki/PPU_OAM
+
+pub const SPRITE_SIZE_IN_BYTES: usize = 4;
+
+const PALETTE_MASK: u8 = 0b0000_0011;
+const PRIORITY_FLAG: u8 = 0b0010_0000;
+const FLIP_HORIZONTAL_FLAG: u8 = 0b0100_0000;
+const FLIP_VERTICAL_FLAG: u8 = 0b1000_0000;
+// Sprites use the last four of the eight palettes
+const FIRST_SPRITE_PALETTE: u8 = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Sprite {
+    pub y: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub x: u8,
+}
+
+impl Sprite {
+    pub fn from_bytes(bytes: &[u8]) -> Sprite {
+        Sprite {
+            y: bytes[0],
+            tile: bytes[1],
+            attributes: bytes[2],
+            x: bytes[3],
+        }
+    }
+
+    // OAM keeps the Y coordinate minus one, sprites are drawn a scanline later
+    pub fn top(&self) -> usize {
+        self.y as usize + 1
+    }
+
+    pub fn left(&self) -> usize {
+        self.x as usize
+    }
+
+    pub fn palette(&self) -> u8 {
+        FIRST_SPRITE_PALETTE + (self.attributes & PALETTE_MASK)
+    }
+
+    pub fn behind_background(&self) -> bool {
+        self.attributes & PRIORITY_FLAG != 0
+    }
+
+    pub fn flip_horizontal(&self) -> bool {
+        self.attributes & FLIP_HORIZONTAL_FLAG != 0
+    }
+
+    pub fn flip_vertical(&self) -> bool {
+        self.attributes & FLIP_VERTICAL_FLAG != 0
+    }
+}
@@ -1,74 +1,77 @@
 use crate::addressing::Addressable;
 use crate::mirroring::Mirroring;
+use crate::save_state::{self, SaveStateError};
 use log::{debug, info};
+use serde::{Deserialize, Serialize};
 #[allow(unused_imports)]
 use std::cmp::PartialEq;
 use std::fmt::Debug;
 
+const NAMETABLE_SIZE: u16 = 0x400;
+const NAMETABLES_MIRROR_MASK: u16 = 0x0FFF;
+
+#[derive(Serialize, Deserialize)]
 pub struct VRAM {
-    nametable_1: [u8; 0x400],
-    nametable_2: [u8; 0x400],
+    // Physical nametables back to back, 2KB on the console, 4KB with four-screen cartridges
+    nametables: Vec<u8>,
     mirroring: Mirroring,
 }
 
+impl Default for VRAM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VRAM {
     pub fn new() -> VRAM {
+        VRAM::with_mirroring(Mirroring::Horizontal)
+    }
+
+    pub fn with_mirroring(mirroring: Mirroring) -> VRAM {
         info!("VRAM is initializing");
         VRAM {
-            nametable_1: [0; 0x400],
-            nametable_2: [0; 0x400],
-            mirroring: Mirroring::Horizontal,
+            nametables: vec![0; VRAM::size_for(&mirroring)],
+            mirroring,
         }
     }
 
-    fn read_from_nametable_1(&self, addr: u16) -> u8 {
-        debug!("Nametable 1 read at relative address {:#06X}", addr);
-        self.nametable_1[addr as usize]
+    // Four-screen cartridges bring the extra 2KB of RAM themselves
+    fn size_for(mirroring: &Mirroring) -> usize {
+        let nametables = match mirroring {
+            Mirroring::FourScreen => 4,
+            _ => 2,
+        };
+        nametables * NAMETABLE_SIZE as usize
     }
 
-    fn read_from_nametable_2(&self, addr: u16) -> u8 {
-        debug!("Nametable 2 read at relative address {:#06X}", addr);
-        self.nametable_2[addr as usize]
-    }
+    // Physical nametable behind each of the four logical ones, see https://www.nesdev.org/wiki/Mirroring
+    fn physical_nametable(&self, addr: u16) -> u16 {
+        if addr > 0x0FFF {
+            panic!("Invalid VRAM address: {:#06X}", addr);
+        }
 
-    fn read_from_nametable(&self, addr: u16) -> u8 {
-        debug!(
-            "Attempt to read from VRAM at address {:#06X}",
-            addr + 0x2000
-        );
-        if self.mirroring == Mirroring::Horizontal {
-            match addr {
-                0x0000..=0x03FF => self.read_from_nametable_1(addr),
-                0x0400..=0x07FF => self.read_from_nametable_1(addr - 0x400),
-                0x0800..=0x0BFF => self.read_from_nametable_2(addr - 0x800),
-                0x0C00..=0x0FFF => self.read_from_nametable_2(addr - 0xC00),
-                _ => panic!("Invalid VRAM address: {:#06X}", addr),
-            }
-        } else {
-            match addr {
-                0x0000..=0x03FF => self.read_from_nametable_1(addr),
-                0x0400..=0x07FF => self.read_from_nametable_2(addr - 0x400),
-                0x0800..=0x0BFF => self.read_from_nametable_1(addr - 0x800),
-                0x0C00..=0x0FFF => self.read_from_nametable_2(addr - 0xC00),
-                _ => panic!("Invalid VRAM address: {:#06X}", addr),
-            }
+        let logical = addr / NAMETABLE_SIZE;
+        match self.mirroring {
+            Mirroring::Horizontal => logical / 2,
+            Mirroring::Vertical => logical % 2,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::FourScreen => logical,
         }
     }
 
-    fn write_to_nametable_1(&mut self, addr: u16, value: u8) {
-        debug!(
-            "Nametable 1 write at relative address {:#06X} with data {:#04X}",
-            addr, value
-        );
-        self.nametable_1[addr as usize] = value;
+    fn physical_address(&self, addr: u16) -> usize {
+        let nametable = self.physical_nametable(addr);
+        (nametable * NAMETABLE_SIZE + addr % NAMETABLE_SIZE) as usize
     }
 
-    fn write_to_nametable_2(&mut self, addr: u16, value: u8) {
+    fn read_from_nametable(&self, addr: u16) -> u8 {
         debug!(
-            "Nametable 2 write at relative address {:#06X} with data {:#04X}",
-            addr, value
+            "Attempt to read from VRAM at address {:#06X}",
+            addr + 0x2000
         );
-        self.nametable_2[addr as usize] = value;
+        self.nametables[self.physical_address(addr)]
     }
 
     fn write_to_nametable(&mut self, addr: u16, value: u8) {
@@ -77,37 +80,38 @@ impl VRAM {
             addr + 0x2000,
             value
         );
-        if self.mirroring == Mirroring::Horizontal {
-            match addr {
-                0x0000..=0x03FF => self.write_to_nametable_1(addr, value),
-                0x0400..=0x07FF => self.write_to_nametable_1(addr - 0x400, value),
-                0x0800..=0x0BFF => self.write_to_nametable_2(addr - 0x800, value),
-                0x0C00..=0x0FFF => self.write_to_nametable_2(addr - 0xC00, value),
-                _ => panic!("Invalid VRAM address: {:#06X}", addr),
-            }
-        } else {
-            match addr {
-                0x0000..=0x03FF => self.write_to_nametable_1(addr, value),
-                0x0400..=0x07FF => self.write_to_nametable_2(addr - 0x400, value),
-                0x0800..=0x0BFF => self.write_to_nametable_1(addr - 0x800, value),
-                0x0C00..=0x0FFF => self.write_to_nametable_2(addr - 0xC00, value),
-                _ => panic!("Invalid VRAM address: {:#06X}", addr),
-            }
-        }
+        let physical_address = self.physical_address(addr);
+        self.nametables[physical_address] = value;
     }
 
+    // Switching to or from four-screen resizes the RAM, the first 2KB are kept
     pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.nametables.resize(VRAM::size_for(&mirroring), 0);
         self.mirroring = mirroring;
     }
+
+    pub fn size(&self) -> usize {
+        self.nametables.len()
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        save_state::encode(self)
+    }
+
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        *self = save_state::decode(bytes)?;
+        Ok(())
+    }
 }
 
 impl Addressable for VRAM {
+    // 0x3000-0x3EFF mirrors the nametables at 0x2000-0x2EFF
     fn read(&mut self, addr: u16) -> u8 {
-        self.read_from_nametable(addr - 0x2000)
+        self.read_from_nametable((addr - 0x2000) & NAMETABLES_MIRROR_MASK)
     }
 
     fn write(&mut self, addr: u16, data: u8) {
-        self.write_to_nametable(addr - 0x2000, data);
+        self.write_to_nametable((addr - 0x2000) & NAMETABLES_MIRROR_MASK, data);
     }
 }
 
@@ -125,16 +129,15 @@ mod tests {
     #[test]
     fn vram_initializes_correctly() {
         let vram = VRAM::new();
-        assert_eq!(vram.nametable_1, [0; 0x400]);
-        assert_eq!(vram.nametable_2, [0; 0x400]);
+        assert_eq!(vram.nametables, vec![0; 0x800]);
         assert_eq!(vram.mirroring, Mirroring::Horizontal);
     }
 
     #[test]
     fn read_from_nametable_1_within_bounds() {
         let vram = VRAM::new();
-        assert_eq!(vram.read_from_nametable_1(0x0000), 0);
-        assert_eq!(vram.read_from_nametable_1(0x03FF), 0);
+        assert_eq!(vram.read_from_nametable(0x0000), 0);
+        assert_eq!(vram.read_from_nametable(0x03FF), 0);
     }
 
     #[test]
@@ -144,11 +147,20 @@ mod tests {
         vram.read_from_nametable(0x1000);
     }
 
+    #[test]
+    fn upper_mirror_maps_onto_nametables() {
+        let mut vram = VRAM::new();
+
+        vram.write(0x3123, 0x42);
+
+        assert_eq!(vram.read(0x2123), 0x42);
+    }
+
     #[test]
     fn write_to_nametable_1_within_bounds() {
         let mut vram = VRAM::new();
-        vram.write_to_nametable_1(0x0000, 42);
-        assert_eq!(vram.read_from_nametable_1(0x0000), 42);
+        vram.write_to_nametable(0x0000, 42);
+        assert_eq!(vram.nametables[0x0000], 42);
     }
 
     #[test]
@@ -169,4 +181,98 @@ mod tests {
         vram.write_to_nametable(0x0400, 84);
         assert_eq!(vram.read_from_nametable(0x0400), 84);
     }
+
+    // Writes a distinct value through each logical nametable and reads back the physical banks
+    fn physical_banks(mirroring: Mirroring) -> [u8; 4] {
+        let mut vram = VRAM::with_mirroring(mirroring);
+        for logical in 0..4u16 {
+            vram.write_to_nametable(logical * 0x400 + 0x10, logical as u8 + 1);
+        }
+        let mut banks = [0; 4];
+        for (bank, value) in banks.iter_mut().enumerate() {
+            *value = vram
+                .nametables
+                .get(bank * 0x400 + 0x10)
+                .copied()
+                .unwrap_or(0);
+        }
+        banks
+    }
+
+    #[test]
+    fn horizontal_mirroring_maps_nametables() {
+        assert_eq!(physical_banks(Mirroring::Horizontal), [2, 4, 0, 0]);
+    }
+
+    #[test]
+    fn vertical_mirroring_maps_nametables() {
+        assert_eq!(physical_banks(Mirroring::Vertical), [3, 4, 0, 0]);
+    }
+
+    #[test]
+    fn single_screen_mirroring_maps_nametables() {
+        assert_eq!(physical_banks(Mirroring::SingleScreenLower), [4, 0, 0, 0]);
+        assert_eq!(physical_banks(Mirroring::SingleScreenUpper), [0, 4, 0, 0]);
+    }
+
+    #[test]
+    fn four_screen_mirroring_maps_nametables() {
+        assert_eq!(physical_banks(Mirroring::FourScreen), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn single_screen_mirroring_reads_one_bank_everywhere() {
+        let mut vram = VRAM::new();
+        vram.set_mirroring(Mirroring::SingleScreenUpper);
+        vram.write_to_nametable(0x0123, 42);
+
+        for logical in 0..4u16 {
+            assert_eq!(vram.read_from_nametable(logical * 0x400 + 0x123), 42);
+        }
+    }
+
+    #[test]
+    fn vram_size_follows_mirroring() {
+        let mut vram = VRAM::new();
+        assert_eq!(vram.size(), 0x800);
+
+        vram.set_mirroring(Mirroring::FourScreen);
+        assert_eq!(vram.size(), 0x1000);
+
+        vram.set_mirroring(Mirroring::Vertical);
+        assert_eq!(vram.size(), 0x800);
+    }
+
+    #[test]
+    fn four_screen_provides_four_independent_nametables() {
+        let mut vram = VRAM::with_mirroring(Mirroring::FourScreen);
+        for logical in 0..4u16 {
+            for offset in [0x000, 0x1FF, 0x3FF] {
+                vram.write_to_nametable(logical * 0x400 + offset, logical as u8 * 0x10 + 1);
+            }
+        }
+
+        for logical in 0..4u16 {
+            for offset in [0x000, 0x1FF, 0x3FF] {
+                assert_eq!(
+                    vram.read_from_nametable(logical * 0x400 + offset),
+                    logical as u8 * 0x10 + 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn leaving_four_screen_keeps_first_two_nametables() {
+        let mut vram = VRAM::with_mirroring(Mirroring::FourScreen);
+        vram.write_to_nametable(0x0005, 1);
+        vram.write_to_nametable(0x0405, 2);
+        vram.write_to_nametable(0x0805, 3);
+
+        vram.set_mirroring(Mirroring::Vertical);
+
+        assert_eq!(vram.read_from_nametable(0x0005), 1);
+        assert_eq!(vram.read_from_nametable(0x0405), 2);
+        assert_eq!(vram.read_from_nametable(0x0805), 1);
+    }
 }
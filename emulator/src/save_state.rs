@@ -0,0 +1,17 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SaveStateError {
+    #[error("malformed save state: {0}")]
+    Malformed(#[from] bincode::Error),
+}
+
+// Save states are bincode blobs, only meant to be loaded by the same emulator version
+pub fn encode<T: Serialize>(state: &T) -> Vec<u8> {
+    bincode::serialize(state).expect("save state types are plain data")
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SaveStateError> {
+    Ok(bincode::deserialize(bytes)?)
+}
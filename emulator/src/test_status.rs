@@ -0,0 +1,57 @@
+// Result protocol used by blargg's test ROMs, see https://github.com/christopherpow/nes-test-roms
+// 0x6000 holds the status, 0x6001-0x6003 a signature and 0x6004 a null-terminated message
+pub const TEST_STATUS_ADDRESS: u16 = 0x6000;
+pub const TEST_SIGNATURE_ADDRESS: u16 = 0x6001;
+pub const TEST_MESSAGE_ADDRESS: u16 = 0x6004;
+pub const TEST_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_RESET_REQUIRED: u8 = 0x81;
+const STATUS_PASSED: u8 = 0x00;
+// The message can't outgrow PRG RAM
+const TEST_MESSAGE_END_ADDRESS: u16 = 0x7FFF;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestStatus {
+    // Signature not written yet, the ROM has not started reporting
+    NotStarted,
+    Running,
+    // The ROM asks for the reset button to be pressed in about 100ms
+    ResetRequired,
+    Passed(String),
+    Failed(u8, String),
+}
+
+impl TestStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, TestStatus::Passed(_) | TestStatus::Failed(_, _))
+    }
+
+    // Reads the protocol through any CPU side view of memory
+    pub fn read(mut read: impl FnMut(u16) -> u8) -> TestStatus {
+        let signature = [
+            read(TEST_SIGNATURE_ADDRESS),
+            read(TEST_SIGNATURE_ADDRESS + 1),
+            read(TEST_SIGNATURE_ADDRESS + 2),
+        ];
+        if signature != TEST_SIGNATURE {
+            return TestStatus::NotStarted;
+        }
+
+        match read(TEST_STATUS_ADDRESS) {
+            STATUS_RUNNING => TestStatus::Running,
+            STATUS_RESET_REQUIRED => TestStatus::ResetRequired,
+            status => {
+                let message = (TEST_MESSAGE_ADDRESS..=TEST_MESSAGE_END_ADDRESS)
+                    .map(&mut read)
+                    .take_while(|&byte| byte != 0)
+                    .map(char::from)
+                    .collect();
+                match status {
+                    STATUS_PASSED => TestStatus::Passed(message),
+                    code => TestStatus::Failed(code, message),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Installs a global logger and changes the max level, so it runs in its own test binary
+use emulator::bus::{Bus, BusLike};
+use emulator::cpu::cpu::CPU;
+use emulator::logging::nes_logging::CPU_LOG_TARGET;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::Mutex;
+
+const LDA_IMMEDIATE_OPCODE: u8 = 0xA9;
+
+// Collects CPU log messages so the test can check what the CPU reports
+struct CaptureLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == CPU_LOG_TARGET
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.messages
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger {
+    messages: Mutex::new(Vec::new()),
+};
+
+#[test]
+fn test_cpu_decode_logs_only_when_enabled() {
+    log::set_logger(&LOGGER).unwrap();
+    let mut bus = Bus::new_cpu();
+    bus.write(0x0000, LDA_IMMEDIATE_OPCODE);
+    bus.write(0x0002, LDA_IMMEDIATE_OPCODE);
+    let mut cpu = CPU::new(bus);
+
+    log::set_max_level(LevelFilter::Off);
+    cpu.step_instruction().unwrap();
+    assert!(LOGGER.messages.lock().unwrap().is_empty());
+
+    log::set_max_level(LevelFilter::Debug);
+    cpu.step_instruction().unwrap();
+    assert_eq!(
+        *LOGGER.messages.lock().unwrap(),
+        vec!["Operation code: 0xA9".to_string()]
+    );
+}
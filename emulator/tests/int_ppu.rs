@@ -13,16 +13,16 @@ mod tests {
         );
 
         let mut ppu = emulator::ppu::ppu::PPU::new(ppu_bus);
-        ppu.write(*&0x2006, 0x23);
-        ppu.write(*&0x2006, 0x06);
-        ppu.write(*&0x2007, 0x66);
+        ppu.write(0x2006, 0x23);
+        ppu.write(0x2006, 0x06);
+        ppu.write(0x2007, 0x66);
 
-        ppu.write(*&0x2006, 0x23);
-        ppu.write(*&0x2006, 0x06);
+        ppu.write(0x2006, 0x23);
+        ppu.write(0x2006, 0x06);
 
-        let vram_data = ppu.read(*&0x2007);
+        let vram_data = ppu.read(0x2007);
         assert_eq!(vram_data, 0x00);
-        let vram_data_valid = ppu.read(*&0x2007);
+        let vram_data_valid = ppu.read(0x2007);
         assert_eq!(vram_data_valid, 0x66);
     }
 
@@ -37,16 +37,15 @@ mod tests {
         );
 
         let mut ppu = emulator::ppu::ppu::PPU::new(ppu_bus);
-        ppu.write(*&0x2006, 0x3F);
-        ppu.write(*&0x2006, 0x2C);
-        ppu.write(*&0x2007, 0b00101001);
+        ppu.write(0x2006, 0x3F);
+        ppu.write(0x2006, 0x2C);
+        ppu.write(0x2007, 0b00101001);
 
-        ppu.write(*&0x2006, 0x3F);
-        ppu.write(*&0x2006, 0x2C);
+        ppu.write(0x2006, 0x3F);
+        ppu.write(0x2006, 0x2C);
 
-        let color_index = ppu.read(*&0x2007);
-        assert_eq!(color_index, 0x00);
-        let color_index_valid = ppu.read(*&0x2007);
-        assert_eq!(color_index_valid, 0b00101001);
+        // Palette reads are not delayed by the read buffer
+        let color_index = ppu.read(0x2007);
+        assert_eq!(color_index, 0b00101001);
     }
 }